@@ -2,11 +2,11 @@ use crate::opcode::OpCode;
 use crate::Line;
 use derive_more::Debug;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[debug("{:04}\t{:?}", _1, _0)]
 pub struct Code(pub OpCode, pub Line);
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 #[debug("Chunk {:p} {:#?}", self, self.codes)]
 pub struct Chunk {
     pub codes: Vec<Code>,
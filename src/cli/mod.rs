@@ -1,70 +1,126 @@
+use crate::error::CompileError;
+use crate::error::RuntimeError;
+use crate::vm::policy::EnvPolicy;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
 use clap::Parser;
-use std::path::PathBuf;
-
-use std::fs::read_to_string;
-// use std::io::{stdin, stdout, Write};
-
 use rustyline::error::ReadlineError;
-use rustyline::{DefaultEditor, Result};
-
-use std::process::exit;
+use rustyline::DefaultEditor;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-pub struct CLI {}
+/// Exit codes from sysexits.h: the script doesn't compile, it failed while running, or it couldn't
+/// be read.
+const EXIT_COMPILE_ERROR: u8 = 65;
+const EXIT_RUNTIME_ERROR: u8 = 70;
+const EXIT_IO_ERROR: u8 = 74;
 
+/// Runs a script, a one-liner given with `-e`, or starts a REPL when there is neither.
 #[derive(Parser, Debug)]
+#[command(name = "lox", version, about)]
 struct Args {
-    #[arg(short, long)]
-    path: Option<PathBuf>,
+    /// Code to run instead of a script.
+    #[arg(short, long, value_name = "CODE")]
+    eval: Option<String>,
+    /// Prints the compiled bytecode before running it.
     #[arg(short, long)]
     debug: bool,
+    /// Starts without the prelude, the library functions written in Lox.
+    #[arg(long)]
+    no_prelude: bool,
+    /// Script to run, `-` reads it from the standard input.
+    script: Option<PathBuf>,
+    /// Arguments for the script, available to it as `args`.
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    arguments: Vec<String>,
 }
 
-fn repl(debug: bool) -> Result<()> {
-    let mut rl = DefaultEditor::new()?;
-    let mut vm = VirtualMachine::initialize();
+/// Entry point of the `lox` binary.
+pub fn run() -> ExitCode {
+    let args = Args::parse();
+    let mut vm = match args.no_prelude {
+        true => VirtualMachine::without_prelude(),
+        false => VirtualMachine::initialize(),
+    };
+    // NOTE: The scripts run by the user from a shell can read the environment of that shell.
+    vm.set_env_policy(EnvPolicy::default().allow_all());
 
-    loop {
-        match rl.readline(">> ") {
-            Ok(line) => vm.interpret(line.trim(), debug),
-            Err(ReadlineError::Interrupted) => {
-                println!("Proccess terminated.");
-                break;
-            }
-            Err(err) => {
-                eprintln!("Error {err}");
-                break;
-            }
-        };
+    let result = match (args.eval, args.script) {
+        // NOTE: With `-e` there is no script, the first positional is already an argument.
+        (Some(code), script) => {
+            let script = script.map(|script| script.to_string_lossy().into_owned());
+            vm.set_arguments(script.into_iter().chain(args.arguments).collect());
+            crate::interpret(&code, args.debug, &mut vm)
+        }
+        (None, Some(script)) if script.as_os_str() == "-" => {
+            vm.set_arguments(args.arguments);
+            read_stdin().and_then(|source| crate::interpret(&source, args.debug, &mut vm))
+        }
+        (None, Some(script)) => {
+            vm.set_arguments(args.arguments);
+            crate::interpret_file(&script, args.debug, &mut vm)
+        }
+        (None, None) => return repl(&mut vm, args.debug),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => report(&error),
     }
+}
+
+fn read_stdin() -> Result<String> {
+    let mut source = String::new();
+    std::io::stdin().read_to_string(&mut source)?;
 
-    Ok(())
+    Ok(source)
 }
 
-fn run_file(path: PathBuf, debug: bool) -> Result<()> {
-    let Ok(source) = read_to_string(&path) else {
-        eprintln!("Could not open file {}.", path.display());
-        exit(74);
+/// Each line is compiled and run on its own, the globals are kept between them.
+fn repl(vm: &mut VirtualMachine, debug: bool) -> ExitCode {
+    let Ok(mut editor) = DefaultEditor::new() else {
+        eprintln!("Could not start the REPL.");
+        return ExitCode::from(EXIT_IO_ERROR);
     };
 
-    let mut vm = VirtualMachine::initialize();
-    match vm.interpret(&source, debug) {
-        InterpretResult::CompileError => exit(65),
-        InterpretResult::RuntimeError => exit(70),
-        InterpretResult::Ok => (),
-    }
+    loop {
+        match editor.readline(">> ") {
+            Ok(line) => {
+                let _ = editor.add_history_entry(line.as_str());
 
-    Ok(())
+                let Err(error) = crate::interpret(line.trim(), debug, vm) else {
+                    continue;
+                };
+
+                let code = report(&error);
+                if let Some(RuntimeError::Exit(_)) = error.downcast_ref() {
+                    return code;
+                }
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => return ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("Could not read line: {error}");
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        }
+    }
 }
 
-// use crate::vm::InterpretResult;
-// use crate::vm::VirtualMachine;
-fn main() -> Result<()> {
-    let args = Args::parse();
+/// Prints the error and returns the exit code for it. The compiler has already reported its own
+/// errors.
+fn report(error: &anyhow::Error) -> ExitCode {
+    if let Some(RuntimeError::Exit(code)) = error.downcast_ref() {
+        return ExitCode::from(*code);
+    }
 
-    match args.path {
-        Some(path) => run_file(path, args.debug),
-        None => repl(args.debug),
-    };
+    if error.downcast_ref::<CompileError>().is_some() {
+        return ExitCode::from(EXIT_COMPILE_ERROR);
+    }
 
-    Ok(())
+    eprintln!("{error}");
+    match error.downcast_ref::<RuntimeError>() {
+        Some(_) => ExitCode::from(EXIT_RUNTIME_ERROR),
+        None => ExitCode::from(EXIT_IO_ERROR),
+    }
 }
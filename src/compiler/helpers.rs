@@ -8,44 +8,85 @@ use std::collections::HashMap;
 
 pub fn create_rules() -> HashMap<TokenKind, ParseRule> {
     HashMap::from([
-        (TokenKind::LeftParen, ParseRule::new(Some(ParseFn::Grouping), None, Precedence::None)),
+        (
+            TokenKind::LeftParen,
+            ParseRule::new(Some(ParseFn::Grouping), Some(ParseFn::Call), Precedence::Call),
+        ),
         (TokenKind::RightParen, ParseRule::new(None, None, Precedence::None)),
-        (TokenKind::LeftBrace, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::LeftBrace, ParseRule::new(Some(ParseFn::Map), None, Precedence::None)),
         (TokenKind::RightBrace, ParseRule::new(None, None, Precedence::None)),
+        (
+            TokenKind::LeftBracket,
+            ParseRule::new(Some(ParseFn::List), Some(ParseFn::Index), Precedence::Call),
+        ),
+        (TokenKind::RightBracket, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Colon, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::Comma, ParseRule::new(None, None, Precedence::None)),
-        (TokenKind::Dot, ParseRule::new(None, None, Precedence::None)),
-        (TokenKind::Minus, ParseRule::new(Some(ParseFn::Unary), Some(ParseFn::Binary), Precedence::Term)),
+        (TokenKind::Dot, ParseRule::new(None, Some(ParseFn::Dot), Precedence::Call)),
+        (TokenKind::Ellipsis, ParseRule::new(None, None, Precedence::None)),
+        (
+            TokenKind::Minus,
+            ParseRule::new(Some(ParseFn::Unary), Some(ParseFn::Binary), Precedence::Term),
+        ),
         (TokenKind::Plus, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Term)),
         (TokenKind::Semicolon, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::Slash, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Factor)),
         (TokenKind::Star, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Factor)),
+        (TokenKind::Ampersand, ParseRule::new(None, Some(ParseFn::Binary), Precedence::BitAnd)),
+        (TokenKind::Pipe, ParseRule::new(None, Some(ParseFn::Binary), Precedence::BitOr)),
+        (TokenKind::Caret, ParseRule::new(None, Some(ParseFn::Binary), Precedence::BitXor)),
+        (TokenKind::Tilde, ParseRule::new(Some(ParseFn::Unary), None, Precedence::None)),
         (TokenKind::Bang, ParseRule::new(Some(ParseFn::Unary), None, Precedence::None)),
         (TokenKind::BangEqual, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Equality)),
         (TokenKind::Equal, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::EqualEqual, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Equality)),
+        (TokenKind::Arrow, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::Greater, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Comparison)),
-        (TokenKind::GreaterEqual, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Comparison)),
+        (
+            TokenKind::GreaterEqual,
+            ParseRule::new(None, Some(ParseFn::Binary), Precedence::Comparison),
+        ),
         (TokenKind::Less, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Comparison)),
         (TokenKind::LessEqual, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Comparison)),
+        (TokenKind::LessLess, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Shift)),
+        (TokenKind::GreaterGreater, ParseRule::new(None, Some(ParseFn::Binary), Precedence::Shift)),
         (TokenKind::Identifier, ParseRule::new(Some(ParseFn::Variable), None, Precedence::None)),
+        (TokenKind::PrivateIdentifier, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::String, ParseRule::new(Some(ParseFn::String), None, Precedence::None)),
         (TokenKind::Number, ParseRule::new(Some(ParseFn::Number), None, Precedence::None)),
-        (TokenKind::And, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Integer, ParseRule::new(Some(ParseFn::Number), None, Precedence::None)),
+        (TokenKind::And, ParseRule::new(None, Some(ParseFn::And), Precedence::And)),
         (TokenKind::Class, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Const, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Let, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::Else, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Enum, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::False, ParseRule::new(Some(ParseFn::Literal), None, Precedence::None)),
         (TokenKind::For, ParseRule::new(None, None, Precedence::None)),
-        (TokenKind::Fun, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Fun, ParseRule::new(Some(ParseFn::Function), None, Precedence::None)),
         (TokenKind::If, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::In, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Import, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Export, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::Nil, ParseRule::new(Some(ParseFn::Literal), None, Precedence::None)),
-        (TokenKind::Or, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Match, ParseRule::new(Some(ParseFn::Match), None, Precedence::None)),
+        (TokenKind::Or, ParseRule::new(None, Some(ParseFn::Or), Precedence::Or)),
         (TokenKind::Print, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::Return, ParseRule::new(None, None, Precedence::None)),
-        (TokenKind::Super, ParseRule::new(None, None, Precedence::None)),
-        (TokenKind::This, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Super, ParseRule::new(Some(ParseFn::Super), None, Precedence::None)),
+        (TokenKind::This, ParseRule::new(Some(ParseFn::This), None, Precedence::None)),
         (TokenKind::True, ParseRule::new(Some(ParseFn::Literal), None, Precedence::None)),
+        (TokenKind::Throw, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Try, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Catch, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Finally, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::Var, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::While, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::Yield, ParseRule::new(Some(ParseFn::Yield), None, Precedence::None)),
+        (TokenKind::Whitespace, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::LineComment, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::BlockComment, ParseRule::new(None, None, Precedence::None)),
+        (TokenKind::DocComment, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::ERROR, ParseRule::new(None, None, Precedence::None)),
         (TokenKind::EOF, ParseRule::new(None, None, Precedence::None)),
     ])
@@ -1,60 +1,192 @@
-// TODO: Check unwrap() lines.
-
 use super::helpers::create_rules;
 use super::precedence::Precedence;
 use super::rules::ParseFn;
 use super::rules::ParseRule;
-use crate::chunk::opcode::OpCode;
 use crate::chunk::Chunk;
+use crate::chunk::Code;
+use crate::opcode::OpCode;
 use crate::scanner::token::*;
 use crate::scanner::Scanner;
+use crate::value::class::MemberKind;
+use crate::value::enumeration::Enum;
+use crate::value::enumeration::VariantDeclaration;
+use crate::value::function::Function;
+use crate::value::function::UpvalueSource;
+use crate::value::object::Object;
 use crate::value::Value;
+use crate::Line;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Values left on the stack by an argument list, see Parser::emit_argument_list().
+enum Arguments {
+    /// One value per argument.
+    Fixed(u8),
+    /// Lists to be concatenated into the arguments.
+    Spread(u8),
+}
+
+/// Name of the functions created by `fun () {}` expressions and arrow lambdas.
+const ANONYMOUS: &str = "anonymous";
 
 // NOTE: Local variables structs.
 // TODO: Move to compiler/mod.rs
 
+/// State of the function being compiled. Every function gets its own compiler, the ones of the
+/// enclosing functions wait in Parser::enclosing_compilers.
 #[derive(Default, Debug)]
 struct Compiler {
+    function: Function,
+    kind: FunctionKind,
     locals: Vec<Local>,
     scope_depth: u32,
 }
 
-// if self.current_compiler.count == u8::MAX.into() + 1 {
-//     self.error_at(Box::new(name), "Too many local variables in function.");
-//     return;
-// }
-//
-// if let Some(local) = self.current_compiler.locals.get(self.current_compiler.count) {
-//    k
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+enum FunctionKind {
+    #[default]
+    Script,
+    Function,
+    /// Methods, getters and field initializers. The receiver is available as `this`.
+    Method,
+    /// The `init` method, it returns the new instance.
+    Initializer,
+    /// A `set name(value)` accessor, it returns the assigned value.
+    Setter,
+    Static,
+}
+
+impl FunctionKind {
+    fn has_receiver(self) -> bool {
+        matches!(self, FunctionKind::Method | FunctionKind::Initializer | FunctionKind::Setter)
+    }
+}
+
+/// Pattern of a match arm. It is parsed before being compiled, since its tests and its bindings
+/// are compiled separately.
+#[derive(Debug)]
+enum Pattern {
+    /// `_`, it matches any value.
+    Wildcard,
+    /// A name matches any value and binds it.
+    Binding(Token),
+    /// Numbers, strings, `true`, `false` and `nil` match equal values.
+    Literal(Value),
+    /// `Enum.Variant` or `Enum.Variant(patterns)`, the patterns match the fields in order.
+    Variant {
+        enumeration: String,
+        name: String,
+        fields: Vec<Pattern>,
+    },
+}
+
+impl Pattern {
+    fn is_irrefutable(&self) -> bool {
+        matches!(self, Pattern::Wildcard | Pattern::Binding(_))
+    }
+}
+
+/// Variants handled by the arms of a match, for the exhaustiveness warning.
+#[derive(Debug, Default)]
+struct MatchCoverage {
+    enumeration: Option<String>,
+    variants: HashSet<String>,
+    catch_all: bool,
+}
+
+impl MatchCoverage {
+    /// Arms with a guard don't handle anything for sure, and a variant is only handled by a
+    /// pattern whose fields match any value.
+    fn add(&mut self, pattern: &Pattern, has_guard: bool) {
+        match pattern {
+            Pattern::Variant { enumeration, name, fields } => {
+                self.enumeration.get_or_insert_with(|| enumeration.clone());
+
+                if !has_guard && fields.iter().all(Pattern::is_irrefutable) {
+                    self.variants.insert(name.clone());
+                }
+            }
+            Pattern::Wildcard | Pattern::Binding(_) if !has_guard => self.catch_all = true,
+            _ => (),
+        }
+    }
+}
+
+/// Class whose body is being compiled.
+#[derive(Debug)]
+struct ClassCompiler {
+    name: String,
+    has_superclass: bool,
+}
 
 impl Compiler {
+    /// The first local slot holds the function being called, it has a name that can't be written
+    /// in source code. Methods keep their receiver there instead, named `this`.
+    fn initialize(kind: FunctionKind, name: Option<String>) -> Self {
+        let mut compiler = Self { kind, ..Self::default() };
+
+        let slot_name = if kind.has_receiver() { "this" } else { "" };
+        compiler.function.name = name;
+        compiler.add_local(&Token::new(TokenKind::Identifier, slot_name.to_string(), 0));
+        compiler.mark_initialized();
+        compiler
+    }
+
+    /// The local is added without a depth, it is marked as initialized once its initializer has
+    /// been compiled.
     fn add_local(&mut self, name: &Token) {
         self.locals.push(Local {
             name: name.clone(),
-            depth: self.scope_depth,
+            depth: None,
+            is_captured: false,
+            is_constant: false,
+            value: None,
         });
     }
 
-    // fn depth(&self) -> u32 {
-    //     self.locals.iter()
-    // }
+    fn mark_initialized(&mut self) {
+        let depth = self.scope_depth;
+
+        if let Some(local) = self.locals.last_mut() {
+            local.depth = Some(depth);
+        }
+    }
+
+    /// Walks the locals backwards so the innermost declaration with the given name wins. The
+    /// index in the locals array is the same as the stack slot of the variable.
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.locals.iter().rposition(|local| local.name.source == name)
+    }
 
-    fn count(&self) -> u32 {
-        self.locals.len() as u32
+    /// A closure that refers to the same variable twice captures it once.
+    fn add_upvalue(&mut self, source: UpvalueSource) -> usize {
+        let upvalues = &mut self.function.upvalues;
+
+        match upvalues
+            .iter()
+            .position(|upvalue| upvalue.is_local == source.is_local && upvalue.index == source.index)
+        {
+            Some(index) => index,
+            None => {
+                upvalues.push(source);
+                upvalues.len() - 1
+            }
+        }
     }
 }
 
 #[derive(Debug)]
 struct Local {
     name: Token,
-    depth: u32,
-}
-
-impl Compiler {
-    fn initialize() -> Self {
-        Self::default()
-    }
+    depth: Option<u32>,
+    /// Captured locals are moved to the heap when they go out of scope, instead of being popped.
+    is_captured: bool,
+    /// Declared with `const`, it can't be assigned.
+    is_constant: bool,
+    /// Literal value of a constant, it is inlined where the constant is read.
+    value: Option<Value>,
 }
 
 // ------------------------------
@@ -69,6 +201,18 @@ pub struct Parser<'a> {
     panic_mode: bool,
     rules: HashMap<TokenKind, ParseRule>,
     current_compiler: Compiler,
+    enclosing_compilers: Vec<Compiler>,
+    /// Classes being compiled, from the outermost to the innermost.
+    classes: Vec<ClassCompiler>,
+    /// Enums declared in the script, match expressions check their patterns against them.
+    enums: HashMap<String, Rc<Enum>>,
+    /// Global constants declared in the script, with their value when it can be inlined.
+    constants: HashMap<String, Option<Value>>,
+    /// Tokens scanned ahead of the current one, see peek_kind().
+    lookahead: VecDeque<Token>,
+    /// Doc comment of the declaration being compiled, taken by the function, class or enum it
+    /// declares.
+    doc: Option<String>,
 }
 
 impl<'a> Parser<'a> {
@@ -81,7 +225,13 @@ impl<'a> Parser<'a> {
             had_error: false,
             panic_mode: false,
             rules: create_rules(),
-            current_compiler: Compiler::initialize(),
+            current_compiler: Compiler::initialize(FunctionKind::Script, None),
+            enclosing_compilers: vec![],
+            classes: vec![],
+            enums: HashMap::new(),
+            constants: HashMap::new(),
+            lookahead: VecDeque::new(),
+            doc: None,
         }
     }
 
@@ -89,11 +239,11 @@ impl<'a> Parser<'a> {
         self.previous_token = self.current_token.clone();
 
         // NOTE: The loop is needed for "ignoring" possbile error tokens.
-        while let Some(token) = self.scanner.next() {
+        while let Some(token) = self.lookahead.pop_front().or_else(|| self.scanner.next()) {
             self.current_token = Some(Box::new(token));
 
             match &self.current_token {
-                Some(token) if token.kind == TokenKind::ERROR => self.error_at(token.clone(), ""),
+                Some(token) if token.kind == TokenKind::ERROR => self.error_at(token.clone(), &token.source.clone()),
                 _ => break,
             }
         }
@@ -119,27 +269,86 @@ impl<'a> Parser<'a> {
         self.had_error = true;
     }
 
+    fn error_at_previous(&mut self, message: &str) {
+        if let Some(token) = self.previous_token.clone() {
+            self.error_at(token, message);
+        }
+    }
+
+    /// Warnings are reported like errors, but the script still compiles.
+    fn warning_at(&self, line: Line, message: &str) {
+        eprintln!("[line {line}] Warning: {message}");
+    }
+
+    /// Kind of the token `distance` positions after the current one, without consuming anything.
+    /// The tokens scanned on the way are kept until advance() reaches them.
+    fn peek_kind(&mut self, distance: usize) -> TokenKind {
+        if distance == 0 {
+            return self.current_kind();
+        }
+
+        while self.lookahead.len() < distance {
+            match self.scanner.next() {
+                Some(token) => self.lookahead.push_back(token),
+                None => return TokenKind::EOF,
+            }
+        }
+
+        self.lookahead[distance - 1].kind
+    }
+
+    /// Chunk of the function being compiled.
+    fn current_chunk(&mut self) -> &mut Chunk {
+        &mut self.current_compiler.function.chunk
+    }
+
     pub fn emit_byte(&mut self, code: OpCode) {
-        if let Some(token) = &self.current_token {
-            self.compiling_chunk.write(code, token.line);
+        if let Some(line) = self.current_token.as_ref().map(|token| token.line) {
+            self.current_chunk().write(code, line);
         }
     }
 
     pub fn emit_bytes(&mut self, code1: OpCode, code2: OpCode) {
-        if let Some(token) = &self.current_token {
-            self.compiling_chunk.write(code1, token.line);
-            self.compiling_chunk.write(code2, token.line);
-        }
+        self.emit_byte(code1);
+        self.emit_byte(code2);
     }
 
+    /// Finishes the top level code, its instructions are moved to the chunk given to the parser.
     pub fn end_compiler(&mut self) {
         self.emit_return();
+        std::mem::swap(self.compiling_chunk, &mut self.current_compiler.function.chunk);
     }
 
+    /// Functions without a return statement return nil. Initializers return the new instance, and
+    /// setters the assigned value.
     fn emit_return(&mut self) {
+        match self.current_compiler.kind {
+            FunctionKind::Initializer => self.emit_byte(OpCode::GetLocal(0)),
+            FunctionKind::Setter => self.emit_byte(OpCode::GetLocal(1)),
+            _ => self.emit_byte(OpCode::Nil),
+        }
+
         self.emit_byte(OpCode::Return);
     }
 
+    /// Lexeme of the token that was just consumed.
+    fn previous_source(&self) -> String {
+        self.previous_token.as_ref().map(|token| token.source.clone()).unwrap_or_default()
+    }
+
+    fn previous_kind(&self) -> TokenKind {
+        self.previous_token.as_ref().map(|token| token.kind).unwrap_or(TokenKind::EOF)
+    }
+
+    fn current_kind(&self) -> TokenKind {
+        self.current_token.as_ref().map(|token| token.kind).unwrap_or(TokenKind::EOF)
+    }
+
+    fn get_rule(&self, kind: TokenKind) -> &ParseRule {
+        // NOTE: Every token kind has an entry in the table, see create_rules().
+        self.rules.get(&kind).unwrap()
+    }
+
     // NOTE: Expressions.
 
     pub fn expression(&mut self) {
@@ -150,131 +359,365 @@ impl<'a> Parser<'a> {
         self.advance();
         let can_assign = precedence <= Precedence::Assignment;
 
-        if let Some(token) = &self.previous_token {
-            let rule = self.rules.get(&token.kind).unwrap();
-
-            match rule.prefix {
-                Some(ParseFn::Unary) => self.emit_unary(can_assign),
-                Some(ParseFn::Binary) => self.emit_binary(can_assign),
-                Some(ParseFn::Number) => self.emit_number(can_assign),
-                Some(ParseFn::Grouping) => self.emit_grouping(can_assign),
-                Some(ParseFn::Literal) => self.emit_literal(can_assign),
-                Some(ParseFn::String) => self.emit_string(can_assign),
-                Some(ParseFn::Variable) => self.emit_variable(can_assign),
-                None => {
-                    self.error_at(token.clone(), "Expect expression.");
-                    return;
-                }
-            }
-        }
+        let Some(prefix) = self.get_rule(self.previous_kind()).prefix else {
+            self.error_at_previous("Expect expression.");
+            return;
+        };
 
-        while let Some(token) = &self.current_token.clone() {
-            if precedence as u32 > self.rules.get(&token.kind).unwrap().precedence as u32 {
-                break;
-            }
+        self.apply_parse_fn(prefix, can_assign);
 
+        while precedence <= self.get_rule(self.current_kind()).precedence {
             self.advance();
 
-            match self.rules.get(&token.kind).unwrap().infix {
-                Some(ParseFn::Unary) => self.emit_unary(can_assign),
-                Some(ParseFn::Binary) => self.emit_binary(can_assign),
-                Some(ParseFn::Number) => self.emit_number(can_assign),
-                Some(ParseFn::Grouping) => self.emit_grouping(can_assign),
-                Some(ParseFn::Literal) => self.emit_literal(can_assign),
-                Some(ParseFn::String) => self.emit_string(can_assign),
-                Some(ParseFn::Variable) => self.emit_variable(can_assign),
-                None => (),
+            if let Some(infix) = self.get_rule(self.previous_kind()).infix {
+                self.apply_parse_fn(infix, can_assign);
             }
         }
 
-        let match_equal = self.match_token(TokenKind::Equal);
-        if let Some(token) = &self.previous_token {
-            if can_assign && match_equal {
-                self.error_at(token.clone(), "Invalid assignment target.");
-            }
+        if can_assign && self.match_token(TokenKind::Equal) {
+            self.error_at_previous("Invalid assignment target.");
+        }
+    }
+
+    fn apply_parse_fn(&mut self, parse_fn: ParseFn, can_assign: bool) {
+        match parse_fn {
+            ParseFn::Unary => self.emit_unary(can_assign),
+            ParseFn::Binary => self.emit_binary(can_assign),
+            ParseFn::Number => self.emit_number(can_assign),
+            ParseFn::Grouping => self.emit_grouping(can_assign),
+            ParseFn::Literal => self.emit_literal(can_assign),
+            ParseFn::String => self.emit_string(can_assign),
+            ParseFn::Variable => self.emit_variable(can_assign),
+            ParseFn::List => self.emit_list(can_assign),
+            ParseFn::Map => self.emit_map(can_assign),
+            ParseFn::Index => self.emit_index(can_assign),
+            ParseFn::Dot => self.emit_dot(can_assign),
+            ParseFn::Call => self.emit_call(can_assign),
+            ParseFn::Function => self.emit_function_expression(can_assign),
+            ParseFn::Yield => self.emit_yield(can_assign),
+            ParseFn::And => self.emit_and(can_assign),
+            ParseFn::Or => self.emit_or(can_assign),
+            ParseFn::This => self.emit_this(can_assign),
+            ParseFn::Super => self.emit_super(can_assign),
+            ParseFn::Match => self.emit_match(can_assign),
         }
     }
 
+    /// Integer literals become Value::Int and the rest Value::Number, following the token kind
+    /// inferred by the scanner. Underscores between digits are ignored.
+    ///
+    /// Hexadecimal, binary and octal literals describe bit patterns, so they can use the full 64
+    /// bits: `0xFFFF_FFFF_FFFF_FFFF` is -1.
     fn emit_number(&mut self, _can_assign: bool) {
-        if let Some(token) = &self.previous_token {
-            let value = token.source.parse::<f64>().unwrap();
-            let code = OpCode::Constant(Value::Number(value));
-            self.emit_byte(code);
+        if let Some(value) = self.number_value() {
+            self.emit_byte(OpCode::Constant(value));
+        }
+    }
+
+    /// Value of the number literal in the previous token.
+    fn number_value(&mut self) -> Option<Value> {
+        let source = self.previous_source().replace('_', "");
+
+        let radix = match source.get(..2) {
+            Some("0x") | Some("0X") => 16,
+            Some("0b") | Some("0B") => 2,
+            Some("0o") | Some("0O") => 8,
+            _ => 10,
+        };
+
+        let value = match self.previous_kind() {
+            TokenKind::Integer if radix != 10 => u64::from_str_radix(&source[2..], radix).map(|bits| Value::Int(bits as i64)),
+            TokenKind::Integer => source.parse::<i64>().map(Value::Int),
+            _ => Ok(Value::Number(source.parse::<f64>().unwrap())),
+        };
+
+        match value {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.error_at_previous("Integer literal is too large.");
+                None
+            }
         }
     }
 
+    /// A left parenthesis either groups an expression or starts the parameter list of an arrow
+    /// lambda. The tokens that follow are scanned ahead to tell them apart, see is_arrow_function().
     fn emit_grouping(&mut self, _can_assign: bool) {
+        if self.is_arrow_function() {
+            self.emit_arrow_function();
+            return;
+        }
+
         self.expression();
         self.consume(TokenKind::RightParen, "Expect ')' after expression");
     }
 
     fn emit_unary(&mut self, _can_assign: bool) {
-        if let Some(token) = &self.previous_token {
-            let operator_kind = token.kind;
-            self.parse_precedence(Precedence::Unary);
+        let operator_kind = self.previous_kind();
+        self.parse_precedence(Precedence::Unary);
+
+        match operator_kind {
+            TokenKind::Minus => self.emit_byte(OpCode::Negate),
+            TokenKind::Bang => self.emit_byte(OpCode::Not),
+            TokenKind::Tilde => self.emit_byte(OpCode::BitNot),
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_binary(&mut self, _can_assign: bool) {
+        let operator_kind = self.previous_kind();
+        let rule = self.get_rule(operator_kind);
+
+        if let Ok(precedence) = Precedence::try_from(u8::from(rule.precedence) + 1) {
+            self.parse_precedence(precedence);
+        }
+
+        // TODO: add err message for precedence.
+
+        match operator_kind {
+            TokenKind::Plus => self.emit_byte(OpCode::Add),
+            TokenKind::Minus => self.emit_byte(OpCode::Substract),
+            TokenKind::Star => self.emit_byte(OpCode::Multiply),
+            TokenKind::Slash => self.emit_byte(OpCode::Divide),
+
+            TokenKind::Ampersand => self.emit_byte(OpCode::BitAnd),
+            TokenKind::Pipe => self.emit_byte(OpCode::BitOr),
+            TokenKind::Caret => self.emit_byte(OpCode::BitXor),
+            TokenKind::LessLess => self.emit_byte(OpCode::ShiftLeft),
+            TokenKind::GreaterGreater => self.emit_byte(OpCode::ShiftRight),
+
+            TokenKind::EqualEqual => self.emit_byte(OpCode::Equal),
+            TokenKind::Greater => self.emit_byte(OpCode::Greater),
+            TokenKind::Less => self.emit_byte(OpCode::Less),
+            TokenKind::BangEqual => self.emit_bytes(OpCode::Equal, OpCode::Not),
+            TokenKind::LessEqual => self.emit_bytes(OpCode::Greater, OpCode::Not),
+            TokenKind::GreaterEqual => self.emit_bytes(OpCode::Less, OpCode::Not),
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_literal(&mut self, _can_assign: bool) {
+        match self.previous_kind() {
+            TokenKind::Nil => self.emit_byte(OpCode::Nil),
+            TokenKind::False => self.emit_byte(OpCode::False),
+            TokenKind::True => self.emit_byte(OpCode::True),
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_string(&mut self, _can_assign: bool) {
+        let value = self.string_value();
+        self.emit_byte(OpCode::Constant(value));
+    }
+
+    /// Value of the string literal in the previous token, without its quotes.
+    fn string_value(&self) -> Value {
+        let source = self.previous_source();
+        let value = source.get(1..source.len().saturating_sub(1));
+
+        Value::from(String::from_iter(value))
+    }
 
-            match operator_kind {
-                TokenKind::Minus => self.emit_byte(OpCode::Negate),
-                TokenKind::Bang => self.emit_byte(OpCode::Not),
-                _ => unreachable!(),
+    // NOTE: Collections.
+
+    /// A list literal is a comma separated sequence of expressions between brackets. Every element
+    /// is left on the stack and OP_BUILD_LIST collects them into a new list.
+    fn emit_list(&mut self, can_assign: bool) {
+        if can_assign && self.is_destructuring_assignment() {
+            self.emit_destructuring_assignment();
+            return;
+        }
+
+        let mut count = 0;
+
+        while !self.check(TokenKind::RightBracket) && !self.check(TokenKind::EOF) {
+            self.expression();
+            count += 1;
+
+            if !self.match_token(TokenKind::Comma) {
+                break;
             }
         }
+
+        self.consume(TokenKind::RightBracket, "Expect ']' after list elements.");
+        self.emit_byte(OpCode::BuildList(count));
     }
 
-    fn emit_binary(&mut self, _can_assign: bool) {
-        if let Some(token) = &self.previous_token {
-            let operator_kind = token.kind;
-            let rule = self.rules.get(&operator_kind).unwrap();
+    /// Tells a destructuring assignment, `[a, b] = value`, apart from a list literal. The left
+    /// bracket has been consumed, and only names separated by commas can be assigned.
+    fn is_destructuring_assignment(&mut self) -> bool {
+        let mut distance = 0;
 
-            if let Ok(precedence) = Precedence::try_from(u8::from(rule.precedence) + 1) {
-                self.parse_precedence(precedence);
+        loop {
+            if self.peek_kind(distance) != TokenKind::Identifier {
+                return false;
             }
 
-            // TODO: add err message for precedence.
+            match self.peek_kind(distance + 1) {
+                TokenKind::Comma => distance += 2,
+                TokenKind::RightBracket => return self.peek_kind(distance + 2) == TokenKind::Equal,
+                _ => return false,
+            }
+        }
+    }
 
-            match operator_kind {
-                TokenKind::Plus => self.emit_byte(OpCode::Add),
-                TokenKind::Minus => self.emit_byte(OpCode::Substract),
-                TokenKind::Star => self.emit_byte(OpCode::Multiply),
-                TokenKind::Slash => self.emit_byte(OpCode::Divide),
+    /// Assigns the elements of the value to the variables in order. The value stays on the stack
+    /// as the result of the assignment.
+    fn emit_destructuring_assignment(&mut self) {
+        let mut targets = vec![];
 
-                TokenKind::EqualEqual => self.emit_byte(OpCode::Equal),
-                TokenKind::Greater => self.emit_byte(OpCode::Greater),
-                TokenKind::Less => self.emit_byte(OpCode::Less),
-                TokenKind::BangEqual => {
-                    self.emit_byte(OpCode::Equal);
-                    self.emit_byte(OpCode::Not);
-                }
-                TokenKind::LessEqual => {
-                    self.emit_byte(OpCode::Greater);
-                    self.emit_byte(OpCode::Not);
-                }
-                TokenKind::GreaterEqual => {
-                    self.emit_byte(OpCode::Less);
-                    self.emit_byte(OpCode::Not);
-                }
-                _ => unreachable!(),
+        loop {
+            self.consume(TokenKind::Identifier, "Expect variable name.");
+            let name = self.previous_source();
+            self.verify_assignable(&name);
+            targets.push(name);
+
+            if !self.match_token(TokenKind::Comma) {
+                break;
             }
         }
+
+        self.consume(TokenKind::RightBracket, "Expect ']' after assignment targets.");
+        self.consume(TokenKind::Equal, "Expect '=' after assignment targets.");
+        self.expression();
+
+        for (index, name) in targets.into_iter().enumerate() {
+            let (_, set_op) = self.variable_ops(name);
+
+            self.emit_bytes(OpCode::Dup, OpCode::Constant(Value::Int(index as i64)));
+            self.emit_bytes(OpCode::GetIndex, set_op);
+            self.emit_byte(OpCode::Pop);
+        }
     }
 
-    fn emit_literal(&mut self, _can_assign: bool) {
-        if let Some(token) = &self.previous_token {
-            match token.kind {
-                TokenKind::Nil => self.emit_byte(OpCode::Nil),
-                TokenKind::False => self.emit_byte(OpCode::False),
-                TokenKind::True => self.emit_byte(OpCode::True),
-                _ => unreachable!(),
+    /// A map literal is a comma separated sequence of `key: value` pairs between braces.
+    ///
+    /// A left brace is ambiguous: it either opens a block or a map. The ambiguity is solved by
+    /// position. A brace at the start of a statement is always a block (see emit_statement()), so
+    /// this parse function is only reached when the brace is in expression position, like after
+    /// `=`, `print`, `(` or an operator.
+    fn emit_map(&mut self, _can_assign: bool) {
+        let mut count = 0;
+
+        while !self.check(TokenKind::RightBrace) && !self.check(TokenKind::EOF) {
+            self.expression();
+            self.consume(TokenKind::Colon, "Expect ':' after map key.");
+            self.expression();
+            count += 1;
+
+            if !self.match_token(TokenKind::Comma) {
+                break;
             }
         }
+
+        self.consume(TokenKind::RightBrace, "Expect '}' after map entries.");
+        self.emit_byte(OpCode::BuildMap(count));
     }
 
-    fn emit_string(&mut self, _can_assign: bool) {
-        if let Some(token) = &self.previous_token {
-            let value = token.source.get(1..token.source.len() - 1);
-            let value = String::from_iter(value);
-            let value = Value::from(value);
-            self.emit_byte(OpCode::Constant(value));
+    /// Subscript operator. The collection is already on the stack, so we compile the index and,
+    /// when an assignment follows, the new value.
+    fn emit_index(&mut self, can_assign: bool) {
+        self.expression();
+        self.consume(TokenKind::RightBracket, "Expect ']' after index.");
+
+        if can_assign && self.match_token(TokenKind::Equal) {
+            self.expression();
+            self.emit_byte(OpCode::SetIndex);
+        } else {
+            self.emit_byte(OpCode::GetIndex);
+        }
+    }
+
+    /// Property access, like `point.x`, assignment, like `point.x = 1`, or a method call, like
+    /// `map.keys()`. The receiver is already on the stack and the arguments of a call, or the
+    /// assigned value, are pushed on top of it.
+    fn emit_dot(&mut self, can_assign: bool) {
+        // NOTE: `match` is a keyword, but also the name of a method of regexes.
+        if !self.match_token(TokenKind::PrivateIdentifier) && !self.match_token(TokenKind::Match) {
+            self.consume(TokenKind::Identifier, "Expect property name after '.'.");
+        }
+
+        let name = self.member_name();
+
+        if can_assign && self.match_token(TokenKind::Equal) {
+            self.expression();
+            self.emit_byte(OpCode::SetProperty(name));
+        } else if self.match_token(TokenKind::LeftParen) {
+            match self.emit_argument_list() {
+                Arguments::Fixed(count) => self.emit_byte(OpCode::Invoke(name, count)),
+                Arguments::Spread(lists) => self.emit_byte(OpCode::InvokeSpread(name, lists)),
+            }
+        } else {
+            self.emit_byte(OpCode::GetProperty(name));
+        }
+    }
+
+    /// Name of the member in the previous token. Private names, `#name`, can only be used inside
+    /// the body of a class, and they are prefixed with its name so other classes, subclasses
+    /// included, can't reach them.
+    fn member_name(&mut self) -> String {
+        let name = self.previous_source();
+
+        if self.previous_kind() != TokenKind::PrivateIdentifier {
+            return name;
+        }
+
+        match self.classes.last() {
+            Some(class) => format!("{}{name}", class.name),
+            None => {
+                self.error_at_previous("Private members can't be used outside their class.");
+                name
+            }
+        }
+    }
+
+    /// The callee is already on the stack, the arguments are compiled on top of it.
+    fn emit_call(&mut self, _can_assign: bool) {
+        match self.emit_argument_list() {
+            Arguments::Fixed(count) => self.emit_byte(OpCode::Call(count)),
+            Arguments::Spread(lists) => self.emit_byte(OpCode::CallSpread(lists)),
+        }
+    }
+
+    /// Arguments are pushed one by one until a spread argument, `...list`, is found. From then on
+    /// the number of arguments is only known at runtime, so the arguments before the spread are
+    /// collected into a list, every later argument is wrapped in a list, and OP_CALL_SPREAD or
+    /// OP_INVOKE_SPREAD concatenates the lists.
+    fn emit_argument_list(&mut self) -> Arguments {
+        let mut count: u8 = 0;
+        let mut is_spread = false;
+
+        while !self.check(TokenKind::RightParen) && !self.check(TokenKind::EOF) {
+            let spread_argument = self.match_token(TokenKind::Ellipsis);
+
+            if spread_argument && !is_spread {
+                self.emit_byte(OpCode::BuildList(count.into()));
+                count = 1;
+                is_spread = true;
+            }
+
+            self.expression();
+
+            if is_spread && !spread_argument {
+                self.emit_byte(OpCode::BuildList(1));
+            }
+
+            if count == u8::MAX {
+                self.error_at_previous("Can't have more than 255 arguments.");
+            }
+
+            count = count.saturating_add(1);
+
+            if !self.match_token(TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.consume(TokenKind::RightParen, "Expect ')' after arguments.");
+
+        match is_spread {
+            true => Arguments::Spread(count),
+            false => Arguments::Fixed(count),
         }
     }
 
@@ -306,8 +749,22 @@ impl<'a> Parser<'a> {
     /// If we hit a compile error while parsing the previous statement, we enter panic mode. When
     /// that happens, wr start synchronizing.
     pub fn emit_declaration(&mut self) {
-        if self.match_token(TokenKind::Var) {
+        self.doc = self.current_token.as_ref().and_then(|token| token.doc.clone());
+
+        if self.match_token(TokenKind::Class) {
+            self.emit_class_declaration();
+        } else if self.match_token(TokenKind::Enum) {
+            self.emit_enum_declaration();
+        } else if self.match_token(TokenKind::Var) {
             self.emit_var_declaration();
+        } else if self.match_token(TokenKind::Const) || self.match_token(TokenKind::Let) {
+            self.emit_const_declaration();
+        } else if self.match_token(TokenKind::Fun) {
+            self.emit_fun_declaration();
+        } else if self.match_token(TokenKind::Import) {
+            self.emit_import_declaration();
+        } else if self.match_token(TokenKind::Export) {
+            self.emit_export_declaration();
         } else {
             self.emit_statement();
         }
@@ -337,12 +794,19 @@ impl<'a> Parser<'a> {
 
             match token.kind {
                 TokenKind::Class
+                | TokenKind::Enum
                 | TokenKind::Fun
                 | TokenKind::Var
+                | TokenKind::Const
+                | TokenKind::Let
                 | TokenKind::For
                 | TokenKind::If
                 | TokenKind::While
                 | TokenKind::Print
+                | TokenKind::Throw
+                | TokenKind::Try
+                | TokenKind::Import
+                | TokenKind::Export
                 | TokenKind::Return => return,
                 _ => (),
             }
@@ -351,11 +815,25 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// TODO: complete docs here
-    /// If we don't see a print keyword, the we must be looking at an expression statement.
+    /// If we don't see a print keyword or a block, then we must be looking at an expression statement.
+    ///
+    /// A left brace at the start of a statement always opens a block, a map literal can't start
+    /// an expression statement.
     fn emit_statement(&mut self) {
         if self.match_token(TokenKind::Print) {
             self.emit_print_statement();
+        } else if self.match_token(TokenKind::If) {
+            self.emit_if_statement();
+        } else if self.match_token(TokenKind::While) {
+            self.emit_while_statement();
+        } else if self.match_token(TokenKind::For) {
+            self.emit_for_in_statement();
+        } else if self.match_token(TokenKind::Return) {
+            self.emit_return_statement();
+        } else if self.match_token(TokenKind::Throw) {
+            self.emit_throw_statement();
+        } else if self.match_token(TokenKind::Try) {
+            self.emit_try_statement();
         } else if self.match_token(TokenKind::LeftBrace) {
             self.begin_scope();
             self.emit_block();
@@ -383,147 +861,1411 @@ impl<'a> Parser<'a> {
         self.emit_byte(OpCode::Print)
     }
 
-    /// NOTE: Global Variables methods.
+    // NOTE: Control flow.
+
+    /// ```text
+    ///       <condition>
+    ///       OP_JUMP_IF_FALSE else
+    ///       OP_POP
+    ///       <then branch>
+    ///       OP_JUMP end
+    /// else: OP_POP
+    ///       <else branch>
+    /// end:  ...
+    /// ```
+    fn emit_if_statement(&mut self) {
+        self.consume(TokenKind::LeftParen, "Expect '(' after 'if'.");
+        self.expression();
+        self.consume(TokenKind::RightParen, "Expect ')' after condition.");
 
-    /// First, the method compiles the variable name through parse_variable(). Then we look for an
-    /// = followed by a initializer expression. If the user doesn't initialize the variable. the
-    /// compiler implicity initializes it to nil. Either way, we expect the statement to be
-    /// terminated with a semicolon.
-    fn emit_var_declaration(&mut self) {
-        let global = self.parse_variable("Expect variable name.");
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse(0));
+        self.emit_byte(OpCode::Pop);
+        self.emit_statement();
 
-        if self.match_token(TokenKind::Equal) {
-            self.expression();
-        } else {
-            self.emit_byte(OpCode::Nil);
+        let else_jump = self.emit_jump(OpCode::Jump(0));
+        self.patch_jump(then_jump);
+        self.emit_byte(OpCode::Pop);
+
+        if self.match_token(TokenKind::Else) {
+            self.emit_statement();
         }
 
-        self.consume(TokenKind::Semicolon, "Expect ',' after variable declaration.");
-        self.define_variable(global);
+        self.patch_jump(else_jump);
     }
 
-    fn parse_variable(&mut self, message: &str) -> OpCode {
-        self.consume(TokenKind::Identifier, message);
+    fn emit_while_statement(&mut self) {
+        let loop_start = self.current_chunk().codes.len();
+        self.consume(TokenKind::LeftParen, "Expect '(' after 'while'.");
+        self.expression();
+        self.consume(TokenKind::RightParen, "Expect ')' after condition.");
 
-        self.declare_variable();
-        if self.current_compiler.scope_depth > 0 {
-            // TODO: Check this
-            // BUG: Parse this option opcode return value. it interprets a return , manage it with
-            // if let :)
-            return OpCode::Return;
-        }
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse(0));
+        self.emit_byte(OpCode::Pop);
+        self.emit_statement();
+        self.emit_loop(loop_start);
 
-        self.parse_identifier_constant()
+        self.patch_jump(exit_jump);
+        self.emit_byte(OpCode::Pop);
     }
 
-    /// Creates a constant OpCode that contains the variable name.
-    fn parse_identifier_constant(&self) -> OpCode {
-        if let Some(token) = &self.previous_token {
-            OpCode::Constant(Value::Object(Box::new(token.source.clone())))
-        } else {
-            unreachable!()
-        }
+    /// The left operand is on the stack. If it is falsey it is the result, otherwise it is
+    /// popped and the right operand is the result.
+    fn emit_and(&mut self, _can_assign: bool) {
+        let end_jump = self.emit_jump(OpCode::JumpIfFalse(0));
+
+        self.emit_byte(OpCode::Pop);
+        self.parse_precedence(Precedence::And);
+        self.patch_jump(end_jump);
     }
 
-    fn emit_variable(&mut self, can_assign: bool) {
-        self.emit_named_variable(can_assign);
+    /// The left operand is the result if it is truthy, otherwise the right operand is.
+    fn emit_or(&mut self, _can_assign: bool) {
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse(0));
+        let end_jump = self.emit_jump(OpCode::Jump(0));
+
+        self.patch_jump(else_jump);
+        self.emit_byte(OpCode::Pop);
+        self.parse_precedence(Precedence::Or);
+        self.patch_jump(end_jump);
     }
 
-    /// Toke the given identifier token and add its lexeme to the chunk's constant table as a
-    /// string.
-    ///
-    /// Since assignment is the lowest precedence expression, the only time we allow an assignment
-    /// is when parsing an assignment expression or top level expression like in an expression
-    /// statement.
-    fn emit_named_variable(&mut self, can_assign: bool) {
-        let get_op: OpCode;
-        let set_op: OpCode;
+    // NOTE: Loops.
 
+    /// A `for (name in iterable) body` loop is desugared to the iterator protocol:
+    ///
+    /// ```text
+    ///       <iterable>
+    ///       OP_GET_ITERATOR        -> hidden local holding the iterator
+    /// loop: OP_FOR_ITER exit       -> pushes the next element as the loop variable
+    ///       <body>
+    ///       OP_POP                 -> the loop variable goes out of scope
+    ///       OP_LOOP loop
+    /// exit: OP_POP                 -> the iterator goes out of scope
+    /// ```
+    ///
+    /// The iterator lives in a local whose name can't be written in source code, so the compiler
+    /// allocates it automatically and the loop body can't shadow it.
+    fn emit_for_in_statement(&mut self) {
+        self.begin_scope();
+        self.consume(TokenKind::LeftParen, "Expect '(' after 'for'.");
+        self.consume(TokenKind::Identifier, "Expect loop variable name.");
+        let variable = self.previous_token.clone();
+
+        self.consume(TokenKind::In, "Expect 'in' after loop variable.");
+        self.expression();
+        self.consume(TokenKind::RightParen, "Expect ')' after for clauses.");
+
+        self.emit_byte(OpCode::GetIterator);
+        let line = variable.as_ref().map(|token| token.line).unwrap_or_default();
+        self.current_compiler
+            .add_local(&Token::new(TokenKind::Identifier, "for iterator".to_string(), line));
+        self.current_compiler.mark_initialized();
+        let iterator_slot = self.current_compiler.locals.len() - 1;
+
+        let loop_start = self.current_chunk().codes.len();
+        let exit_jump = self.emit_jump(OpCode::ForIter(iterator_slot, 0));
+
+        self.begin_scope();
+        if let Some(variable) = variable {
+            self.current_compiler.add_local(&variable);
+            self.current_compiler.mark_initialized();
+        }
+        self.emit_statement();
+        self.end_scope();
 
-        // let arg = self.resolve_constant();
+        self.emit_loop(loop_start);
+        self.patch_jump(exit_jump);
+        self.end_scope();
+    }
 
-        let arg = self.parse_identifier_constant();
+    /// Emits a jump instruction with a placeholder offset and returns its index, so the offset
+    /// can be filled in by patch_jump() once the target is known.
+    fn emit_jump(&mut self, code: OpCode) -> usize {
+        self.emit_byte(code);
+        self.current_chunk().codes.len() - 1
+    }
 
-        if self.match_token(TokenKind::Equal) && can_assign {
-            self.expression();
-            self.emit_byte(arg);
-            self.emit_byte(OpCode::SetGlobal);
-        } else {
-            self.emit_byte(arg);
-            self.emit_byte(OpCode::GetGlobal);
+    /// Makes the jump at the given index land on the next instruction to be emitted.
+    fn patch_jump(&mut self, index: usize) {
+        let jump = self.current_chunk().codes.len() - index - 1;
+
+        match self.current_chunk().codes.get_mut(index) {
+            Some(Code(OpCode::ForIter(_, offset), _))
+            | Some(Code(OpCode::Jump(offset), _))
+            | Some(Code(OpCode::JumpIfFalse(offset), _))
+            | Some(Code(OpCode::PushHandler(offset, _), _))
+            | Some(Code(OpCode::DefaultParameter(_, offset), _)) => *offset = jump,
+            _ => (),
         }
     }
 
-    fn define_variable(&mut self, global: OpCode) {
-        if self.current_compiler.scope_depth > 0 {
-            return;
-        }
+    fn emit_loop(&mut self, loop_start: usize) {
+        let offset = self.current_chunk().codes.len() - loop_start + 1;
+        self.emit_byte(OpCode::Loop(offset));
+    }
+
+    // NOTE: Exceptions.
 
-        self.emit_byte(global);
-        self.emit_byte(OpCode::DefineGlobal);
+    fn emit_throw_statement(&mut self) {
+        self.expression();
+        self.consume(TokenKind::Semicolon, "Expect ';' after thrown value.");
+        self.emit_byte(OpCode::Throw);
     }
 
-    fn declare_variable(&mut self) {
-        // If we're in the top-level global scope, we just bail out.
-        if self.current_compiler.scope_depth == 0 {
-            return;
+    /// `try { A } catch (e) { B } finally { C }` compiles to:
+    ///
+    /// ```text
+    ///          OP_PUSH_HANDLER catch
+    ///          <A>
+    ///          OP_POP_HANDLER
+    ///          OP_JUMP normal
+    /// catch:   OP_PUSH_HANDLER pending  -> the exception is the local e
+    ///          <B>
+    ///          OP_POP_HANDLER
+    ///          OP_JUMP normal
+    /// pending: OP_TRUE                  -> the exception is thrown again after C
+    ///          OP_JUMP finally
+    /// normal:  OP_NIL
+    ///          OP_FALSE                 -> nothing to throw again
+    /// finally: <C>
+    ///          OP_END_FINALLY
+    /// ```
+    ///
+    /// The catch and the finally clauses are optional, but at least one of them is required.
+    /// Without a finally clause, exceptions thrown by the catch block are thrown again from the
+    /// pending label. A handler restores the stack to the locals that were alive when the try
+    /// statement started, so every path into the finally block finds the same two hidden locals on
+    /// top of the stack.
+    fn emit_try_statement(&mut self) {
+        let slots = self.current_compiler.locals.len();
+
+        let try_handler = self.emit_jump(OpCode::PushHandler(0, slots));
+        self.emit_scoped_block("Expect '{' after 'try'.");
+        self.emit_byte(OpCode::PopHandler);
+        let mut normal_exits = vec![self.emit_jump(OpCode::Jump(0))];
+        self.patch_jump(try_handler);
+
+        let mut pending_handler = try_handler;
+        let has_catch = self.match_token(TokenKind::Catch);
+
+        if has_catch {
+            self.begin_scope();
+            self.consume(TokenKind::LeftParen, "Expect '(' after 'catch'.");
+            self.consume(TokenKind::Identifier, "Expect exception variable name.");
+            if let Some(name) = self.previous_token.clone() {
+                self.current_compiler.add_local(&name);
+                self.current_compiler.mark_initialized();
+            }
+            self.consume(TokenKind::RightParen, "Expect ')' after exception variable name.");
+
+            pending_handler = self.emit_jump(OpCode::PushHandler(0, slots));
+            self.emit_scoped_block("Expect '{' after catch clause.");
+            self.emit_byte(OpCode::PopHandler);
+            self.end_scope();
+            normal_exits.push(self.emit_jump(OpCode::Jump(0)));
         }
 
-        // For local variables it needs to remember that the variable exists.
-        if let Some(name) = &self.previous_token {
-            // Shadowing verification.
-            for local in self.current_compiler.locals.iter().rev() {
-                if local.depth != 1 && local.depth < self.current_compiler.scope_depth {
-                    break;
-                }
+        let has_finally = self.match_token(TokenKind::Finally);
 
-                // WARNING: CHANGE REFERENCES AND USE THIS ERROR LABRERY
-                //
-                if *name.as_ref() == local.name {
-                    // self.error_at(Box::new(local.name.clone()), "Already a variable with this name in the scope.")
-                    eprintln!("Already a variable with this name in the scope.")
-                }
-            }
+        if !has_catch && !has_finally {
+            self.error_at_previous("Expect 'catch' or 'finally' after try block.");
+            return;
+        }
 
-            self.current_compiler.add_local(name);
+        if !has_finally {
+            self.patch_jump(pending_handler);
+            self.emit_byte(OpCode::Throw);
+            normal_exits.into_iter().for_each(|exit| self.patch_jump(exit));
+            return;
         }
-    }
 
-    // NOTE: Block statements.
+        self.patch_jump(pending_handler);
+        self.emit_byte(OpCode::True);
+        let pending_exit = self.emit_jump(OpCode::Jump(0));
 
-    /// It keeps parsing declarations and statements until it hits the closing brace. As we do with
-    /// any loop in the parser, we also checl for the end of the token stream.
-    fn emit_block(&mut self) {
-        while !self.check(TokenKind::RightBrace) && !self.check(TokenKind::EOF) {
-            self.emit_declaration();
+        normal_exits.into_iter().for_each(|exit| self.patch_jump(exit));
+        self.emit_bytes(OpCode::Nil, OpCode::False);
+        self.patch_jump(pending_exit);
+
+        let line = self.previous_token.as_ref().map(|token| token.line).unwrap_or_default();
+        for name in ["finally exception", "finally rethrow"] {
+            self.current_compiler
+                .add_local(&Token::new(TokenKind::Identifier, name.to_string(), line));
+            self.current_compiler.mark_initialized();
         }
 
-        self.consume(TokenKind::RightBrace, "Expect '}' after block.");
+        self.emit_scoped_block("Expect '{' after 'finally'.");
+
+        // NOTE: OP_END_FINALLY pops the hidden locals itself.
+        self.current_compiler.locals.truncate(slots);
+        self.emit_byte(OpCode::EndFinally);
+    }
+
+    /// Consumes a brace delimited block that gets its own scope.
+    fn emit_scoped_block(&mut self, message: &str) {
+        self.consume(TokenKind::LeftBrace, message);
+        self.begin_scope();
+        self.emit_block();
+        self.end_scope();
+    }
+
+    // NOTE: Functions.
+
+    /// `fun name(parameters) { body }` binds a new function to a variable. A local function is
+    /// initialized before its body is compiled, so it can call itself.
+    fn emit_fun_declaration(&mut self) {
+        let global = self.parse_variable("Expect function name.");
+        let name = self.previous_source();
+
+        if global.is_none() {
+            self.current_compiler.mark_initialized();
+        }
+
+        self.consume(TokenKind::LeftParen, "Expect '(' after function name.");
+        self.emit_function(name, FunctionKind::Function);
+        self.define_variable(global);
+    }
+
+    /// `fun (parameters) { body }` creates an anonymous function. At the start of a statement
+    /// `fun` always begins a declaration, so this parse function is only reached when it is in
+    /// expression position.
+    fn emit_function_expression(&mut self, _can_assign: bool) {
+        self.consume(TokenKind::LeftParen, "Expect '(' after 'fun'.");
+        self.emit_function(ANONYMOUS.to_string(), FunctionKind::Function);
+    }
+
+    /// Compiles the parameters and the body of a function, the opening parenthesis has already
+    /// been consumed. The function is left on the stack as a closure.
+    fn emit_function(&mut self, name: String, kind: FunctionKind) {
+        self.begin_function(name, kind);
+        self.emit_parameter_list();
+        self.consume(TokenKind::LeftBrace, "Expect '{' before function body.");
+        self.emit_block();
+        self.end_function();
+    }
+
+    /// `(a, b) => a + b` is a lambda whose body is a single expression, it returns its value.
+    /// The body can also be a block, `(a, b) => { return a + b; }`, which means that a map
+    /// literal returned by an expression body has to be wrapped in parentheses.
+    fn emit_arrow_function(&mut self) {
+        self.begin_function(ANONYMOUS.to_string(), FunctionKind::Function);
+        self.emit_parameter_list();
+        self.consume(TokenKind::Arrow, "Expect '=>' after lambda parameters.");
+
+        if self.match_token(TokenKind::LeftBrace) {
+            self.emit_block();
+        } else {
+            self.expression();
+            self.emit_byte(OpCode::Return);
+        }
+
+        self.end_function();
+    }
+
+    /// Tells an arrow lambda apart from a grouping. The left parenthesis has been consumed, and
+    /// only a list of parameters followed by `) =>` can be a parameter list.
+    fn is_arrow_function(&mut self) -> bool {
+        let mut distance = 0;
+
+        loop {
+            if self.peek_kind(distance) == TokenKind::Ellipsis {
+                distance += 1;
+            }
+
+            if self.peek_kind(distance) != TokenKind::Identifier {
+                break;
+            }
+
+            distance += 1;
+
+            if self.peek_kind(distance) == TokenKind::Equal {
+                distance = self.skip_default_value(distance + 1);
+            }
+
+            if self.peek_kind(distance) != TokenKind::Comma {
+                break;
+            }
+
+            distance += 1;
+        }
+
+        self.peek_kind(distance) == TokenKind::RightParen && self.peek_kind(distance + 1) == TokenKind::Arrow
+    }
+
+    /// Returns the distance of the comma or the closing parenthesis that ends a default value,
+    /// skipping the ones nested in the value itself.
+    fn skip_default_value(&mut self, mut distance: usize) -> usize {
+        let mut nesting = 0;
+
+        loop {
+            match self.peek_kind(distance) {
+                TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => nesting += 1,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace if nesting > 0 => nesting -= 1,
+                TokenKind::Comma | TokenKind::RightParen if nesting == 0 => return distance,
+                TokenKind::EOF => return distance,
+                _ => (),
+            }
+
+            distance += 1;
+        }
+    }
+
+    /// Parameters are the first locals of the function, right after the slot of the function
+    /// itself. Consumes the closing parenthesis.
+    ///
+    /// Parameters with a default value, `b = 2`, follow the required ones. The caller fills the
+    /// missing arguments with nil, and the function starts by evaluating the defaults of the
+    /// arguments it didn't receive:
+    ///
+    /// ```text
+    ///       OP_DEFAULT_PARAMETER b skip  -> jumps when the argument was passed
+    ///       <default value>
+    ///       OP_SET_LOCAL b
+    ///       OP_POP
+    /// skip: ...
+    /// ```
+    ///
+    /// Defaults are evaluated on every call and can use the parameters before them. A last
+    /// parameter written as `...rest` receives the extra arguments in a list.
+    fn emit_parameter_list(&mut self) {
+        while !self.check(TokenKind::RightParen) && !self.check(TokenKind::EOF) {
+            if self.match_token(TokenKind::Ellipsis) {
+                let rest = self.parse_variable("Expect rest parameter name after '...'.");
+                self.define_variable(rest);
+                self.current_compiler.function.has_rest = true;
+
+                if !self.check(TokenKind::RightParen) {
+                    self.error_at_previous("Rest parameter must be the last parameter.");
+                }
+
+                break;
+            }
+
+            if self.current_compiler.function.arity == u8::MAX {
+                self.error_at_previous("Can't have more than 255 parameters.");
+            }
+
+            let function = &mut self.current_compiler.function;
+            function.arity = function.arity.saturating_add(1);
+            let parameter = self.parse_variable("Expect parameter name.");
+            self.define_variable(parameter);
+
+            if self.match_token(TokenKind::Equal) {
+                self.emit_default_parameter();
+            } else if self.current_compiler.function.required < self.current_compiler.function.arity - 1 {
+                self.error_at_previous("Parameter without a default value can't follow one with a default value.");
+            } else {
+                self.current_compiler.function.required = self.current_compiler.function.arity;
+            }
+
+            if !self.match_token(TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.consume(TokenKind::RightParen, "Expect ')' after parameters.");
+    }
+
+    fn emit_default_parameter(&mut self) {
+        let slot = self.current_compiler.locals.len() - 1;
+
+        let skip = self.emit_jump(OpCode::DefaultParameter(slot, 0));
+        self.expression();
+        self.emit_bytes(OpCode::SetLocal(slot), OpCode::Pop);
+        self.patch_jump(skip);
+    }
+
+    /// The body of a function is compiled with a new compiler, whose locals start in a new scope.
+    fn begin_function(&mut self, name: String, kind: FunctionKind) {
+        let mut compiler = Compiler::initialize(kind, Some(name));
+        compiler.function.doc = self.doc.take();
+
+        let enclosing = std::mem::replace(&mut self.current_compiler, compiler);
+
+        self.enclosing_compilers.push(enclosing);
+        self.begin_scope();
+    }
+
+    /// The locals of the function don't need to be popped, returning discards the whole frame.
+    fn end_function(&mut self) {
+        self.emit_return();
+
+        let enclosing = self.enclosing_compilers.pop().unwrap_or_default();
+        let compiler = std::mem::replace(&mut self.current_compiler, enclosing);
+
+        self.emit_byte(OpCode::Closure(Rc::new(compiler.function)));
+    }
+
+    /// `yield value` suspends the coroutine running the function and hands the value to the code
+    /// that resumed it. The yield evaluates to the value passed to the next resume. Any function
+    /// containing a yield becomes a generator: calling it creates a coroutine.
+    fn emit_yield(&mut self, _can_assign: bool) {
+        if self.current_compiler.kind == FunctionKind::Script {
+            self.error_at_previous("Can't yield from top-level code.");
+        }
+
+        self.current_compiler.function.is_generator = true;
+
+        match self.current_kind() {
+            TokenKind::Semicolon | TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace | TokenKind::Comma => {
+                self.emit_byte(OpCode::Nil)
+            }
+            _ => self.expression(),
+        }
+
+        self.emit_byte(OpCode::Yield);
+    }
+
+    fn emit_return_statement(&mut self) {
+        if self.current_compiler.kind == FunctionKind::Script {
+            self.error_at_previous("Can't return from top-level code.");
+        }
+
+        if self.match_token(TokenKind::Semicolon) {
+            self.emit_return();
+        } else {
+            if matches!(self.current_compiler.kind, FunctionKind::Initializer | FunctionKind::Setter) {
+                self.error_at_previous("Can't return a value from an initializer or a setter.");
+            }
+
+            self.emit_return_values();
+            self.consume(TokenKind::Semicolon, "Expect ';' after return value.");
+            self.emit_byte(OpCode::Return);
+        }
+    }
+
+    /// `return a, b;` returns the values in a list, so the caller can destructure them with
+    /// `var a, b = f();`.
+    fn emit_return_values(&mut self) {
+        self.expression();
+
+        let mut count = 1;
+        while self.match_token(TokenKind::Comma) {
+            self.expression();
+            count += 1;
+        }
+
+        if count > 1 {
+            self.emit_byte(OpCode::BuildList(count));
+        }
+    }
+
+    // NOTE: Classes.
+
+    /// The class is bound to its name before the body is compiled, so methods can refer to it.
+    /// A superclass is stored in a local named `super` that the methods capture.
+    fn emit_class_declaration(&mut self) {
+        let doc = self.doc.take();
+        let global = self.parse_variable("Expect class name.");
+        let name = self.previous_source();
+
+        self.emit_byte(OpCode::Class(name.clone(), doc));
+        self.define_variable(global);
+        self.classes.push(ClassCompiler {
+            name: name.clone(),
+            has_superclass: false,
+        });
+
+        if self.match_token(TokenKind::Less) {
+            self.consume(TokenKind::Identifier, "Expect superclass name.");
+            if self.previous_source() == name {
+                self.error_at_previous("A class can't inherit from itself.");
+            }
+
+            self.emit_variable(false);
+
+            let line = self.previous_token.as_ref().map(|token| token.line).unwrap_or_default();
+            self.begin_scope();
+            self.current_compiler
+                .add_local(&Token::new(TokenKind::Identifier, "super".to_string(), line));
+            self.current_compiler.mark_initialized();
+
+            self.emit_named_variable(name.clone(), false);
+            self.emit_byte(OpCode::Inherit);
+
+            if let Some(class) = self.classes.last_mut() {
+                class.has_superclass = true;
+            }
+        }
+
+        self.emit_named_variable(name, false);
+        self.consume(TokenKind::LeftBrace, "Expect '{' before class body.");
+
+        while !self.check(TokenKind::RightBrace) && !self.check(TokenKind::EOF) {
+            self.emit_member();
+        }
+
+        self.consume(TokenKind::RightBrace, "Expect '}' after class body.");
+        self.emit_byte(OpCode::Pop);
+
+        if self.classes.pop().is_some_and(|class| class.has_superclass) {
+            self.end_scope();
+        }
+    }
+
+    /// A member of a class body is one of:
+    ///
+    /// ```text
+    /// name(parameters) { body }          method, `init` is the initializer
+    /// static name(parameters) { body }   called on the class
+    /// get name { body }                  read like a field
+    /// set name(value) { body }           assigned like a field
+    /// var name = value;                  field, initialized for every new instance
+    /// ```
+    ///
+    /// `static`, `get` and `set` are only special when a name follows them, so they are still
+    /// valid method names. Any member name can be private, `#name`.
+    fn emit_member(&mut self) {
+        self.doc = self.current_token.as_ref().and_then(|token| token.doc.clone());
+
+        let kind = if self.match_token(TokenKind::Var) {
+            MemberKind::Field
+        } else {
+            let modifier = match self.current_token.as_ref().map(|token| token.source.as_str()) {
+                Some("static") => Some(MemberKind::Static),
+                Some("get") => Some(MemberKind::Getter),
+                Some("set") => Some(MemberKind::Setter),
+                _ => None,
+            };
+
+            let is_modifier = matches!(self.peek_kind(1), TokenKind::Identifier | TokenKind::PrivateIdentifier);
+            match modifier {
+                Some(kind) if self.check(TokenKind::Identifier) && is_modifier => {
+                    self.advance();
+                    kind
+                }
+                _ => MemberKind::Method,
+            }
+        };
+
+        if !self.match_token(TokenKind::PrivateIdentifier) {
+            self.consume(TokenKind::Identifier, "Expect member name.");
+        }
+
+        let name = self.member_name();
+
+        match kind {
+            MemberKind::Field => {
+                self.begin_function(name.clone(), FunctionKind::Method);
+
+                if self.match_token(TokenKind::Equal) {
+                    self.expression();
+                } else {
+                    self.emit_byte(OpCode::Nil);
+                }
+
+                self.emit_byte(OpCode::Return);
+                self.end_function();
+                self.consume(TokenKind::Semicolon, "Expect ';' after field declaration.");
+            }
+            MemberKind::Getter => {
+                self.begin_function(name.clone(), FunctionKind::Method);
+                self.consume(TokenKind::LeftBrace, "Expect '{' before getter body.");
+                self.emit_block();
+                self.end_function();
+            }
+            MemberKind::Setter => {
+                self.consume(TokenKind::LeftParen, "Expect '(' after setter name.");
+                self.begin_function(name.clone(), FunctionKind::Setter);
+                self.emit_parameter_list();
+
+                let function = &self.current_compiler.function;
+                if function.arity != 1 || function.has_rest {
+                    self.error_at_previous("A setter takes exactly one parameter.");
+                }
+
+                self.consume(TokenKind::LeftBrace, "Expect '{' before setter body.");
+                self.emit_block();
+                self.end_function();
+            }
+            MemberKind::Method | MemberKind::Static => {
+                let function_kind = match kind {
+                    MemberKind::Static => FunctionKind::Static,
+                    _ if name == "init" => FunctionKind::Initializer,
+                    _ => FunctionKind::Method,
+                };
+
+                self.consume(TokenKind::LeftParen, "Expect '(' after method name.");
+                self.emit_function(name.clone(), function_kind);
+            }
+        }
+
+        self.emit_byte(OpCode::Member(name, kind));
+    }
+
+    fn emit_this(&mut self, _can_assign: bool) {
+        if self.classes.is_empty() {
+            self.error_at_previous("Can't use 'this' outside of a class.");
+            return;
+        }
+
+        if self.method_kind() == FunctionKind::Static {
+            self.error_at_previous("Can't use 'this' in a static method.");
+            return;
+        }
+
+        self.emit_named_variable("this".to_string(), false);
+    }
+
+    /// Kind of the method the code being compiled belongs to. Functions nested in a method
+    /// capture its `this`, so they are skipped.
+    fn method_kind(&self) -> FunctionKind {
+        std::iter::once(&self.current_compiler)
+            .chain(self.enclosing_compilers.iter().rev())
+            .map(|compiler| compiler.kind)
+            .find(|kind| *kind != FunctionKind::Function)
+            .unwrap_or_default()
+    }
+
+    /// `super.name` reads a method of the superclass, bound to `this`.
+    fn emit_super(&mut self, _can_assign: bool) {
+        match self.classes.last() {
+            None => self.error_at_previous("Can't use 'super' outside of a class."),
+            Some(class) if !class.has_superclass => self.error_at_previous("Can't use 'super' in a class with no superclass."),
+            _ if self.method_kind() == FunctionKind::Static => self.error_at_previous("Can't use 'super' in a static method."),
+            _ => (),
+        }
+
+        self.consume(TokenKind::Dot, "Expect '.' after 'super'.");
+        self.consume(TokenKind::Identifier, "Expect superclass method name.");
+        let name = self.previous_source();
+
+        self.emit_named_variable("this".to_string(), false);
+        self.emit_named_variable("super".to_string(), false);
+        self.emit_byte(OpCode::GetSuper(name));
+    }
+
+    // NOTE: Enums and pattern matching.
+
+    /// `enum Shape { Circle(radius), Rect(width, height), Empty }` declares a constant enum. Its
+    /// variants are created like `Shape.Circle(2)` and `Shape.Empty`.
+    fn emit_enum_declaration(&mut self) {
+        let global = self.parse_variable("Expect enum name.");
+        let name = self.previous_source();
+        self.consume(TokenKind::LeftBrace, "Expect '{' before enum body.");
+
+        let mut variants: Vec<VariantDeclaration> = vec![];
+        while !self.check(TokenKind::RightBrace) && !self.check(TokenKind::EOF) {
+            self.consume(TokenKind::Identifier, "Expect variant name.");
+            let variant = self.previous_source();
+
+            if variants.iter().any(|declared| declared.name == variant) {
+                self.error_at_previous("Already a variant with this name in this enum.");
+            }
+
+            let mut fields = vec![];
+            if self.match_token(TokenKind::LeftParen) {
+                while !self.check(TokenKind::RightParen) && !self.check(TokenKind::EOF) {
+                    self.consume(TokenKind::Identifier, "Expect field name.");
+                    fields.push(self.previous_source());
+
+                    if !self.match_token(TokenKind::Comma) {
+                        break;
+                    }
+                }
+
+                self.consume(TokenKind::RightParen, "Expect ')' after variant fields.");
+            }
+
+            variants.push(VariantDeclaration { name: variant, fields });
+
+            if !self.match_token(TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.consume(TokenKind::RightBrace, "Expect '}' after enum body.");
+
+        let enumeration = Rc::new(Enum {
+            name: name.clone(),
+            doc: self.doc.take(),
+            variants,
+        });
+        self.enums.insert(name, enumeration.clone());
+        self.emit_byte(OpCode::Constant(Value::Object(Object::Enum(enumeration))));
+        self.define_variable(global);
+    }
+
+    /// ```text
+    /// match (shape) {
+    ///     Shape.Circle(r) => 3 * r * r,
+    ///     Shape.Rect(w, h) if w == h => w * w,
+    ///     Shape.Rect(w, h) => w * h,
+    ///     _ => 0,
+    /// }
+    /// ```
+    ///
+    /// The subject and the bindings of the arms are locals of a scope of their own, see
+    /// OP_BEGIN_MATCH. The arms are tried in order, and the first one that matches jumps to the
+    /// end with the value of its body, where OP_END_MATCH discards the locals below it:
+    ///
+    /// ```text
+    ///        <subject>    OP_BEGIN_MATCH
+    ///        <tests>      each one followed by OP_JUMP_IF_FALSE fail, OP_POP
+    ///        <bindings>
+    ///        <guard>      OP_JUMP_IF_FALSE guard, OP_POP
+    ///        <body>
+    ///        OP_JUMP end
+    /// guard: OP_POP, <pop the bindings>, OP_JUMP next
+    /// fail:  OP_POP
+    /// next:  <next arm>
+    ///        OP_NO_MATCH
+    /// end:   OP_END_MATCH
+    /// ```
+    fn emit_match(&mut self, _can_assign: bool) {
+        let line = self.previous_token.as_ref().map(|token| token.line).unwrap_or_default();
+
+        self.consume(TokenKind::LeftParen, "Expect '(' after 'match'.");
+        self.expression();
+        self.consume(TokenKind::RightParen, "Expect ')' after match subject.");
+
+        self.begin_scope();
+        let subject = self.current_compiler.locals.len();
+        self.emit_byte(OpCode::BeginMatch(subject));
+        self.current_compiler.add_local(&Token::new(TokenKind::Identifier, String::new(), line));
+        self.current_compiler.mark_initialized();
+
+        self.consume(TokenKind::LeftBrace, "Expect '{' before match arms.");
+
+        let mut coverage = MatchCoverage::default();
+        let mut exits = vec![];
+        while !self.check(TokenKind::RightBrace) && !self.check(TokenKind::EOF) {
+            exits.push(self.emit_match_arm(subject, &mut coverage));
+
+            if !self.match_token(TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.consume(TokenKind::RightBrace, "Expect '}' after match arms.");
+        self.verify_exhaustiveness(&coverage, line);
+
+        self.emit_bytes(OpCode::GetLocal(subject), OpCode::NoMatch);
+        for exit in exits {
+            self.patch_jump(exit);
+        }
+
+        self.emit_byte(OpCode::EndMatch(subject));
+        self.forget_scope();
+    }
+
+    /// Returns the jump to the end of the match, taken after the body of the arm.
+    fn emit_match_arm(&mut self, subject: usize, coverage: &mut MatchCoverage) -> usize {
+        let pattern = self.parse_pattern();
+        self.begin_scope();
+
+        let mut failures = vec![];
+        self.emit_pattern_tests(&pattern, subject, &mut vec![], &mut failures);
+        self.emit_pattern_bindings(&pattern, subject, &mut vec![]);
+
+        let guard = self.match_token(TokenKind::If).then(|| {
+            self.expression();
+            let jump = self.emit_jump(OpCode::JumpIfFalse(0));
+            self.emit_byte(OpCode::Pop);
+            jump
+        });
+
+        self.consume(TokenKind::Arrow, "Expect '=>' after match pattern.");
+        self.expression();
+        let exit = self.emit_jump(OpCode::Jump(0));
+
+        let next = match guard {
+            Some(jump) => {
+                self.patch_jump(jump);
+                self.emit_byte(OpCode::Pop);
+                self.end_scope();
+                Some(self.emit_jump(OpCode::Jump(0)))
+            }
+            None => {
+                // NOTE: The arm has jumped to the end, where OP_END_MATCH discards its bindings.
+                self.forget_scope();
+                None
+            }
+        };
+
+        if !failures.is_empty() {
+            for failure in failures {
+                self.patch_jump(failure);
+            }
+
+            self.emit_byte(OpCode::Pop);
+        }
+
+        if let Some(next) = next {
+            self.patch_jump(next);
+        }
+
+        coverage.add(&pattern, guard.is_some());
+        exit
+    }
+
+    fn parse_pattern(&mut self) -> Pattern {
+        if self.match_token(TokenKind::Identifier) {
+            let token = self.previous_token.clone();
+
+            if !self.match_token(TokenKind::Dot) {
+                return match token {
+                    Some(token) if token.source != "_" => Pattern::Binding(*token),
+                    _ => Pattern::Wildcard,
+                };
+            }
+
+            let enumeration = token.map(|token| token.source).unwrap_or_default();
+            self.consume(TokenKind::Identifier, "Expect variant name after '.'.");
+            let name = self.previous_source();
+
+            let mut fields = vec![];
+            if self.match_token(TokenKind::LeftParen) {
+                while !self.check(TokenKind::RightParen) && !self.check(TokenKind::EOF) {
+                    fields.push(self.parse_pattern());
+
+                    if !self.match_token(TokenKind::Comma) {
+                        break;
+                    }
+                }
+
+                self.consume(TokenKind::RightParen, "Expect ')' after variant patterns.");
+            }
+
+            self.verify_variant_pattern(&enumeration, &name, fields.len());
+            return Pattern::Variant { enumeration, name, fields };
+        }
+
+        let negate = self.match_token(TokenKind::Minus);
+        self.advance();
+
+        let value = match self.previous_kind() {
+            TokenKind::Number | TokenKind::Integer => self.number_value(),
+            TokenKind::String if !negate => Some(self.string_value()),
+            TokenKind::True if !negate => Some(Value::from(true)),
+            TokenKind::False if !negate => Some(Value::from(false)),
+            TokenKind::Nil if !negate => Some(Value::Nil),
+            _ => {
+                self.error_at_previous("Expect pattern.");
+                None
+            }
+        };
+
+        match value {
+            Some(Value::Int(integer)) if negate => Pattern::Literal(Value::Int(-integer)),
+            Some(Value::Number(number)) if negate => Pattern::Literal(Value::Number(-number)),
+            Some(value) => Pattern::Literal(value),
+            None => Pattern::Wildcard,
+        }
+    }
+
+    /// Patterns of enums declared in the script must name one of their variants, with a pattern
+    /// for each field. Other enums are only known at runtime.
+    fn verify_variant_pattern(&mut self, enumeration: &str, name: &str, field_count: usize) {
+        let Some(declaration) = self.enums.get(enumeration) else {
+            return;
+        };
+
+        match declaration.variant(name) {
+            None => self.error_at_previous(&format!("Enum '{enumeration}' has no variant '{name}'.")),
+            Some((_, variant)) if variant.fields.len() != field_count => {
+                let message = format!("Variant '{enumeration}.{name}' has {} fields.", variant.fields.len());
+                self.error_at_previous(&message);
+            }
+            _ => (),
+        }
+    }
+
+    /// Pushes the value a pattern is matched against: the subject, or a field of a variant nested
+    /// in it, following the field indexes in `path`.
+    fn emit_pattern_subject(&mut self, subject: usize, path: &[usize]) {
+        self.emit_byte(OpCode::GetLocal(subject));
+
+        for index in path {
+            self.emit_byte(OpCode::GetVariantField(*index));
+        }
+    }
+
+    /// Every test leaves a boolean on the stack, it is popped when the test passes. The jumps
+    /// taken when a test fails are collected so they can be patched to the next arm.
+    fn emit_pattern_tests(&mut self, pattern: &Pattern, subject: usize, path: &mut Vec<usize>, failures: &mut Vec<usize>) {
+        match pattern {
+            Pattern::Wildcard | Pattern::Binding(_) => (),
+            Pattern::Literal(value) => {
+                self.emit_pattern_subject(subject, path);
+                self.emit_bytes(OpCode::Constant(value.clone()), OpCode::Equal);
+                failures.push(self.emit_jump(OpCode::JumpIfFalse(0)));
+                self.emit_byte(OpCode::Pop);
+            }
+            Pattern::Variant { enumeration, name, fields } => {
+                self.emit_pattern_subject(subject, path);
+                self.emit_named_variable(enumeration.clone(), false);
+                self.emit_byte(OpCode::IsVariant(name.clone(), fields.len()));
+                failures.push(self.emit_jump(OpCode::JumpIfFalse(0)));
+                self.emit_byte(OpCode::Pop);
+
+                for (index, field) in fields.iter().enumerate() {
+                    path.push(index);
+                    self.emit_pattern_tests(field, subject, path, failures);
+                    path.pop();
+                }
+            }
+        }
+    }
+
+    /// The bound values become locals of the arm, they are only pushed once every test passed.
+    fn emit_pattern_bindings(&mut self, pattern: &Pattern, subject: usize, path: &mut Vec<usize>) {
+        match pattern {
+            Pattern::Binding(token) => {
+                let scope_depth = self.current_compiler.scope_depth;
+                let already_bound = self
+                    .current_compiler
+                    .locals
+                    .iter()
+                    .any(|local| local.depth == Some(scope_depth) && local.name.source == token.source);
+
+                if already_bound {
+                    self.error_at(Box::new(token.clone()), "Already a binding with this name in this pattern.");
+                }
+
+                self.emit_pattern_subject(subject, path);
+                self.current_compiler.add_local(token);
+                self.current_compiler.mark_initialized();
+            }
+            Pattern::Variant { fields, .. } => {
+                for (index, field) in fields.iter().enumerate() {
+                    path.push(index);
+                    self.emit_pattern_bindings(field, subject, path);
+                    path.pop();
+                }
+            }
+            Pattern::Wildcard | Pattern::Literal(_) => (),
+        }
+    }
+
+    /// Warns when the arms of a match on an enum declared in the script leave variants
+    /// unhandled, and there is no arm matching any value.
+    fn verify_exhaustiveness(&self, coverage: &MatchCoverage, line: Line) {
+        if coverage.catch_all {
+            return;
+        }
+
+        let Some((name, enumeration)) = coverage
+            .enumeration
+            .as_ref()
+            .and_then(|name| self.enums.get(name).map(|enumeration| (name, enumeration)))
+        else {
+            return;
+        };
+
+        let missing: Vec<&str> = enumeration
+            .variants
+            .iter()
+            .filter(|variant| !coverage.variants.contains(&variant.name))
+            .map(|variant| variant.name.as_str())
+            .collect();
+
+        if !missing.is_empty() {
+            self.warning_at(line, &format!("Match on '{name}' doesn't handle {}.", missing.join(", ")));
+        }
+    }
+
+    // NOTE: Modules.
+
+    /// `import "path/to/module.lox" as name;` runs the module, unless it was already imported, and
+    /// binds it to a variable like a `var` declaration does.
+    fn emit_import_declaration(&mut self) {
+        self.consume(TokenKind::String, "Expect module path after 'import'.");
+        let path = self.previous_source();
+        let path = path.get(1..path.len().saturating_sub(1)).unwrap_or_default().to_string();
+
+        if !self.check(TokenKind::Identifier) || self.current_token.as_ref().is_some_and(|token| token.source != "as") {
+            self.error_at_previous("Expect 'as' after module path.");
+            return;
+        }
+
+        self.advance();
+        let variable = self.parse_variable("Expect module name after 'as'.");
+        self.consume(TokenKind::Semicolon, "Expect ';' after import.");
+
+        self.emit_byte(OpCode::Import(path));
+        self.define_variable(variable);
+    }
+
+    /// `export` marks a top level declaration as visible to the modules that import this one.
+    fn emit_export_declaration(&mut self) {
+        if self.current_compiler.scope_depth > 0 {
+            self.error_at_previous("Can only export top level declarations.");
+            return;
+        }
+
+        let declaration = self.current_kind();
+        let exportable = [
+            TokenKind::Fun,
+            TokenKind::Var,
+            TokenKind::Const,
+            TokenKind::Let,
+            TokenKind::Class,
+            TokenKind::Enum,
+        ];
+        if !exportable.contains(&declaration) {
+            self.error_at_previous("Expect declaration after 'export'.");
+            return;
+        }
+
+        self.advance();
+        let name = self.current_token.as_ref().map(|token| token.source.clone()).unwrap_or_default();
+
+        if declaration == TokenKind::Var && (self.current_kind() != TokenKind::Identifier || self.peek_kind(1) == TokenKind::Comma) {
+            self.error_at_previous("Can only export a single variable.");
+            return;
+        }
+
+        match declaration {
+            TokenKind::Fun => self.emit_fun_declaration(),
+            TokenKind::Class => self.emit_class_declaration(),
+            TokenKind::Enum => self.emit_enum_declaration(),
+            TokenKind::Const | TokenKind::Let => self.emit_const_declaration(),
+            _ => self.emit_var_declaration(),
+        }
+
+        self.emit_byte(OpCode::Export(name));
+    }
+
+    // NOTE: Variables.
+
+    /// First, the method compiles the variable name through parse_variable(). Then we look for an
+    /// = followed by a initializer expression. If the user doesn't initialize the variable. the
+    /// compiler implicity initializes it to nil. Either way, we expect the statement to be
+    /// terminated with a semicolon.
+    fn emit_var_declaration(&mut self) {
+        if matches!(self.current_kind(), TokenKind::LeftBracket | TokenKind::LeftBrace) || self.peek_kind(1) == TokenKind::Comma {
+            self.emit_destructuring_declaration();
+            return;
+        }
+
+        let global = self.parse_variable("Expect variable name.");
+
+        if self.match_token(TokenKind::Equal) {
+            self.expression();
+        } else {
+            self.emit_byte(OpCode::Nil);
+        }
+
+        self.consume(TokenKind::Semicolon, "Expect ';' after variable declaration.");
+        self.define_variable(global);
+    }
+
+    /// `const NAME = value;` declares a variable that can't be assigned, `let` is a synonym.
+    /// Constants initialized with a literal are inlined where they are read.
+    fn emit_const_declaration(&mut self) {
+        let global = self.parse_variable("Expect constant name.");
+        self.consume(TokenKind::Equal, "Expect '=' after constant name.");
+
+        let start = self.current_chunk().codes.len();
+        self.expression();
+        let value = self.literal_value(start);
+        self.consume(TokenKind::Semicolon, "Expect ';' after constant declaration.");
+
+        match global {
+            Some(identifier) => {
+                self.constants.insert(identifier.clone(), value);
+                self.emit_byte(OpCode::DefineConstant(identifier));
+            }
+            None => {
+                self.current_compiler.mark_initialized();
+
+                if let Some(local) = self.current_compiler.locals.last_mut() {
+                    local.is_constant = true;
+                    local.value = value;
+                }
+            }
+        }
+    }
+
+    /// Value of the expression compiled from the given instruction, if it is a single literal.
+    fn literal_value(&mut self, start: usize) -> Option<Value> {
+        match &self.current_chunk().codes[start..] {
+            [Code(OpCode::Constant(value), _)] => Some(value.clone()),
+            [Code(OpCode::True, _)] => Some(Value::from(true)),
+            [Code(OpCode::False, _)] => Some(Value::from(false)),
+            [Code(OpCode::Nil, _)] => Some(Value::Nil),
+            _ => None,
+        }
+    }
+
+    /// Constants can't be assigned, whether they are locals, captured variables or globals of
+    /// the script. Globals declared by other scripts are checked when the assignment runs.
+    fn verify_assignable(&mut self, name: &str) {
+        let local = std::iter::once(&self.current_compiler)
+            .chain(self.enclosing_compilers.iter().rev())
+            .find_map(|compiler| compiler.resolve_local(name).map(|slot| compiler.locals[slot].is_constant));
+
+        if local.unwrap_or_else(|| self.constants.contains_key(name)) {
+            self.error_at_previous(&format!("Can't assign to constant '{name}'."));
+        }
+    }
+
+    fn verify_not_constant(&mut self, name: &str) {
+        if self.constants.contains_key(name) {
+            self.error_at_previous(&format!("Can't redefine constant '{name}'."));
+        }
+    }
+
+    /// `var [a, b] = list;` and `var a, b = list;` declare a variable for each element of a list,
+    /// `var {x, y: name} = object;` one for each property of an object or key of a map. Locals
+    /// read the value from a hidden local below them, globals from the top of the stack.
+    fn emit_destructuring_declaration(&mut self) {
+        let is_object = self.match_token(TokenKind::LeftBrace);
+        let is_bracketed = is_object || self.match_token(TokenKind::LeftBracket);
+
+        // NOTE: Each target is the variable and the property it is read from, if any.
+        let mut targets: Vec<(Box<Token>, Option<String>)> = vec![];
+
+        loop {
+            self.consume(TokenKind::Identifier, "Expect variable name.");
+            let mut name = self.previous_token.clone();
+            let mut property = None;
+
+            if is_object {
+                property = Some(self.previous_source());
+
+                if self.match_token(TokenKind::Colon) {
+                    self.consume(TokenKind::Identifier, "Expect variable name after ':'.");
+                    name = self.previous_token.clone();
+                }
+            }
+
+            if self.current_compiler.scope_depth == 0 {
+                self.verify_not_constant(&self.previous_source());
+            }
+
+            if let Some(name) = name {
+                targets.push((name, property));
+            }
+
+            if !self.match_token(TokenKind::Comma) {
+                break;
+            }
+        }
+
+        match (is_object, is_bracketed) {
+            (true, _) => self.consume(TokenKind::RightBrace, "Expect '}' after destructuring pattern."),
+            (false, true) => self.consume(TokenKind::RightBracket, "Expect ']' after destructuring pattern."),
+            _ => (),
+        }
+
+        self.consume(TokenKind::Equal, "Expect '=' after destructuring pattern.");
+        self.expression();
+        self.consume(TokenKind::Semicolon, "Expect ';' after variable declaration.");
+
+        let is_global = self.current_compiler.scope_depth == 0;
+        let source = self.current_compiler.locals.len();
+
+        if !is_global {
+            self.current_compiler.add_local(&Token::new(TokenKind::Identifier, String::new(), 0));
+            self.current_compiler.mark_initialized();
+        }
+
+        for (index, (name, property)) in targets.into_iter().enumerate() {
+            match is_global {
+                true => self.emit_byte(OpCode::Dup),
+                false => self.emit_byte(OpCode::GetLocal(source)),
+            }
+
+            match property {
+                Some(property) => self.emit_byte(OpCode::Destructure(property)),
+                None => self.emit_bytes(OpCode::Constant(Value::Int(index as i64)), OpCode::GetIndex),
+            }
+
+            if is_global {
+                self.emit_byte(OpCode::DefineGlobal(name.source));
+            } else {
+                self.declare_local(&name);
+                self.current_compiler.mark_initialized();
+            }
+        }
+
+        if is_global {
+            self.emit_byte(OpCode::Pop);
+        }
+    }
+
+    /// Consumes the variable name. Locals are registered in the current scope and return None,
+    /// globals return the identifier that OP_DEFINE_GLOBAL will use.
+    fn parse_variable(&mut self, message: &str) -> Option<String> {
+        self.consume(TokenKind::Identifier, message);
+
+        self.declare_variable();
+        if self.current_compiler.scope_depth > 0 {
+            return None;
+        }
+
+        let identifier = self.previous_source();
+        self.verify_not_constant(&identifier);
+        Some(identifier)
+    }
+
+    fn emit_variable(&mut self, can_assign: bool) {
+        let name = self.previous_source();
+        self.emit_named_variable(name, can_assign);
+    }
+
+    /// Resolves the identifier as a local slot, an upvalue or a global name.
+    ///
+    /// Since assignment is the lowest precedence expression, the only time we allow an assignment
+    /// is when parsing an assignment expression or top level expression like in an expression
+    /// statement.
+    fn emit_named_variable(&mut self, name: String, can_assign: bool) {
+        if can_assign && self.check(TokenKind::Equal) {
+            self.verify_assignable(&name);
+        }
+
+        let (get_op, set_op) = self.variable_ops(name);
+
+        if can_assign && self.match_token(TokenKind::Equal) {
+            self.expression();
+            self.emit_byte(set_op);
+        } else {
+            self.emit_byte(get_op);
+        }
+    }
+
+    /// Instructions that read and assign the variable with the given name. Constants with a
+    /// literal value are read by loading the value itself.
+    fn variable_ops(&mut self, name: String) -> (OpCode, OpCode) {
+        if let Some(slot) = self.resolve_local(&name) {
+            let get_op = match &self.current_compiler.locals[slot].value {
+                Some(value) => OpCode::Constant(value.clone()),
+                None => OpCode::GetLocal(slot),
+            };
+
+            (get_op, OpCode::SetLocal(slot))
+        } else if let Some(index) = self.resolve_upvalue(&name) {
+            (OpCode::GetUpvalue(index), OpCode::SetUpvalue(index))
+        } else {
+            let get_op = match self.constants.get(&name) {
+                Some(Some(value)) => OpCode::Constant(value.clone()),
+                _ => OpCode::GetGlobal(name.clone()),
+            };
+
+            (get_op, OpCode::SetGlobal(name))
+        }
+    }
+
+    /// Looks for a local of the function being compiled.
+    fn resolve_local(&mut self, name: &str) -> Option<usize> {
+        let slot = self.current_compiler.resolve_local(name)?;
+
+        if self.current_compiler.locals[slot].depth.is_none() {
+            self.error_at_previous("Can't read local variable in its own initializer.");
+        }
+
+        Some(slot)
+    }
+
+    /// Looks for a local of the enclosing functions. The function being compiled is pushed with
+    /// the enclosing ones so the whole chain can be walked from the innermost function.
+    fn resolve_upvalue(&mut self, name: &str) -> Option<usize> {
+        let current = std::mem::take(&mut self.current_compiler);
+        self.enclosing_compilers.push(current);
+
+        let innermost = self.enclosing_compilers.len() - 1;
+        let upvalue = Self::resolve_upvalue_at(&mut self.enclosing_compilers, innermost, name);
+
+        self.current_compiler = self.enclosing_compilers.pop().unwrap_or_default();
+        upvalue
+    }
+
+    /// A local of the directly enclosing function is captured from its stack slot. A variable
+    /// further out is first captured by the enclosing function, and then from its upvalues.
+    fn resolve_upvalue_at(compilers: &mut [Compiler], depth: usize, name: &str) -> Option<usize> {
+        let enclosing = depth.checked_sub(1)?;
+
+        if let Some(slot) = compilers[enclosing].resolve_local(name) {
+            compilers[enclosing].locals[slot].is_captured = true;
+            return Some(compilers[depth].add_upvalue(UpvalueSource { is_local: true, index: slot }));
+        }
+
+        let index = Self::resolve_upvalue_at(compilers, enclosing, name)?;
+        Some(compilers[depth].add_upvalue(UpvalueSource { is_local: false, index }))
+    }
+
+    fn define_variable(&mut self, global: Option<String>) {
+        match global {
+            Some(identifier) => self.emit_byte(OpCode::DefineGlobal(identifier)),
+            None => self.current_compiler.mark_initialized(),
+        }
+    }
+
+    fn declare_variable(&mut self) {
+        // If we're in the top-level global scope, we just bail out.
+        if self.current_compiler.scope_depth == 0 {
+            return;
+        }
+
+        // For local variables it needs to remember that the variable exists.
+        if let Some(name) = self.previous_token.clone() {
+            self.declare_local(&name);
+        }
+    }
+
+    fn declare_local(&mut self, name: &Token) {
+        // Shadowing verification.
+        let scope_depth = self.current_compiler.scope_depth;
+        let already_declared = self
+            .current_compiler
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth.is_none_or(|depth| depth >= scope_depth))
+            .any(|local| local.name.source == name.source);
+
+        if already_declared {
+            self.error_at(Box::new(name.clone()), "Already a variable with this name in this scope.");
+        }
+
+        self.current_compiler.add_local(name);
+    }
+
+    // NOTE: Block statements.
+
+    /// It keeps parsing declarations and statements until it hits the closing brace. As we do with
+    /// any loop in the parser, we also checl for the end of the token stream.
+    fn emit_block(&mut self) {
+        while !self.check(TokenKind::RightBrace) && !self.check(TokenKind::EOF) {
+            self.emit_declaration();
+        }
+
+        self.consume(TokenKind::RightBrace, "Expect '}' after block.");
     }
 
     fn begin_scope(&mut self) {
         self.current_compiler.scope_depth += 1;
     }
 
+    /// Discards the locals declared in the scope that just ended, popping their stack slots.
     fn end_scope(&mut self) {
         self.current_compiler.scope_depth -= 1;
+        let scope_depth = self.current_compiler.scope_depth;
 
-        // WARNING: check number
-        // TODO: CHECK FUNCTIONAL METHOD
-        let mut pops = 0;
-
-        for local in self.current_compiler.locals.iter().rev() {
-            if self.current_compiler.count() > 0 && local.depth > self.current_compiler.scope_depth {
-                pops += 1;
+        while let Some(local) = self.current_compiler.locals.last() {
+            if local.depth.is_some_and(|depth| depth <= scope_depth) {
+                break;
             }
-        }
 
-        self.current_compiler.scope_depth -= pops;
+            let local = self.current_compiler.locals.pop();
 
-        for _ in 1..=pops {
-             self.emit_byte(OpCode::Pop);
+            match local {
+                Some(local) if local.is_captured => self.emit_byte(OpCode::CloseUpvalue),
+                _ => self.emit_byte(OpCode::Pop),
+            }
         }
+    }
 
+    /// Ends a scope whose locals are already gone from the stack.
+    fn forget_scope(&mut self) {
+        self.current_compiler.scope_depth -= 1;
+        let scope_depth = self.current_compiler.scope_depth;
 
+        let locals = &mut self.current_compiler.locals;
+        while locals.last().is_some_and(|local| local.depth.is_some_and(|depth| depth > scope_depth)) {
+            locals.pop();
+        }
     }
 }
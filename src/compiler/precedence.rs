@@ -10,9 +10,13 @@ pub enum Precedence {
     And = 3,
     Equality = 4,
     Comparison = 5,
-    Term = 6,
-    Factor = 7,
-    Unary = 8,
-    Call = 9,
-    Primary = 10,
+    BitOr = 6,
+    BitXor = 7,
+    BitAnd = 8,
+    Shift = 9,
+    Term = 10,
+    Factor = 11,
+    Unary = 12,
+    Call = 13,
+    Primary = 14,
 }
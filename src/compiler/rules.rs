@@ -1,6 +1,6 @@
 use super::precedence::Precedence;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ParseFn {
     Grouping,
     Unary,
@@ -9,6 +9,18 @@ pub enum ParseFn {
     Literal,
     String,
     Variable,
+    List,
+    Map,
+    Index,
+    Dot,
+    Call,
+    Function,
+    Yield,
+    And,
+    Or,
+    This,
+    Super,
+    Match,
 }
 
 #[derive(Debug)]
@@ -1,31 +1,110 @@
-use thiserror::Error;
-use std::path::Path;
-use crate::Line;
 use crate::Identifier;
+use crate::Line;
+use std::path::Path;
+use thiserror::Error;
 
-// #[derive(PartialEq, Error, Debug)]
-// pub enum CompileError {
-//     MissingSemicolon,
-//     MissingBrace
-// }
-
+#[derive(PartialEq, Error, Debug)]
+pub enum CompileError {
+    #[error("Failed to compile script.")]
+    Failed,
+}
 
 #[derive(PartialEq, Error, Debug)]
 pub enum InputError {
     #[error("Failed to read line")]
     ReadLine(),
     #[error("Failed to read {0}.")]
-    FileNotFound(Box<Path>)
+    FileNotFound(Box<Path>),
 }
 
 #[derive(PartialEq, Error, Debug)]
 pub enum RuntimeError {
     #[error("Operands must be a number. [line {0}] in script.")]
     ExpectedNumber(Line),
+    #[error("Operands must be integers. [line {0}] in script.")]
+    ExpectedInteger(Line),
+    #[error("Shift amount {0} is out of range. [line {1}] in script.")]
+    ShiftOutOfRange(i64, Line),
     #[error("Operands must be a number or string. [line {0}] in script.")]
     ExpectedNumberOrString(Line),
     #[error("Exepected value. [line {0}] in script.")]
     ExpectedValue(Line),
     #[error("Undefined variable '{0}'. [line {1}] in script.")]
     UndefinedVariable(Identifier, Line),
+    #[error("Can't assign to constant '{0}'. [line {1}] in script.")]
+    AssignToConstant(Identifier, Line),
+    #[error("Integer overflow. [line {0}] in script.")]
+    IntegerOverflow(Line),
+    #[error("Can only call functions and classes, got {0}. [line {1}] in script.")]
+    ExpectedCallable(&'static str, Line),
+    #[error("Invalid argument for {0}(): expected {1}. [line {2}] in script.")]
+    InvalidArgument(Identifier, String, Line),
+    #[error("Only lists, maps and strings can be indexed, got {0}. [line {1}] in script.")]
+    ExpectedIndexable(&'static str, Line),
+    #[error("List index must be a non-negative integer. [line {0}] in script.")]
+    ExpectedIndex(Line),
+    #[error("Index {0} out of bounds for length {1}. [line {2}] in script.")]
+    IndexOutOfBounds(usize, usize, Line),
+    #[error("Strings can't be longer than {0} bytes. [line {1}] in script.")]
+    StringTooLong(usize, Line),
+    #[error("Key {0} is not hashable. [line {1}] in script.")]
+    UnhashableKey(String, Line),
+    #[error("Can't compare {0} with {1}. [line {2}] in script.")]
+    Incomparable(&'static str, &'static str, Line),
+    #[error("Undefined key {0}. [line {1}] in script.")]
+    UndefinedKey(String, Line),
+    #[error("Access to '{0}' is not allowed. [line {1}] in script.")]
+    FileAccessDenied(String, Line),
+    #[error("Access to the environment variable '{0}' is not allowed. [line {1}] in script.")]
+    EnvAccessDenied(String, Line),
+    #[error("{0}. [line {1}] in script.")]
+    FileError(String, Line),
+    #[error("Invalid JSON at line {1}, column {2}: {0}. [line {3}] in script.")]
+    InvalidJson(String, usize, usize, Line),
+    #[error("Can't convert {0} to JSON. [line {1}] in script.")]
+    JsonUnsupported(String, Line),
+    #[error("Invalid format string: {0}. [line {1}] in script.")]
+    InvalidFormat(String, Line),
+    #[error("Invalid regular expression: {0}. [line {1}] in script.")]
+    InvalidRegex(String, Line),
+    #[error("Only lists, maps, sets, strings, iterators, coroutines and instances with iter() can be iterated, got {0}. [line {1}] in script.")]
+    ExpectedIterable(&'static str, Line),
+    #[error("Uncaught exception: {0}. [line {1}] in script.")]
+    UncaughtException(String, Line),
+    #[error("Could not open module \"{0}\". [line {1}] in script.")]
+    ModuleNotFound(String, Line),
+    #[error("Failed to compile module \"{0}\". [line {1}] in script.")]
+    ModuleCompileError(String, Line),
+    #[error("Import cycle detected: {0}. [line {1}] in script.")]
+    ImportCycle(String, Line),
+    #[error("Module '{0}' does not export '{1}'. [line {2}] in script.")]
+    UndefinedExport(String, Identifier, Line),
+    #[error("Only instances, classes and modules have properties, got {0}. [line {1}] in script.")]
+    ExpectedProperties(&'static str, Line),
+    #[error("Only instances have fields. [line {0}] in script.")]
+    ExpectedInstance(Line),
+    #[error("Undefined property '{0}'. [line {1}] in script.")]
+    UndefinedProperty(Identifier, Line),
+    #[error("Property '{0}' has a getter but no setter. [line {1}] in script.")]
+    ReadOnlyProperty(Identifier, Line),
+    #[error("Superclass must be a class. [line {0}] in script.")]
+    ExpectedSuperclass(Line),
+    #[error("Only enums have variants. [line {0}] in script.")]
+    ExpectedEnum(Line),
+    #[error("No match arm matched {0}. [line {1}] in script.")]
+    NoMatchingArm(String, Line),
+    #[error("Undefined method '{0}'. [line {1}] in script.")]
+    UndefinedMethod(Identifier, Line),
+    #[error("'{0}' expected {1} arguments but got {2}. [line {3}] in script.")]
+    WrongArity(Identifier, String, usize, Line),
+    #[error("Only lists can be spread into arguments. [line {0}] in script.")]
+    ExpectedSpreadList(Line),
+    #[error("Can't resume a coroutine that is already running. [line {0}] in script.")]
+    CoroutineRunning(Line),
+    #[error("Can only yield inside a coroutine. [line {0}] in script.")]
+    YieldOutsideCoroutine(Line),
+    #[error("Stack overflow. [line {0}] in script.")]
+    StackOverflow(Line),
+    #[error("Script exited with code {0}.")]
+    Exit(u8),
 }
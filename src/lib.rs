@@ -1,11 +1,13 @@
 pub mod chunk;
+pub mod error;
 pub mod opcode;
+pub mod regex;
 pub mod value;
-// mod cli;
-// mod compiler;
-mod error;
-mod scanner;
-mod vm;
+pub mod vm;
+pub mod cli;
+mod compiler;
+pub mod scanner;
+mod stdlib;
 
 use anyhow::Result;
 
@@ -13,20 +15,17 @@ type Line = u32;
 type Identifier = String;
 
 use crate::chunk::Chunk;
+use crate::error::CompileError;
+use crate::error::InputError;
+use std::fs::read_to_string;
+use std::path::Path;
 use vm::VirtualMachine;
-// use crate::compiler::parser::Parser;
-// use crate::scanner::token::TokenKind::EOF;
 
 pub fn compile(chunk: &mut Chunk, source: &str) -> Result<()> {
-    // let mut parser = Parser::new(chunk, source);
-    // parser.advance();
-    //
-    // while !parser.match_token(EOF) {
-    //     parser.emit_declaration();
-    // }
-    //
-    // parser.end_compiler();
-    // // !parser.had_error
+    if !compiler::compile(chunk, source) {
+        return Err(CompileError::Failed.into());
+    }
+
     Ok(())
 }
 
@@ -42,3 +41,13 @@ pub fn interpret(source: &str, debug: bool, vm: &mut VirtualMachine) -> Result<(
 
     Ok(())
 }
+
+/// Runs the script in the given file. Its imports are resolved relative to the file.
+pub fn interpret_file(path: &Path, debug: bool, vm: &mut VirtualMachine) -> Result<()> {
+    let Ok(source) = read_to_string(path) else {
+        return Err(InputError::FileNotFound(path.into()).into());
+    };
+
+    vm.set_script_path(path);
+    interpret(&source, debug, vm)
+}
@@ -1,7 +1,10 @@
+use crate::value::class::MemberKind;
+use crate::value::function::Function;
 use crate::value::Value;
 use derive_more::Debug;
+use std::rc::Rc;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum OpCode {
     #[debug("OP_RETURN")]
     Return,
@@ -21,6 +24,8 @@ pub enum OpCode {
     False,
     #[debug("OP_POP")]
     Pop,
+    #[debug("OP_DUP")]
+    Dup,
     #[debug("OP_ADD")]
     Add,
     #[debug("OP_SUBSTRACT")]
@@ -29,6 +34,18 @@ pub enum OpCode {
     Multiply,
     #[debug("OP_DIVIDE")]
     Divide,
+    #[debug("OP_BIT_AND")]
+    BitAnd,
+    #[debug("OP_BIT_OR")]
+    BitOr,
+    #[debug("OP_BIT_XOR")]
+    BitXor,
+    #[debug("OP_BIT_NOT")]
+    BitNot,
+    #[debug("OP_SHIFT_LEFT")]
+    ShiftLeft,
+    #[debug("OP_SHIFT_RIGHT")]
+    ShiftRight,
     #[debug("OP_EQUAL")]
     Equal,
     #[debug("OP_GREATER")]
@@ -37,8 +54,88 @@ pub enum OpCode {
     Less,
     #[debug("{: <16} {}", "OP_DEFINE_GLOBAL", _0)]
     DefineGlobal(String),
+    #[debug("{: <16} {}", "OP_DEFINE_CONSTANT", _0)]
+    DefineConstant(String),
     #[debug("{: <16} {}", "OP_GET_GLOBAL", _0)]
     GetGlobal(String),
     #[debug("{: <16} {}", "OP_SET_GLOBAL", _0)]
     SetGlobal(String),
+    #[debug("{: <16} {}", "OP_EXPORT", _0)]
+    Export(String),
+    #[debug("{: <16} {:?}", "OP_IMPORT", _0)]
+    Import(String),
+    #[debug("{: <16} {}", "OP_GET_PROPERTY", _0)]
+    GetProperty(String),
+    #[debug("{: <16} {}", "OP_SET_PROPERTY", _0)]
+    SetProperty(String),
+    #[debug("{: <16} {}", "OP_DESTRUCTURE", _0)]
+    Destructure(String),
+    #[debug("{: <16} {}", "OP_GET_SUPER", _0)]
+    GetSuper(String),
+    #[debug("{: <16} {}", "OP_GET_LOCAL", _0)]
+    GetLocal(usize),
+    #[debug("{: <16} {}", "OP_SET_LOCAL", _0)]
+    SetLocal(usize),
+    #[debug("{: <16} {}", "OP_GET_UPVALUE", _0)]
+    GetUpvalue(usize),
+    #[debug("{: <16} {}", "OP_SET_UPVALUE", _0)]
+    SetUpvalue(usize),
+    #[debug("OP_CLOSE_UPVALUE")]
+    CloseUpvalue,
+    #[debug("{: <16} {}", "OP_CLOSURE", _0)]
+    Closure(Rc<Function>),
+    #[debug("{: <16} {}", "OP_CLASS", _0)]
+    Class(String, Option<String>),
+    #[debug("OP_INHERIT")]
+    Inherit,
+    #[debug("{: <16} {} ({:?})", "OP_MEMBER", _0, _1)]
+    Member(String, MemberKind),
+    #[debug("{: <16} {} ({} fields)", "OP_IS_VARIANT", _0, _1)]
+    IsVariant(String, usize),
+    #[debug("{: <16} {}", "OP_GET_VARIANT_FIELD", _0)]
+    GetVariantField(usize),
+    #[debug("OP_NO_MATCH")]
+    NoMatch,
+    #[debug("{: <16} {}", "OP_BEGIN_MATCH", _0)]
+    BeginMatch(usize),
+    #[debug("{: <16} {}", "OP_END_MATCH", _0)]
+    EndMatch(usize),
+    #[debug("{: <16} {}", "OP_BUILD_LIST", _0)]
+    BuildList(usize),
+    #[debug("{: <16} {}", "OP_BUILD_MAP", _0)]
+    BuildMap(usize),
+    #[debug("OP_GET_INDEX")]
+    GetIndex,
+    #[debug("OP_SET_INDEX")]
+    SetIndex,
+    #[debug("{: <16} {}", "OP_CALL", _0)]
+    Call(u8),
+    #[debug("{: <16} {} (lists)", "OP_CALL_SPREAD", _0)]
+    CallSpread(u8),
+    #[debug("{: <16} {} ({} args)", "OP_INVOKE", _0, _1)]
+    Invoke(String, u8),
+    #[debug("{: <16} {} ({} lists)", "OP_INVOKE_SPREAD", _0, _1)]
+    InvokeSpread(String, u8),
+    #[debug("OP_GET_ITERATOR")]
+    GetIterator,
+    #[debug("{: <16} {} -> +{}", "OP_FOR_ITER", _0, _1)]
+    ForIter(usize, usize),
+    #[debug("{: <16} -{}", "OP_LOOP", _0)]
+    Loop(usize),
+    #[debug("{: <16} +{}", "OP_JUMP", _0)]
+    Jump(usize),
+    #[debug("{: <16} +{}", "OP_JUMP_IF_FALSE", _0)]
+    JumpIfFalse(usize),
+    #[debug("{: <16} {} -> +{}", "OP_DEFAULT_PARAMETER", _0, _1)]
+    DefaultParameter(usize, usize),
+    #[debug("{: <16} +{} ({} slots)", "OP_PUSH_HANDLER", _0, _1)]
+    PushHandler(usize, usize),
+    #[debug("OP_POP_HANDLER")]
+    PopHandler,
+    #[debug("OP_YIELD")]
+    Yield,
+    #[debug("OP_THROW")]
+    Throw,
+    #[debug("OP_END_FINALLY")]
+    EndFinally,
 }
@@ -0,0 +1,100 @@
+//! Regular expressions for the `re` module. Patterns are compiled to a Thompson NFA and run
+//! without backtracking, so matching is linear in the length of the text and no pattern can
+//! hang the VM.
+//!
+//! The syntax is a subset of the usual one: literals, `.`, classes like `[a-z]` and `[^0-9]`,
+//! `\d`, `\w`, `\s` and their negations, the anchors `^` and `$`, `\b` and `\B`, groups with
+//! `(...)`, `(?:...)` and `(?<name>...)`, alternation with `|`, and the repetitions `*`, `+`,
+//! `?`, `{n}`, `{n,}` and `{n,m}`, lazy when followed by `?`. Backreferences and lookarounds
+//! are not supported, they can't be matched in linear time.
+
+mod parser;
+mod program;
+
+pub use parser::is_word_char;
+use program::Instruction;
+
+/// Compiled pattern. Positions are character indexes, like the ones of string methods.
+#[derive(Debug)]
+pub struct Regex {
+    pattern: String,
+    program: Vec<Instruction>,
+    /// Names of the groups, starting with group 1.
+    group_names: Vec<Option<String>>,
+}
+
+/// Start and end of the whole match and of every group, None for the groups that didn't take
+/// part in the match.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let parsed = parser::parse(pattern)?;
+        let program = program::compile(&parsed.node)?;
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            program,
+            group_names: parsed.group_names,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Number of groups, not counting the whole match.
+    pub fn group_count(&self) -> usize {
+        self.group_names.len()
+    }
+
+    /// Named groups and their indexes.
+    pub fn named_groups(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.group_names
+            .iter()
+            .enumerate()
+            .filter_map(|(index, name)| name.as_deref().map(|name| (name, index + 1)))
+    }
+
+    /// First match starting at `start` or after it. An anchored search only tries `start`.
+    pub fn captures_at(&self, text: &[char], start: usize, anchored: bool) -> Option<Captures> {
+        let slot_count = (self.group_count() + 1) * 2;
+        let slots = program::execute(&self.program, slot_count, text, start, anchored)?;
+
+        Some(
+            slots
+                .chunks(2)
+                .map(|pair| match pair {
+                    [Some(start), Some(end)] => Some((*start, *end)),
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// Every match that doesn't overlap the previous one, from left to right. After an empty
+    /// match the search goes on one character further, so it can't find it again.
+    pub fn captures_iter<'a>(&'a self, text: &'a [char]) -> impl Iterator<Item = Captures> + 'a {
+        let mut start = 0;
+        let mut done = false;
+
+        std::iter::from_fn(move || {
+            if done || start > text.len() {
+                return None;
+            }
+
+            let Some(captures) = self.captures_at(text, start, false) else {
+                done = true;
+                return None;
+            };
+
+            let (match_start, match_end) = captures[0]?;
+            start = match match_start == match_end {
+                true => match_end + 1,
+                false => match_end,
+            };
+
+            Some(captures)
+        })
+    }
+}
@@ -0,0 +1,428 @@
+/// Largest count accepted in `{n}`, `{n,}` and `{n,m}`. Counted repetitions are expanded into
+/// copies of the repeated pattern, so the count is limited like the size of the program.
+const MAX_REPEAT: u32 = 1000;
+
+/// Deepest nesting of groups accepted in a pattern.
+const MAX_DEPTH: usize = 256;
+
+/// Syntax tree of a pattern.
+#[derive(Debug, Clone)]
+pub enum Node {
+    Empty,
+    Char(char),
+    /// `.`, any character but a newline.
+    Any,
+    Class(CharClass),
+    Assertion(Assertion),
+    /// Group in parentheses, with the index of its capture unless it is written `(?:...)`.
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+/// Condition on the position between two characters, it doesn't consume any.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assertion {
+    /// `^`, the start of the text.
+    Start,
+    /// `$`, the end of the text.
+    End,
+    /// `\b`, between a word character and something else.
+    WordBoundary,
+    /// `\B`, anywhere `\b` doesn't match.
+    NotWordBoundary,
+}
+
+/// Set of characters like `[a-z_]` or `\d`, stored as inclusive ranges.
+#[derive(Debug, Clone, Default)]
+pub struct CharClass {
+    pub ranges: Vec<(char, char)>,
+    pub negated: bool,
+}
+
+impl CharClass {
+    pub fn matches(&self, character: char) -> bool {
+        let found = self.ranges.iter().any(|&(low, high)| low <= character && character <= high);
+        found != self.negated
+    }
+
+    fn from_ranges(ranges: &[(char, char)], negated: bool) -> Self {
+        Self {
+            ranges: ranges.to_vec(),
+            negated,
+        }
+    }
+
+    /// Ranges of the characters that are not in the class, used for `\D`, `\W` and `\S` inside
+    /// brackets.
+    fn complement_ranges(&self) -> Vec<(char, char)> {
+        let mut ranges = self.ranges.clone();
+        ranges.sort();
+
+        let mut complement = Vec::new();
+        let mut next = Some('\0');
+        for (low, high) in ranges {
+            if let Some(start) = next {
+                if start < low {
+                    if let Some(end) = previous_char(low) {
+                        complement.push((start, end));
+                    }
+                }
+                if high >= start {
+                    next = next_char(high);
+                }
+            }
+        }
+
+        if let Some(start) = next {
+            complement.push((start, char::MAX));
+        }
+
+        complement
+    }
+}
+
+fn next_char(character: char) -> Option<char> {
+    match character as u32 + 1 {
+        0xD800 => Some('\u{E000}'),
+        code => char::from_u32(code),
+    }
+}
+
+fn previous_char(character: char) -> Option<char> {
+    match (character as u32).checked_sub(1)? {
+        0xDFFF => Some('\u{D7FF}'),
+        code => char::from_u32(code),
+    }
+}
+
+const DIGITS: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACES: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+
+/// Characters matched by `\w`, they decide where `\b` matches.
+pub fn is_word_char(character: char) -> bool {
+    character.is_ascii_alphanumeric() || character == '_'
+}
+
+/// Result of parsing a pattern: the tree and the names of the capture groups, indexed like the
+/// groups starting at 1.
+#[derive(Debug)]
+pub struct Parsed {
+    pub node: Node,
+    pub group_names: Vec<Option<String>>,
+}
+
+pub fn parse(pattern: &str) -> Result<Parsed, String> {
+    let mut parser = Parser {
+        characters: pattern.chars().collect(),
+        position: 0,
+        group_names: Vec::new(),
+        depth: 0,
+    };
+
+    let node = parser.parse_alternation()?;
+    if parser.position < parser.characters.len() {
+        return Err(format!("unmatched ')' at position {}", parser.position));
+    }
+
+    Ok(Parsed {
+        node,
+        group_names: parser.group_names,
+    })
+}
+
+struct Parser {
+    characters: Vec<char>,
+    position: usize,
+    group_names: Vec<Option<String>>,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.characters.get(self.position).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let character = self.peek();
+        self.position += 1;
+        character
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            return true;
+        }
+
+        false
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.parse_concatenation()?];
+        while self.eat('|') {
+            branches.push(self.parse_concatenation()?);
+        }
+
+        Ok(match branches.len() {
+            1 => branches.remove(0),
+            _ => Node::Alternate(branches),
+        })
+    }
+
+    fn parse_concatenation(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+
+        while let Some(character) = self.peek() {
+            if character == '|' || character == ')' {
+                break;
+            }
+
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_repetition(atom)?);
+        }
+
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        let position = self.position;
+
+        match self.advance() {
+            Some('(') => self.parse_group(),
+            Some('[') => self.parse_class().map(Node::Class),
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Assertion(Assertion::Start)),
+            Some('$') => Ok(Node::Assertion(Assertion::End)),
+            Some('\\') => self.parse_escape(false),
+            Some('*' | '+' | '?') => Err(format!("nothing to repeat at position {position}")),
+            Some('{') if self.counted_repetition_follows(position) => Err(format!("nothing to repeat at position {position}")),
+            Some(character) => Ok(Node::Char(character)),
+            None => Ok(Node::Empty),
+        }
+    }
+
+    /// `(...)`, `(?:...)`, and named groups written `(?<name>...)` or `(?P<name>...)`.
+    fn parse_group(&mut self) -> Result<Node, String> {
+        let start = self.position - 1;
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("groups nested too deeply".to_string());
+        }
+
+        let index = if self.eat('?') {
+            if self.eat(':') {
+                None
+            } else {
+                self.eat('P');
+                if !self.eat('<') {
+                    return Err(format!("unknown group flag at position {}", self.position));
+                }
+
+                let name = self.parse_group_name()?;
+                self.group_names.push(Some(name));
+                Some(self.group_names.len())
+            }
+        } else {
+            self.group_names.push(None);
+            Some(self.group_names.len())
+        };
+
+        let node = self.parse_alternation()?;
+        if !self.eat(')') {
+            return Err(format!("missing ')' for the group at position {start}"));
+        }
+
+        self.depth -= 1;
+        Ok(Node::Group(Box::new(node), index))
+    }
+
+    fn parse_group_name(&mut self) -> Result<String, String> {
+        let mut name = String::new();
+        loop {
+            match self.advance() {
+                Some('>') if !name.is_empty() => break,
+                Some(character) if is_word_char(character) => name.push(character),
+                _ => return Err(format!("invalid group name at position {}", self.position - 1)),
+            }
+        }
+
+        if self.group_names.iter().flatten().any(|existing| *existing == name) {
+            return Err(format!("duplicate group name '{name}'"));
+        }
+
+        Ok(name)
+    }
+
+    /// `[abc]`, `[^a-z]`, and the escapes `\d`, `\w` and `\s` and their negations inside
+    /// brackets. A `]` right after the opening bracket is a literal.
+    fn parse_class(&mut self) -> Result<CharClass, String> {
+        let start = self.position - 1;
+        let mut class = CharClass {
+            negated: self.eat('^'),
+            ..CharClass::default()
+        };
+
+        let mut first = true;
+        loop {
+            let low = match self.advance() {
+                None => return Err(format!("unterminated character class at position {start}")),
+                Some(']') if !first => break,
+                Some('\\') => match self.parse_escape(true)? {
+                    Node::Char(character) => character,
+                    Node::Class(escaped) => {
+                        match escaped.negated {
+                            true => class.ranges.extend(escaped.complement_ranges()),
+                            false => class.ranges.extend(escaped.ranges),
+                        }
+                        first = false;
+                        continue;
+                    }
+                    _ => return Err(format!("invalid escape in character class at position {}", self.position - 2)),
+                },
+                Some(character) => character,
+            };
+            first = false;
+
+            if self.peek() == Some('-') && !matches!(self.characters.get(self.position + 1), Some(']') | None) {
+                self.position += 1;
+                let high = match self.advance() {
+                    Some('\\') => match self.parse_escape(true)? {
+                        Node::Char(character) => character,
+                        _ => return Err(format!("invalid range end at position {}", self.position - 2)),
+                    },
+                    Some(character) => character,
+                    None => return Err(format!("unterminated character class at position {start}")),
+                };
+
+                if high < low {
+                    return Err(format!("invalid range {low}-{high}"));
+                }
+                class.ranges.push((low, high));
+            } else {
+                class.ranges.push((low, low));
+            }
+        }
+
+        Ok(class)
+    }
+
+    /// The character after a backslash. Any punctuation can be escaped to match it literally.
+    fn parse_escape(&mut self, in_class: bool) -> Result<Node, String> {
+        let position = self.position - 1;
+
+        let node = match self.advance() {
+            Some('d') => Node::Class(CharClass::from_ranges(DIGITS, false)),
+            Some('D') => Node::Class(CharClass::from_ranges(DIGITS, true)),
+            Some('w') => Node::Class(CharClass::from_ranges(WORD, false)),
+            Some('W') => Node::Class(CharClass::from_ranges(WORD, true)),
+            Some('s') => Node::Class(CharClass::from_ranges(SPACES, false)),
+            Some('S') => Node::Class(CharClass::from_ranges(SPACES, true)),
+            Some('b') if !in_class => Node::Assertion(Assertion::WordBoundary),
+            Some('B') if !in_class => Node::Assertion(Assertion::NotWordBoundary),
+            Some('n') => Node::Char('\n'),
+            Some('t') => Node::Char('\t'),
+            Some('r') => Node::Char('\r'),
+            Some(character) if character.is_ascii_punctuation() || character == ' ' => Node::Char(character),
+            Some(character) => return Err(format!("unknown escape '\\{character}' at position {position}")),
+            None => return Err("trailing backslash".to_string()),
+        };
+
+        Ok(node)
+    }
+
+    fn parse_repetition(&mut self, atom: Node) -> Result<Node, String> {
+        let mut node = atom;
+
+        loop {
+            let position = self.position;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') if self.counted_repetition_follows(position) => {
+                    self.position += 1;
+                    self.parse_counts()?
+                }
+                _ => return Ok(node),
+            };
+
+            if self.characters[position] != '{' {
+                self.position += 1;
+            }
+
+            if matches!(node, Node::Assertion(_) | Node::Empty) {
+                return Err(format!("nothing to repeat at position {position}"));
+            }
+            if matches!(node, Node::Repeat { .. }) {
+                return Err(format!("repeated repetition at position {position}"));
+            }
+
+            let greedy = !self.eat('?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    /// A `{` only starts a counted repetition when it looks like one, `a{b` matches literally.
+    fn counted_repetition_follows(&self, position: usize) -> bool {
+        let rest = &self.characters[position + 1..];
+        let Some(end) = rest.iter().position(|&character| character == '}') else {
+            return false;
+        };
+
+        let inside = &rest[..end];
+        !inside.is_empty()
+            && inside[0].is_ascii_digit()
+            && inside.iter().all(|character| character.is_ascii_digit() || *character == ',')
+            && inside.iter().filter(|&&character| character == ',').count() <= 1
+    }
+
+    /// The inside of `{n}`, `{n,}` or `{n,m}`, up to and including the closing brace.
+    fn parse_counts(&mut self) -> Result<(u32, Option<u32>), String> {
+        let min = self.parse_count()?;
+        let max = match self.eat(',') {
+            true if self.peek() == Some('}') => None,
+            true => Some(self.parse_count()?),
+            false => Some(min),
+        };
+        self.eat('}');
+
+        if let Some(max) = max {
+            if max < min {
+                return Err(format!("invalid repetition {{{min},{max}}}"));
+            }
+        }
+
+        Ok((min, max))
+    }
+
+    fn parse_count(&mut self) -> Result<u32, String> {
+        let mut count: u32 = 0;
+        while let Some(digit) = self.peek().and_then(|character| character.to_digit(10)) {
+            self.position += 1;
+            count = count.saturating_mul(10).saturating_add(digit);
+        }
+
+        if count > MAX_REPEAT {
+            return Err(format!("repetition count {count} is larger than {MAX_REPEAT}"));
+        }
+
+        Ok(count)
+    }
+}
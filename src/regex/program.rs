@@ -0,0 +1,268 @@
+use super::parser::is_word_char;
+use super::parser::Assertion;
+use super::parser::CharClass;
+use super::parser::Node;
+
+/// Most instructions a pattern may compile to, counted repetitions of big groups would
+/// otherwise use any amount of memory.
+const MAX_INSTRUCTIONS: usize = 100_000;
+
+/// Instruction of the NFA. The program is run by a Pike VM, which follows every thread in
+/// lockstep, one character at a time, so matching takes time linear in the length of the text
+/// whatever the pattern is.
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    Char(char),
+    Any,
+    Class(CharClass),
+    Assertion(Assertion),
+    /// Continues at both targets, the first one has the priority.
+    Split(usize, usize),
+    Jump(usize),
+    /// Records the current position in a capture slot. Group `n` uses the slots `2n` and
+    /// `2n + 1`.
+    Save(usize),
+    Match,
+}
+
+pub fn compile(node: &Node) -> Result<Vec<Instruction>, String> {
+    let mut compiler = Compiler { program: Vec::new() };
+
+    // NOTE: The whole match is group 0.
+    compiler.emit(Instruction::Save(0))?;
+    compiler.compile(node)?;
+    compiler.emit(Instruction::Save(1))?;
+    compiler.emit(Instruction::Match)?;
+
+    Ok(compiler.program)
+}
+
+struct Compiler {
+    program: Vec<Instruction>,
+}
+
+impl Compiler {
+    fn emit(&mut self, instruction: Instruction) -> Result<usize, String> {
+        if self.program.len() >= MAX_INSTRUCTIONS {
+            return Err("pattern is too large".to_string());
+        }
+
+        self.program.push(instruction);
+        Ok(self.program.len() - 1)
+    }
+
+    fn patch(&mut self, index: usize, instruction: Instruction) {
+        self.program[index] = instruction;
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Empty => (),
+            Node::Char(character) => _ = self.emit(Instruction::Char(*character))?,
+            Node::Any => _ = self.emit(Instruction::Any)?,
+            Node::Class(class) => _ = self.emit(Instruction::Class(class.clone()))?,
+            Node::Assertion(assertion) => _ = self.emit(Instruction::Assertion(*assertion))?,
+            Node::Group(node, None) => self.compile(node)?,
+            Node::Group(node, Some(index)) => {
+                self.emit(Instruction::Save(index * 2))?;
+                self.compile(node)?;
+                self.emit(Instruction::Save(index * 2 + 1))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            }
+            Node::Alternate(branches) => self.compile_alternation(branches)?,
+            Node::Repeat { node, min, max, greedy } => self.compile_repetition(node, *min, *max, *greedy)?,
+        }
+
+        Ok(())
+    }
+
+    /// Every branch but the last starts with a split to the next one, and ends with a jump
+    /// past the alternation.
+    fn compile_alternation(&mut self, branches: &[Node]) -> Result<(), String> {
+        let mut jumps = Vec::new();
+
+        for (index, branch) in branches.iter().enumerate() {
+            if index == branches.len() - 1 {
+                self.compile(branch)?;
+                break;
+            }
+
+            let split = self.emit(Instruction::Split(0, 0))?;
+            self.compile(branch)?;
+            jumps.push(self.emit(Instruction::Jump(0))?);
+            self.patch(split, Instruction::Split(split + 1, self.program.len()));
+        }
+
+        let end = self.program.len();
+        for jump in jumps {
+            self.patch(jump, Instruction::Jump(end));
+        }
+
+        Ok(())
+    }
+
+    /// `x{n,m}` is compiled as `n` copies of `x` followed by `m - n` optional ones, and `x{n,}`
+    /// as `n` copies followed by `x*`.
+    fn compile_repetition(&mut self, node: &Node, min: u32, max: Option<u32>, greedy: bool) -> Result<(), String> {
+        for _ in 0..min {
+            self.compile(node)?;
+        }
+
+        match max {
+            None => {
+                let split = self.emit(Instruction::Split(0, 0))?;
+                self.compile(node)?;
+                self.emit(Instruction::Jump(split))?;
+                let end = self.program.len();
+                self.patch(split, self.split(split + 1, end, greedy));
+            }
+            Some(max) => {
+                let mut splits = Vec::new();
+                for _ in min..max {
+                    splits.push(self.emit(Instruction::Split(0, 0))?);
+                    self.compile(node)?;
+                }
+
+                let end = self.program.len();
+                for split in splits {
+                    self.patch(split, self.split(split + 1, end, greedy));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lazy repetitions prefer to stop.
+    fn split(&self, repeat: usize, stop: usize, greedy: bool) -> Instruction {
+        match greedy {
+            true => Instruction::Split(repeat, stop),
+            false => Instruction::Split(stop, repeat),
+        }
+    }
+}
+
+/// Capture slots of a thread, the positions are character indexes.
+pub type Slots = Vec<Option<usize>>;
+
+struct Thread {
+    pc: usize,
+    slots: Slots,
+}
+
+/// Threads waiting at a position, in priority order. An instruction is only added once per
+/// position, the later threads that reach it could only find the same matches.
+struct ThreadList {
+    threads: Vec<Thread>,
+    seen: Vec<bool>,
+}
+
+impl ThreadList {
+    fn new(size: usize) -> Self {
+        Self {
+            threads: Vec::new(),
+            seen: vec![false; size],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.threads.clear();
+        self.seen.iter_mut().for_each(|seen| *seen = false);
+    }
+}
+
+/// Runs the program on the text from `start`. The leftmost match wins, and among the matches
+/// that start there the one the pattern prefers, like a backtracking engine would find.
+pub fn execute(program: &[Instruction], slot_count: usize, text: &[char], start: usize, anchored: bool) -> Option<Slots> {
+    let mut current = ThreadList::new(program.len());
+    let mut next = ThreadList::new(program.len());
+    let mut matched = None;
+
+    for position in start..=text.len() {
+        if matched.is_none() && (!anchored || position == start) {
+            add_thread(program, &mut current, 0, position, text, vec![None; slot_count]);
+        }
+
+        // NOTE: An unanchored search goes on with the next start, the threads of this one may
+        // have died on an assertion like `\b`.
+        if current.threads.is_empty() && (anchored || matched.is_some()) {
+            break;
+        }
+
+        for thread in std::mem::take(&mut current.threads) {
+            let character = text.get(position).copied();
+            let advances = match (&program[thread.pc], character) {
+                (Instruction::Char(expected), Some(character)) => *expected == character,
+                (Instruction::Any, Some(character)) => character != '\n',
+                (Instruction::Class(class), Some(character)) => class.matches(character),
+                (Instruction::Match, _) => {
+                    matched = Some(thread.slots);
+                    // NOTE: The remaining threads have a lower priority.
+                    break;
+                }
+                _ => false,
+            };
+
+            if advances {
+                add_thread(program, &mut next, thread.pc + 1, position + 1, text, thread.slots);
+            }
+        }
+
+        std::mem::swap(&mut current, &mut next);
+        next.clear();
+    }
+
+    matched
+}
+
+/// Adds the thread and follows its jumps, splits, saves and assertions, so the list only holds
+/// threads waiting on a character or a match.
+fn add_thread(program: &[Instruction], list: &mut ThreadList, pc: usize, position: usize, text: &[char], slots: Slots) {
+    let mut stack = vec![Thread { pc, slots }];
+
+    while let Some(Thread { pc, mut slots }) = stack.pop() {
+        if list.seen[pc] {
+            continue;
+        }
+        list.seen[pc] = true;
+
+        match &program[pc] {
+            Instruction::Jump(target) => stack.push(Thread { pc: *target, slots }),
+            Instruction::Split(first, second) => {
+                stack.push(Thread {
+                    pc: *second,
+                    slots: slots.clone(),
+                });
+                stack.push(Thread { pc: *first, slots });
+            }
+            Instruction::Save(slot) => {
+                if let Some(saved) = slots.get_mut(*slot) {
+                    *saved = Some(position);
+                }
+                stack.push(Thread { pc: pc + 1, slots });
+            }
+            Instruction::Assertion(assertion) => {
+                if check_assertion(*assertion, text, position) {
+                    stack.push(Thread { pc: pc + 1, slots });
+                }
+            }
+            _ => list.threads.push(Thread { pc, slots }),
+        }
+    }
+}
+
+fn check_assertion(assertion: Assertion, text: &[char], position: usize) -> bool {
+    let word_before = position > 0 && is_word_char(text[position - 1]);
+    let word_after = text.get(position).is_some_and(|&character| is_word_char(character));
+
+    match assertion {
+        Assertion::Start => position == 0,
+        Assertion::End => position == text.len(),
+        Assertion::WordBoundary => word_before != word_after,
+        Assertion::NotWordBoundary => word_before == word_after,
+    }
+}
@@ -9,13 +9,25 @@ pub struct Scanner {
     current: usize,
     line: u32,
     eof_reached: bool,
+    /// Emit whitespace and comments as tokens instead of skipping them, for tools like formatters.
+    trivia: bool,
+    /// Lines of the `///` comments read since the last token.
+    doc_lines: Vec<String>,
+    /// Nothing has been scanned yet, so a `#!` line is skipped like a comment.
+    at_start: bool,
 }
 
 impl Iterator for Scanner {
     type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.skip_whitespace();
+        if self.trivia {
+            if let Some(token) = self.make_trivia() {
+                return Some(token);
+            }
+        } else if let Some(error) = self.skip_trivia() {
+            return Some(error);
+        }
 
         self.start = self.current;
 
@@ -36,25 +48,40 @@ impl Iterator for Scanner {
             (Some('"'), _) => self.make_string(),
             (Some(digit), _) if digit.is_ascii_digit() => self.make_number(),
             (Some(character), _) if character.is_alphabetic() || character == '_' => self.make_identifier_or_keyword(),
+            (Some('#'), Some(character)) if character.is_alphabetic() || character == '_' => self.make_private_identifier(),
             // Single character
             (Some('('), _) => self.make_token(TokenKind::LeftParen),
             (Some(')'), _) => self.make_token(TokenKind::RightParen),
             (Some('{'), _) => self.make_token(TokenKind::LeftBrace),
             (Some('}'), _) => self.make_token(TokenKind::RightBrace),
+            (Some('['), _) => self.make_token(TokenKind::LeftBracket),
+            (Some(']'), _) => self.make_token(TokenKind::RightBracket),
+            (Some(':'), _) => self.make_token(TokenKind::Colon),
             (Some(';'), _) => self.make_token(TokenKind::Semicolon),
             (Some(','), _) => self.make_token(TokenKind::Comma),
+            (Some('.'), Some('.')) if self.peek(self.current + 1) == Some('.') => {
+                self.current += 2;
+                self.make_token(TokenKind::Ellipsis)
+            }
             (Some('.'), _) => self.make_token(TokenKind::Dot),
             (Some('-'), _) => self.make_token(TokenKind::Minus),
             (Some('+'), _) => self.make_token(TokenKind::Plus),
             (Some('/'), _) => self.make_token(TokenKind::Slash),
             (Some('*'), _) => self.make_token(TokenKind::Star),
+            (Some('&'), _) => self.make_token(TokenKind::Ampersand),
+            (Some('|'), _) => self.make_token(TokenKind::Pipe),
+            (Some('^'), _) => self.make_token(TokenKind::Caret),
+            (Some('~'), _) => self.make_token(TokenKind::Tilde),
             // Two characters match
             (Some('!'), Some('=')) => self.make_token(TokenKind::BangEqual),
             (Some('!'), _) => self.make_token(TokenKind::Bang),
             (Some('='), Some('=')) => self.make_token(TokenKind::EqualEqual),
+            (Some('='), Some('>')) => self.make_token(TokenKind::Arrow),
             (Some('='), _) => self.make_token(TokenKind::Equal),
+            (Some('<'), Some('<')) => self.make_token(TokenKind::LessLess),
             (Some('<'), Some('=')) => self.make_token(TokenKind::LessEqual),
             (Some('<'), _) => self.make_token(TokenKind::Less),
+            (Some('>'), Some('>')) => self.make_token(TokenKind::GreaterGreater),
             (Some('>'), Some('=')) => self.make_token(TokenKind::GreaterEqual),
             (Some('>'), _) => self.make_token(TokenKind::Greater),
             _ => self.make_error_token("Unexpected character"),
@@ -70,70 +97,164 @@ impl Scanner {
             start: 0,
             current: 0,
             eof_reached: false,
+            trivia: false,
+            doc_lines: Vec::new(),
+            at_start: true,
         }
     }
 
-    fn make_identifier_or_keyword(&mut self) -> Token {
-        while let Some(c) = self.peek(self.current) {
-            if c.is_alphanumeric() || c == '_' {
-                self.current += 1;
-            } else {
-                break;
-            }
+    /// Scanner that also emits `Whitespace`, `LineComment`, `BlockComment` and `DocComment`
+    /// tokens, so the source can be rebuilt from the tokens.
+    pub fn with_trivia(source: impl Into<String>) -> Self {
+        Self {
+            trivia: true,
+            ..Self::new(source)
         }
+    }
 
-        let kind = match self.source.get(self.start..self.current) {
+    fn make_identifier_or_keyword(&mut self) -> Token {
+        self.consume_identifier();
+
+        let kind = match self.source.get(self.byte_offset(self.start)..self.byte_offset(self.current)) {
             Some("and") => TokenKind::And,
             Some("class") => TokenKind::Class,
+            Some("const") => TokenKind::Const,
             Some("else") => TokenKind::Else,
+            Some("enum") => TokenKind::Enum,
             Some("if") => TokenKind::If,
+            Some("in") => TokenKind::In,
+            Some("let") => TokenKind::Let,
+            Some("import") => TokenKind::Import,
+            Some("export") => TokenKind::Export,
             Some("nil") => TokenKind::Nil,
+            Some("match") => TokenKind::Match,
             Some("or") => TokenKind::Or,
             Some("print") => TokenKind::Print,
+            Some("return") => TokenKind::Return,
             Some("super") => TokenKind::Super,
             Some("var") => TokenKind::Var,
             Some("while") => TokenKind::While,
+            Some("yield") => TokenKind::Yield,
             Some("false") => TokenKind::False,
             Some("for") => TokenKind::For,
             Some("fun") => TokenKind::Fun,
             Some("this") => TokenKind::This,
             Some("true") => TokenKind::True,
+            Some("throw") => TokenKind::Throw,
+            Some("try") => TokenKind::Try,
+            Some("catch") => TokenKind::Catch,
+            Some("finally") => TokenKind::Finally,
             _ => TokenKind::Identifier,
         };
 
         self.make_token(kind)
     }
 
-    fn make_number(&mut self) -> Token {
-        while let Some(possible_digit) = self.peek(self.current) {
-            if possible_digit.is_ascii_digit() {
+    /// Private class member names, like `#count`. The token includes the `#`.
+    fn make_private_identifier(&mut self) -> Token {
+        self.consume_identifier();
+        self.make_token(TokenKind::PrivateIdentifier)
+    }
+
+    fn consume_identifier(&mut self) {
+        while let Some(c) = self.peek(self.current) {
+            if c.is_alphanumeric() || c == '_' {
                 self.current += 1;
             } else {
                 break;
             }
         }
+    }
+
+    /// Scans decimal, hexadecimal (`0xFF`), binary (`0b1010`) and octal (`0o17`) literals, digits
+    /// can be separated with underscores like in `1_000_000`. Numbers with a fractional part or an
+    /// exponent, like `1.5` or `1e-9`, are floats and the rest are integers. A leading dot, like in
+    /// `.5`, has already been consumed when this method is called.
+    fn make_number(&mut self) -> Token {
+        let mut is_float = self.peek(self.start) == Some('.');
+
+        if self.peek(self.start) == Some('0') {
+            let radix = match self.peek(self.current) {
+                Some('x') | Some('X') => Some(16),
+                Some('b') | Some('B') => Some(2),
+                Some('o') | Some('O') => Some(8),
+                _ => None,
+            };
+
+            if let Some(radix) = radix {
+                self.current += 1;
+
+                if self.consume_digits(radix) == 0 {
+                    return self.make_error_token("Expect digits after radix prefix.");
+                }
+
+                return self.make_number_token(TokenKind::Integer);
+            }
+        }
+
+        self.consume_digits(10);
 
         match (self.peek(self.current), self.peek(self.current + 1)) {
-            (Some('.'), Some(possible_digit)) if possible_digit.is_ascii_digit() => self.current += 1,
+            (Some('.'), Some(possible_digit)) if possible_digit.is_ascii_digit() && !is_float => {
+                self.current += 1;
+                is_float = true;
+                self.consume_digits(10);
+            }
             _ => (),
         }
 
-        while let Some(possible_digit) = self.peek(self.current) {
-            if possible_digit.is_ascii_digit() {
-                self.current += 1;
-            } else {
+        if let Some('e') | Some('E') = self.peek(self.current) {
+            let exponent_start = match self.peek(self.current + 1) {
+                Some('+') | Some('-') => self.current + 2,
+                _ => self.current + 1,
+            };
+
+            if self.peek(exponent_start).is_some_and(|digit| digit.is_ascii_digit()) {
+                self.current = exponent_start;
+                is_float = true;
+                self.consume_digits(10);
+            }
+        }
+
+        self.make_number_token(if is_float { TokenKind::Number } else { TokenKind::Integer })
+    }
+
+    /// Consumes digits of the given radix and the underscores that follow a digit. Returns the
+    /// number of digits consumed.
+    fn consume_digits(&mut self, radix: u32) -> usize {
+        let mut digits = 0;
+
+        while let Some(character) = self.peek(self.current) {
+            let follows_digit = self.current > 0 && self.peek(self.current - 1).is_some_and(|previous| previous.is_digit(radix));
+
+            if character.is_digit(radix) {
+                digits += 1;
+            } else if character != '_' || !follows_digit {
                 break;
             }
+
+            self.current += 1;
+        }
+
+        digits
+    }
+
+    fn make_number_token(&mut self, kind: TokenKind) -> Token {
+        if self.peek(self.current - 1) == Some('_') {
+            return self.make_error_token("Numeric literal can't end with '_'.");
         }
 
-        self.make_token(TokenKind::Number)
+        self.make_token(kind)
     }
 
     fn make_string(&mut self) -> Token {
         while let Some(character) = self.peek(self.current) {
             match character {
                 '"' => break,
-                '\n' => self.line += 1,
+                '\n' => {
+                    self.line += 1;
+                    self.current += 1;
+                }
                 _ => self.current += 1,
             }
         }
@@ -147,48 +268,147 @@ impl Scanner {
         self.make_token(TokenKind::String)
     }
 
-    fn skip_whitespace(&mut self) {
-        while let (Some(a), Some(b)) = (self.peek(self.current), self.peek(self.current + 1)) {
-            match (a, b) {
-                ('/', '/') => {
-                    while let Some(c) = self.peek(self.current) {
-                        if c != '\n' && !self.is_at_end() {
-                            self.current += 1;
-                        } else {
-                            self.current += 1;
-                            break;
-                        }
-                    }
+    /// Skips whitespace and comments before the next token, keeping the text of `///` comments
+    /// for the token. Returns an error token for an unterminated block comment.
+    fn skip_trivia(&mut self) -> Option<Token> {
+        let mut error = None;
+
+        loop {
+            let start = self.current;
+
+            match self.consume_trivia() {
+                Some(Ok(TokenKind::DocComment)) => {
+                    let text: String = self.source.chars().skip(start + 3).take(self.current - start - 3).collect();
+                    self.doc_lines.push(text.strip_prefix(' ').unwrap_or(&text).to_string());
                 }
-                (' ', _) | ('\r', _) | ('\t', _) => self.current += 1,
-                ('\n', _) => {
-                    self.line += 1;
-                    self.current += 2;
+                Some(Ok(_)) => (),
+                Some(Err(token)) => {
+                    error = Some(token);
+                    break;
                 }
-                _ => break,
+                None => break,
             }
         }
 
-        self.source.drain(self.start..self.current);
+        self.source.drain(self.byte_offset(self.start)..self.byte_offset(self.current));
         self.start = 0;
         self.current = 0;
+
+        error
+    }
+
+    fn make_trivia(&mut self) -> Option<Token> {
+        self.start = self.current;
+
+        match self.consume_trivia()? {
+            Ok(kind) => Some(self.make_token(kind)),
+            Err(token) => Some(token),
+        }
+    }
+
+    /// Consumes one run of whitespace or one comment. Block comments `/* ... */` can be nested.
+    fn consume_trivia(&mut self) -> Option<Result<TokenKind, Token>> {
+        match (self.peek(self.current)?, self.peek(self.current + 1)) {
+            (' ' | '\r' | '\t' | '\n', _) => {
+                while let Some(character @ (' ' | '\r' | '\t' | '\n')) = self.peek(self.current) {
+                    if character == '\n' {
+                        self.line += 1;
+                    }
+
+                    self.current += 1;
+                }
+
+                Some(Ok(TokenKind::Whitespace))
+            }
+            ('#', Some('!')) if self.at_start && self.current == 0 => {
+                while self.peek(self.current).is_some_and(|c| c != '\n') {
+                    self.current += 1;
+                }
+
+                Some(Ok(TokenKind::LineComment))
+            }
+            ('/', Some('/')) => {
+                let is_doc = self.peek(self.current + 2) == Some('/') && self.peek(self.current + 3) != Some('/');
+
+                while self.peek(self.current).is_some_and(|c| c != '\n') {
+                    self.current += 1;
+                }
+
+                Some(Ok(if is_doc { TokenKind::DocComment } else { TokenKind::LineComment }))
+            }
+            ('/', Some('*')) => {
+                let line = self.line;
+                let mut depth = 0;
+
+                loop {
+                    match (self.peek(self.current), self.peek(self.current + 1)) {
+                        (Some('/'), Some('*')) => {
+                            depth += 1;
+                            self.current += 2;
+                        }
+                        (Some('*'), Some('/')) => {
+                            depth -= 1;
+                            self.current += 2;
+
+                            if depth == 0 {
+                                return Some(Ok(TokenKind::BlockComment));
+                            }
+                        }
+                        (Some(character), _) => {
+                            if character == '\n' {
+                                self.line += 1;
+                            }
+
+                            self.current += 1;
+                        }
+                        (None, _) => {
+                            return Some(Err(Token::new(TokenKind::ERROR, "Unterminated block comment.".to_string(), line)));
+                        }
+                    }
+                }
+            }
+            _ => None,
+        }
     }
 
     fn peek(&self, index: usize) -> Option<char> {
         self.source.chars().nth(index)
     }
 
+    /// The scanner positions count characters, this converts them to byte offsets in the source.
+    fn byte_offset(&self, index: usize) -> usize {
+        self.source
+            .char_indices()
+            .nth(index)
+            .map(|(offset, _)| offset)
+            .unwrap_or(self.source.len())
+    }
+
     fn is_at_end(&self) -> bool {
         self.source.chars().nth(self.current).is_none()
     }
 
     fn make_token(&mut self, kind: TokenKind) -> Token {
         match kind {
-            TokenKind::BangEqual | TokenKind::EqualEqual | TokenKind::LessEqual | TokenKind::GreaterEqual => self.current += 1,
+            TokenKind::BangEqual
+            | TokenKind::EqualEqual
+            | TokenKind::Arrow
+            | TokenKind::LessEqual
+            | TokenKind::GreaterEqual
+            | TokenKind::LessLess
+            | TokenKind::GreaterGreater => self.current += 1,
             _ => (),
         }
 
-        let token = Token::new(kind, String::from_iter(self.source.drain(self.start..self.current)), self.line);
+        self.at_start = false;
+
+        let lexeme = self.source.drain(self.byte_offset(self.start)..self.byte_offset(self.current));
+        let mut token = Token::new(kind, String::from_iter(lexeme), self.line);
+
+        if !self.doc_lines.is_empty() {
+            token.doc = Some(self.doc_lines.join("\n"));
+            self.doc_lines.clear();
+        }
 
         self.start = 0;
         self.current = 0;
@@ -6,57 +6,96 @@ pub struct Token {
     pub kind: TokenKind,
     pub source: String,
     pub line: u32,
+    /// Text of the `///` comments right before the token, without the slashes.
+    pub doc: Option<String>,
 }
 
 impl Token {
     pub fn new(kind: TokenKind, source: String, line: u32) -> Self {
-        Token { kind, source, line }
+        Token {
+            kind,
+            source,
+            line,
+            doc: None,
+        }
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Hash, Eq, Clone, Copy)]
 pub enum TokenKind {
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
+    Ellipsis,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
 
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    Arrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
 
     Identifier,
+    PrivateIdentifier,
     String,
     Number,
+    Integer,
 
     And,
     Class,
+    Const,
     Else,
+    Enum,
     False,
     Fun,
     For,
     If,
+    Import,
+    Export,
+    In,
+    Let,
     Nil,
+    Match,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
+    Catch,
+    Finally,
     Var,
     While,
+    Yield,
+
+    Whitespace,
+    LineComment,
+    BlockComment,
+    DocComment,
+
     ERROR,
     EOF,
 }
@@ -83,6 +122,9 @@ impl<'a> Display for TokenError<'a> {
 
 impl<'a> TokenError<'a> {
     pub fn new(token: &'a Token, message: impl Into<String>) -> Self {
-        Self { token, message: message.into() }
+        Self {
+            token,
+            message: message.into(),
+        }
     }
 }
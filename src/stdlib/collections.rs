@@ -0,0 +1,290 @@
+use crate::error::RuntimeError;
+use crate::value::object::Object;
+use crate::value::set::Set;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+fn invalid_argument(vm: &VirtualMachine, name: &str, expected: &str) -> anyhow::Error {
+    RuntimeError::InvalidArgument(name.to_string(), expected.to_string(), vm.current_line()).into()
+}
+
+/// Natives with optional arguments are variadic, this checks the maximum.
+fn check_arity(vm: &VirtualMachine, name: &str, arguments: &[Value], expected: &str, maximum: usize) -> Result<()> {
+    if arguments.len() > maximum {
+        let error = RuntimeError::WrongArity(name.to_string(), expected.to_string(), arguments.len(), vm.current_line());
+        return Err(error.into());
+    }
+
+    Ok(())
+}
+
+/// Items of a list or a set, copied so callbacks can modify the collection safely.
+fn items(vm: &VirtualMachine, name: &str, value: &Value) -> Result<Vec<Value>> {
+    match value {
+        Value::Object(Object::List(list)) => Ok(list.borrow().clone()),
+        Value::Object(Object::Set(set)) => Ok(set.borrow().iter().cloned().collect()),
+        _ => Err(invalid_argument(vm, name, "a list or a set")),
+    }
+}
+
+fn hashable_set(vm: &VirtualMachine, values: Vec<Value>) -> Result<Set> {
+    if let Some(value) = values.iter().find(|value| !value.is_hashable()) {
+        return Err(RuntimeError::UnhashableKey(value.repr(), vm.current_line()).into());
+    }
+
+    Ok(values.into_iter().collect())
+}
+
+/// `Set()` creates an empty set, `Set(items)` a set of the items of a list or a set.
+pub fn set(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    check_arity(vm, "Set", &arguments, "0 to 1", 1)?;
+
+    let values = match arguments.first() {
+        Some(collection) => items(vm, "Set", collection)?,
+        None => vec![],
+    };
+
+    Ok(hashable_set(vm, values)?.into())
+}
+
+/// Methods of sets. `add(value)` and `remove(value)` modify the set, `union(other)`,
+/// `intersection(other)` and `difference(other)` return a new one, and take a set or a list.
+pub fn invoke_set_method(vm: &mut VirtualMachine, set: &Rc<RefCell<Set>>, name: String, arguments: Vec<Value>) -> Result<Value> {
+    let expected_arity = match name.as_str() {
+        "len" | "values" => 0,
+        "add" | "remove" | "has" | "union" | "intersection" | "difference" => 1,
+        _ => return Err(RuntimeError::UndefinedMethod(name, vm.current_line()).into()),
+    };
+
+    if arguments.len() != expected_arity {
+        let expected = expected_arity.to_string();
+        return Err(RuntimeError::WrongArity(name, expected, arguments.len(), vm.current_line()).into());
+    }
+
+    let result = match name.as_str() {
+        "len" => Value::Int(set.borrow().len() as i64),
+        "values" => set.borrow().iter().cloned().collect::<Vec<Value>>().into(),
+        "has" => set.borrow().contains(&arguments[0]).into(),
+        "add" => {
+            let value = arguments[0].clone();
+            if !value.is_hashable() {
+                return Err(RuntimeError::UnhashableKey(value.repr(), vm.current_line()).into());
+            }
+
+            set.borrow_mut().insert(value);
+            Value::Nil
+        }
+        "remove" => set.borrow_mut().remove(&arguments[0]).into(),
+        "union" | "intersection" | "difference" => {
+            let other = hashable_set(vm, items(vm, &name, &arguments[0])?)?;
+            let set = set.borrow();
+
+            match name.as_str() {
+                "union" => set.union(&other),
+                "intersection" => set.intersection(&other),
+                _ => set.difference(&other),
+            }
+            .into()
+        }
+        _ => return Err(RuntimeError::UndefinedMethod(name, vm.current_line()).into()),
+    };
+
+    Ok(result)
+}
+
+/// Natural order of values: numbers by value, strings by their characters, booleans with false
+/// first, and lists item by item. Other values, and values of different types, can't be compared.
+fn compare(vm: &VirtualMachine, a: &Value, b: &Value) -> Result<Ordering> {
+    let ordering = match (a, b) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(b),
+        (Value::Int(_) | Value::Number(_), Value::Int(_) | Value::Number(_)) => {
+            let (a, b) = (a.as_f64().unwrap_or_default(), b.as_f64().unwrap_or_default());
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        }
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Object(Object::Str(a)), Value::Object(Object::Str(b))) => a.cmp(b),
+        (Value::Object(Object::List(a)), Value::Object(Object::List(b))) => {
+            let (a, b) = (a.borrow().clone(), b.borrow().clone());
+            for (a, b) in a.iter().zip(&b) {
+                match compare(vm, a, b)? {
+                    Ordering::Equal => continue,
+                    ordering => return Ok(ordering),
+                }
+            }
+
+            a.len().cmp(&b.len())
+        }
+        _ => return Err(RuntimeError::Incomparable(a.type_name(), b.type_name(), vm.current_line()).into()),
+    };
+
+    Ok(ordering)
+}
+
+/// Stable merge sort that stops at the first error of the comparison. The sort of the standard
+/// library can't return errors, and may panic when a Lox comparator isn't consistent.
+fn merge_sort<T: Clone>(items: &mut [T], compare: &mut impl FnMut(&T, &T) -> Result<Ordering>) -> Result<()> {
+    if items.len() <= 1 {
+        return Ok(());
+    }
+
+    let middle = items.len() / 2;
+    merge_sort(&mut items[..middle], compare)?;
+    merge_sort(&mut items[middle..], compare)?;
+
+    let mut merged = Vec::with_capacity(items.len());
+    let (mut left, mut right) = (0, middle);
+    while left < middle && right < items.len() {
+        // NOTE: Equal items keep their order, so only a strictly smaller right item goes first.
+        if compare(&items[right], &items[left])? == Ordering::Less {
+            merged.push(items[right].clone());
+            right += 1;
+        } else {
+            merged.push(items[left].clone());
+            left += 1;
+        }
+    }
+
+    merged.extend_from_slice(&items[left..middle]);
+    merged.extend_from_slice(&items[right..]);
+    items.clone_from_slice(&merged);
+
+    Ok(())
+}
+
+/// `sort(list, by?)` returns a sorted copy, equal items keep their order. Without a function the
+/// items are sorted in their natural order. A function that needs two arguments is a comparator,
+/// it returns a negative number when its first argument goes first, a positive one when it goes
+/// last, and zero when they are equal. Any other function that takes one argument is a key, the
+/// items are sorted by what it returns. Parameters with a default and rest parameters aren't
+/// needed, so `fun (a, b = 0)` and `fun (...values)` are keys. See needed_arguments().
+pub fn sort(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    check_arity(vm, "sort", &arguments, "1 to 2", 2)?;
+    let mut items = items(vm, "sort", &arguments[0])?;
+
+    match arguments.get(1) {
+        None | Some(Value::Nil) => merge_sort(&mut items, &mut |a, b| compare(vm, a, b))?,
+        Some(comparator) if matches!(needed_arguments(comparator), Some((2, _))) => merge_sort(&mut items, &mut |a, b| {
+            let result = vm.call_value(comparator.clone(), vec![a.clone(), b.clone()])?;
+            match result {
+                Value::Int(_) | Value::Number(_) => Ok(compare(vm, &result, &Value::Int(0))?),
+                _ => Err(invalid_argument(vm, "sort", "a comparator that returns a number")),
+            }
+        })?,
+        Some(key) if matches!(needed_arguments(key), Some((0 | 1, true))) => {
+            let mut keyed = Vec::with_capacity(items.len());
+            for item in items {
+                keyed.push((vm.call_value(key.clone(), vec![item.clone()])?, item));
+            }
+
+            merge_sort(&mut keyed, &mut |(a, _), (b, _)| compare(vm, a, b))?;
+            items = keyed.into_iter().map(|(_, item)| item).collect();
+        }
+        Some(_) => return Err(invalid_argument(vm, "sort", "a key function or a comparator")),
+    }
+
+    Ok(items.into())
+}
+
+/// Number of arguments a callable needs, and whether it accepts a single one. None if the value
+/// can't be called.
+fn needed_arguments(value: &Value) -> Option<(u8, bool)> {
+    let function = match value {
+        Value::Object(Object::Closure(closure)) => closure.function.clone(),
+        Value::Object(Object::BoundMethod(bound)) => bound.method.function.clone(),
+        Value::Object(Object::Class(class)) => match class.methods.borrow().get("init") {
+            Some(init) => init.function.clone(),
+            None => return Some((0, false)),
+        },
+        Value::Object(Object::Native(native)) => return Some((native.arity, native.arity == 1 || native.has_rest && native.arity == 0)),
+        _ => return None,
+    };
+
+    Some((function.required, function.accepts(1)))
+}
+
+/// Copy of the list with its items in the opposite order.
+pub fn reverse(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let mut items = items(vm, "reverse", &arguments[0])?;
+    items.reverse();
+
+    Ok(items.into())
+}
+
+/// `zip(a, b, ...)` pairs the items of the lists at the same index, like `[[a[0], b[0]], ...]`.
+/// It stops at the end of the shortest list.
+pub fn zip(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let lists = arguments.iter().map(|list| items(vm, "zip", list)).collect::<Result<Vec<Vec<Value>>>>()?;
+    let length = lists.iter().map(Vec::len).min().unwrap_or(0);
+
+    let zipped: Vec<Value> = (0..length)
+        .map(|index| lists.iter().map(|list| list[index].clone()).collect::<Vec<Value>>().into())
+        .collect();
+
+    Ok(zipped.into())
+}
+
+/// The items with their index, like `[[0, a], [1, b]]`.
+pub fn enumerate(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let pairs: Vec<Value> = items(vm, "enumerate", &arguments[0])?
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| vec![Value::Int(index as i64), item].into())
+        .collect();
+
+    Ok(pairs.into())
+}
+
+/// Sum of the numbers, an integer unless one of them is a float. The sum of no numbers is 0.
+pub fn sum(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let mut total = Value::Int(0);
+
+    for item in items(vm, "sum", &arguments[0])? {
+        total = match (&total, &item) {
+            (Value::Int(total), Value::Int(item)) => match total.checked_add(*item) {
+                Some(total) => Value::Int(total),
+                None => return Err(RuntimeError::IntegerOverflow(vm.current_line()).into()),
+            },
+            (_, Value::Int(_) | Value::Number(_)) => Value::Number(total.as_f64().unwrap_or_default() + item.as_f64().unwrap_or_default()),
+            _ => return Err(invalid_argument(vm, "sum", "a list of numbers")),
+        };
+    }
+
+    Ok(total)
+}
+
+/// Tells whether the predicate is true for some or every item, stopping as soon as the answer is
+/// known. Without a predicate the items themselves are tested.
+fn test_items(vm: &mut VirtualMachine, name: &str, arguments: Vec<Value>, wanted: bool) -> Result<Value> {
+    check_arity(vm, name, &arguments, "1 to 2", 2)?;
+
+    for item in items(vm, name, &arguments[0])? {
+        let result = match arguments.get(1) {
+            Some(predicate) => vm.call_value(predicate.clone(), vec![item])?,
+            None => item,
+        };
+
+        if result.is_falsey() != wanted {
+            return Ok(wanted.into());
+        }
+    }
+
+    Ok((!wanted).into())
+}
+
+pub fn any(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    test_items(vm, "any", arguments, true)
+}
+
+pub fn all(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    test_items(vm, "all", arguments, false)
+}
+
+/// The items without the repeated ones, in the order they first appear.
+pub fn unique(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let items = items(vm, "unique", &arguments[0])?;
+    Ok(hashable_set(vm, items)?.iter().cloned().collect::<Vec<Value>>().into())
+}
@@ -0,0 +1,39 @@
+use crate::error::RuntimeError;
+use crate::value::object::Object;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+
+/// Converts numbers and numeric strings to integers. Floats are truncated towards zero.
+pub fn int(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let invalid_argument = || RuntimeError::InvalidArgument("int".to_string(), "a number or a numeric string".to_string(), vm.current_line());
+
+    let integer = match &arguments[0] {
+        Value::Int(integer) => *integer,
+        Value::Number(number) if number.is_finite() && number.trunc() >= i64::MIN as f64 && number.trunc() < -(i64::MIN as f64) => {
+            number.trunc() as i64
+        }
+        Value::Number(number) if number.is_finite() => return Err(RuntimeError::IntegerOverflow(vm.current_line()).into()),
+        Value::Object(Object::Str(string)) => string.trim().parse::<i64>().map_err(|_| invalid_argument())?,
+        _ => return Err(invalid_argument().into()),
+    };
+
+    Ok(Value::Int(integer))
+}
+
+/// Converts numbers and numeric strings to floats.
+pub fn float(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let invalid_argument = || RuntimeError::InvalidArgument("float".to_string(), "a number or a numeric string".to_string(), vm.current_line());
+
+    let number = match &arguments[0] {
+        Value::Object(Object::Str(string)) => string.trim().parse::<f64>().map_err(|_| invalid_argument())?,
+        value => value.as_f64().ok_or_else(invalid_argument)?,
+    };
+
+    Ok(Value::Number(number))
+}
+
+/// Text of a value as shown inside collections, strings are quoted: `repr("a")` is `"a"`.
+pub fn repr(_vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    Ok(arguments[0].repr().into())
+}
@@ -0,0 +1,9 @@
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+
+/// Wraps a function in a coroutine, the function is called without arguments the first time the
+/// coroutine is resumed. It can then `yield` back to whoever resumed it.
+pub fn fiber(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    vm.create_fiber(arguments[0].clone())
+}
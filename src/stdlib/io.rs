@@ -0,0 +1,118 @@
+use super::function;
+use crate::error::RuntimeError;
+use crate::value::iterator::NativeIterator;
+use crate::value::module::Module;
+use crate::value::object::Object;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// The `io` module. Every path is checked against the file policy of the VM first, see
+/// `VirtualMachine::set_file_policy()`.
+pub fn module() -> Module {
+    Module::native(
+        "io",
+        vec![
+            function("io", "read_file", 1, read_file),
+            function("io", "write_file", 2, write_file),
+            function("io", "append", 2, append),
+            function("io", "lines", 1, lines),
+            function("io", "exists", 1, exists),
+            function("io", "list_dir", 1, list_dir),
+        ],
+    )
+}
+
+fn string_argument<'a>(vm: &VirtualMachine, name: &str, value: &'a Value) -> Result<&'a str> {
+    match value {
+        Value::Object(Object::Str(string)) => Ok(string),
+        _ => Err(RuntimeError::InvalidArgument(format!("io.{name}"), "a string".to_string(), vm.current_line()).into()),
+    }
+}
+
+fn readable_path(vm: &VirtualMachine, name: &str, arguments: &[Value]) -> Result<PathBuf> {
+    let path = string_argument(vm, name, &arguments[0])?;
+    vm.file_policy()
+        .check_read(path)
+        .ok_or_else(|| RuntimeError::FileAccessDenied(path.to_string(), vm.current_line()).into())
+}
+
+fn writable_path(vm: &VirtualMachine, name: &str, arguments: &[Value]) -> Result<PathBuf> {
+    let path = string_argument(vm, name, &arguments[0])?;
+    vm.file_policy()
+        .check_write(path)
+        .ok_or_else(|| RuntimeError::FileAccessDenied(path.to_string(), vm.current_line()).into())
+}
+
+fn file_error(vm: &VirtualMachine, action: &str, arguments: &[Value], error: std::io::Error) -> anyhow::Error {
+    RuntimeError::FileError(format!("Could not {action} '{}': {error}", arguments[0]), vm.current_line()).into()
+}
+
+fn read_file(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let path = readable_path(vm, "read_file", &arguments)?;
+    let content = std::fs::read_to_string(path).map_err(|error| file_error(vm, "read", &arguments, error))?;
+
+    Ok(content.into())
+}
+
+/// Creates the file or replaces its content.
+fn write_file(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let path = writable_path(vm, "write_file", &arguments)?;
+    let content = string_argument(vm, "write_file", &arguments[1])?;
+    std::fs::write(path, content).map_err(|error| file_error(vm, "write", &arguments, error))?;
+
+    Ok(Value::Nil)
+}
+
+/// Adds to the end of the file, creating it if needed.
+fn append(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let path = writable_path(vm, "append", &arguments)?;
+    let content = string_argument(vm, "append", &arguments[1])?;
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|error| file_error(vm, "append to", &arguments, error))?;
+
+    Ok(Value::Nil)
+}
+
+/// Iterator over the lines of a file, without their line endings. The lines are read as the
+/// loop asks for them, so big files aren't loaded at once.
+fn lines(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let path = readable_path(vm, "lines", &arguments)?;
+    let file = File::open(path).map_err(|error| file_error(vm, "read", &arguments, error))?;
+    let lines = BufReader::new(file).lines();
+
+    let iterator = NativeIterator::Lines(Rc::new(RefCell::new(lines)));
+    Ok(Value::Object(Object::Iterator(Rc::new(RefCell::new(iterator)))))
+}
+
+fn exists(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let path = readable_path(vm, "exists", &arguments)?;
+    Ok(path.exists().into())
+}
+
+/// Names of the entries of a directory, sorted.
+fn list_dir(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let path = readable_path(vm, "list_dir", &arguments)?;
+    let entries = std::fs::read_dir(path).map_err(|error| file_error(vm, "list", &arguments, error))?;
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|error| file_error(vm, "list", &arguments, error))?;
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+
+    Ok(names.into_iter().map(Value::from).collect::<Vec<Value>>().into())
+}
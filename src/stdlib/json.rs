@@ -0,0 +1,454 @@
+use super::function;
+use super::variadic;
+use crate::error::RuntimeError;
+use crate::value::map::Map;
+use crate::value::module::Module;
+use crate::value::object::Object;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+
+/// Deepest nesting of arrays and objects accepted by `json.parse()` and written by
+/// `json.stringify()`.
+const MAX_DEPTH: usize = 512;
+
+/// Widest indent of `json.stringify()`, like in JavaScript.
+const MAX_INDENT: usize = 10;
+
+/// The `json` module. Numbers without a fraction or an exponent are parsed as integers, and floats
+/// are always written with one, so `json.parse(json.stringify(value))` keeps the type of numbers.
+pub fn module() -> Module {
+    Module::native(
+        "json",
+        vec![function("json", "parse", 1, parse), variadic("json", "stringify", 1, stringify)],
+    )
+}
+
+/// Objects become maps, arrays become lists and `null` becomes nil.
+fn parse(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let Value::Object(Object::Str(text)) = &arguments[0] else {
+        return Err(RuntimeError::InvalidArgument("json.parse".to_string(), "a string".to_string(), vm.current_line()).into());
+    };
+
+    let mut parser = Parser {
+        characters: text.chars().collect(),
+        position: 0,
+        line: 1,
+        column: 1,
+    };
+
+    parser
+        .parse_document()
+        .map_err(|message| RuntimeError::InvalidJson(message, parser.line, parser.column, vm.current_line()).into())
+}
+
+/// `json.stringify(value, indent?)`, the indent is at most 10 spaces. Instances are written as
+/// objects of their public fields, the other values that JSON can't represent, like functions or
+/// NaN, are an error.
+fn stringify(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    if arguments.len() > 2 {
+        let error = RuntimeError::WrongArity("json.stringify".to_string(), "1 to 2".to_string(), arguments.len(), vm.current_line());
+        return Err(error.into());
+    }
+
+    let indent = match arguments.get(1) {
+        None | Some(Value::Nil) => None,
+        Some(indent) => match indent.as_index() {
+            Some(indent) if indent <= MAX_INDENT => Some(indent),
+            _ => {
+                let expected = format!("an integer indent from 0 to {MAX_INDENT}");
+                return Err(RuntimeError::InvalidArgument("json.stringify".to_string(), expected, vm.current_line()).into());
+            }
+        },
+    };
+
+    let mut writer = Writer {
+        output: String::new(),
+        indent,
+        open: Vec::new(),
+    };
+
+    match writer.write(&arguments[0], 0) {
+        Ok(()) => Ok(writer.output.into()),
+        Err(description) => Err(RuntimeError::JsonUnsupported(description, vm.current_line()).into()),
+    }
+}
+
+struct Parser {
+    characters: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn parse_document(&mut self) -> Result<Value, String> {
+        let value = self.parse_value(0)?;
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(character) => Err(format!("unexpected '{character}' after the value")),
+            None => Ok(value),
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err("too deeply nested".to_string());
+        }
+
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('{') => self.parse_object(depth),
+            Some('[') => self.parse_array(depth),
+            Some('"') => self.parse_string().map(Value::from),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_literal("true", Value::Bool(true)),
+            Some('f') => self.parse_literal("false", Value::Bool(false)),
+            Some('n') => self.parse_literal("null", Value::Nil),
+            Some(character) => Err(format!("unexpected '{character}'")),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<Value, String> {
+        self.advance();
+        let mut map = Map::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.advance();
+            return Ok(map.into());
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err("expected a string key".to_string());
+            }
+
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+
+            let value = self.parse_value(depth + 1)?;
+            map.insert(key.into(), value);
+
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some('}') => return Ok(map.into()),
+                _ => return Err("expected ',' or '}' in object".to_string()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<Value, String> {
+        self.advance();
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.advance();
+            return Ok(items.into());
+        }
+
+        loop {
+            items.push(self.parse_value(depth + 1)?);
+
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some(']') => return Ok(items.into()),
+                _ => return Err("expected ',' or ']' in array".to_string()),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.advance();
+        let mut string = String::new();
+
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let escaped = match self.advance() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.parse_unicode_escape()?,
+                        _ => return Err("invalid escape sequence".to_string()),
+                    };
+                    string.push(escaped);
+                }
+                Some(character) if character < ' ' => return Err("control character in string".to_string()),
+                Some(character) => string.push(character),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    /// `\uXXXX`, characters outside the basic plane are written as a pair of surrogates.
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.parse_hex()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| "invalid unicode escape".to_string());
+        }
+
+        if self.advance() != Some('\\') || self.advance() != Some('u') {
+            return Err("unpaired surrogate in unicode escape".to_string());
+        }
+
+        let low = self.parse_hex()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err("unpaired surrogate in unicode escape".to_string());
+        }
+
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).ok_or_else(|| "invalid unicode escape".to_string())
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let mut value = 0;
+
+        for _ in 0..4 {
+            let digit = self.advance().and_then(|character| character.to_digit(16));
+            value = value * 16 + digit.ok_or_else(|| "invalid unicode escape".to_string())?;
+        }
+
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, String> {
+        let start = self.position;
+        let mut is_float = false;
+
+        if self.peek() == Some('-') {
+            self.advance();
+        }
+
+        match self.peek() {
+            Some('0') => {
+                self.advance();
+            }
+            Some('1'..='9') => self.consume_digits(),
+            _ => return Err("expected digits in number".to_string()),
+        }
+
+        if self.peek() == Some('.') {
+            is_float = true;
+            self.advance();
+            if !self.peek().is_some_and(|character| character.is_ascii_digit()) {
+                return Err("expected digits after '.'".to_string());
+            }
+            self.consume_digits();
+        }
+
+        if let Some('e' | 'E') = self.peek() {
+            is_float = true;
+            self.advance();
+            if let Some('+' | '-') = self.peek() {
+                self.advance();
+            }
+            if !self.peek().is_some_and(|character| character.is_ascii_digit()) {
+                return Err("expected digits in exponent".to_string());
+            }
+            self.consume_digits();
+        }
+
+        let text: String = self.characters[start..self.position].iter().collect();
+        if !is_float {
+            if let Ok(integer) = text.parse::<i64>() {
+                return Ok(Value::Int(integer));
+            }
+        }
+
+        text.parse::<f64>().map(Value::Number).map_err(|_| "invalid number".to_string())
+    }
+
+    fn consume_digits(&mut self) {
+        while self.peek().is_some_and(|character| character.is_ascii_digit()) {
+            self.advance();
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Value) -> Result<Value, String> {
+        for expected in literal.chars() {
+            if self.peek() != Some(expected) {
+                return Err(format!("expected '{literal}'"));
+            }
+            self.advance();
+        }
+
+        Ok(value)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(character) if character == expected => {
+                self.advance();
+                Ok(())
+            }
+            _ => Err(format!("expected '{expected}'")),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.advance();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.characters.get(self.position).copied()
+    }
+
+    /// Consumes a character, keeping track of the line and column reported by errors.
+    fn advance(&mut self) -> Option<char> {
+        let character = self.peek()?;
+        self.position += 1;
+
+        if character == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(character)
+    }
+}
+
+struct Writer {
+    output: String,
+    indent: Option<usize>,
+    /// Lists, maps and instances being written, to detect cycles.
+    open: Vec<*const ()>,
+}
+
+impl Writer {
+    /// Errors describe the value that can't be written, like "a cyclic structure".
+    fn write(&mut self, value: &Value, depth: usize) -> Result<(), String> {
+        match value {
+            Value::Nil => self.output.push_str("null"),
+            Value::Bool(boolean) => self.output.push_str(&boolean.to_string()),
+            Value::Int(integer) => self.output.push_str(&integer.to_string()),
+            Value::Number(number) if number.is_finite() => self.output.push_str(&format!("{number:?}")),
+            Value::Number(number) => return Err(format!("the number {number}")),
+            Value::Object(Object::Str(string)) => self.write_string(string),
+            Value::Object(object @ (Object::List(_) | Object::Map(_) | Object::Instance(_))) => {
+                if depth > MAX_DEPTH {
+                    return Err("a structure nested too deeply".to_string());
+                }
+
+                if self.open.contains(&object.address()) {
+                    return Err("a cyclic structure".to_string());
+                }
+
+                self.open.push(object.address());
+                let written = match object {
+                    Object::List(list) => self.write_list(&list.borrow(), depth),
+                    Object::Map(map) => {
+                        let entries = map.borrow().iter().cloned().collect();
+                        self.write_object(entries, depth)
+                    }
+                    Object::Instance(instance) => {
+                        // NOTE: Private fields are stored as `Class#name`, they stay private.
+                        let mut entries: Vec<(Value, Value)> = instance
+                            .fields
+                            .borrow()
+                            .iter()
+                            .filter(|(name, _)| !name.contains('#'))
+                            .map(|(name, value)| (name.clone().into(), value.clone()))
+                            .collect();
+                        entries.sort_by_key(|(name, _)| name.to_string());
+                        self.write_object(entries, depth)
+                    }
+                    other => Err(other.to_string()),
+                };
+                self.open.pop();
+                written?;
+            }
+            Value::Object(object) => return Err(object.to_string()),
+        }
+
+        Ok(())
+    }
+
+    fn write_list(&mut self, items: &[Value], depth: usize) -> Result<(), String> {
+        if items.is_empty() {
+            self.output.push_str("[]");
+            return Ok(());
+        }
+
+        self.output.push('[');
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                self.output.push(',');
+            }
+            self.write_newline(depth + 1);
+            self.write(item, depth + 1)?;
+        }
+        self.write_newline(depth);
+        self.output.push(']');
+
+        Ok(())
+    }
+
+    fn write_object(&mut self, entries: Vec<(Value, Value)>, depth: usize) -> Result<(), String> {
+        if entries.is_empty() {
+            self.output.push_str("{}");
+            return Ok(());
+        }
+
+        self.output.push('{');
+        for (index, (key, value)) in entries.iter().enumerate() {
+            let Value::Object(Object::Str(key)) = key else {
+                return Err(format!("the map key {}", key.repr()));
+            };
+
+            if index > 0 {
+                self.output.push(',');
+            }
+            self.write_newline(depth + 1);
+            self.write_string(key);
+            self.output.push_str(if self.indent.is_some() { ": " } else { ":" });
+            self.write(value, depth + 1)?;
+        }
+        self.write_newline(depth);
+        self.output.push('}');
+
+        Ok(())
+    }
+
+    fn write_newline(&mut self, depth: usize) {
+        if let Some(indent) = self.indent {
+            self.output.push('\n');
+            self.output.push_str(&" ".repeat(indent * depth));
+        }
+    }
+
+    fn write_string(&mut self, string: &str) {
+        self.output.push('"');
+
+        for character in string.chars() {
+            match character {
+                '"' => self.output.push_str("\\\""),
+                '\\' => self.output.push_str("\\\\"),
+                '\n' => self.output.push_str("\\n"),
+                '\r' => self.output.push_str("\\r"),
+                '\t' => self.output.push_str("\\t"),
+                character if character < ' ' => self.output.push_str(&format!("\\u{:04x}", character as u32)),
+                character => self.output.push(character),
+            }
+        }
+
+        self.output.push('"');
+    }
+}
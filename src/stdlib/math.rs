@@ -0,0 +1,189 @@
+use super::function;
+use super::variadic;
+use crate::error::RuntimeError;
+use crate::value::module::Module;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+
+/// The `math` module. Functions that only reorder or round numbers, like `abs` or `floor`, keep
+/// integers as integers, the rest always return floats.
+pub fn module() -> Module {
+    Module::native(
+        "math",
+        vec![
+            function("math", "sqrt", 1, sqrt),
+            function("math", "pow", 2, pow),
+            function("math", "abs", 1, abs),
+            function("math", "floor", 1, floor),
+            function("math", "ceil", 1, ceil),
+            function("math", "round", 1, round),
+            variadic("math", "min", 1, min),
+            variadic("math", "max", 1, max),
+            function("math", "sin", 1, sin),
+            function("math", "cos", 1, cos),
+            function("math", "tan", 1, tan),
+            function("math", "asin", 1, asin),
+            function("math", "acos", 1, acos),
+            function("math", "atan", 1, atan),
+            function("math", "atan2", 2, atan2),
+            function("math", "exp", 1, exp),
+            function("math", "log", 1, log),
+            function("math", "log2", 1, log2),
+            function("math", "log10", 1, log10),
+            function("math", "isnan", 1, isnan),
+            function("math", "isfinite", 1, isfinite),
+            ("PI", Value::Number(std::f64::consts::PI)),
+            ("E", Value::Number(std::f64::consts::E)),
+            ("INF", Value::Number(f64::INFINITY)),
+            ("NAN", Value::Number(f64::NAN)),
+        ],
+    )
+}
+
+fn number(vm: &VirtualMachine, name: &str, value: &Value) -> Result<f64> {
+    value
+        .as_f64()
+        .ok_or_else(|| RuntimeError::InvalidArgument(format!("math.{name}"), "a number".to_string(), vm.current_line()).into())
+}
+
+/// Applies a float function to the only argument.
+fn float_function(vm: &VirtualMachine, name: &str, arguments: &[Value], function: fn(f64) -> f64) -> Result<Value> {
+    Ok(Value::Number(function(number(vm, name, &arguments[0])?)))
+}
+
+/// Applies a rounding function to the only argument, integers are already rounded.
+fn rounding_function(vm: &VirtualMachine, name: &str, arguments: &[Value], function: fn(f64) -> f64) -> Result<Value> {
+    match arguments[0] {
+        Value::Int(integer) => Ok(Value::Int(integer)),
+        ref value => Ok(Value::Number(function(number(vm, name, value)?))),
+    }
+}
+
+fn sqrt(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "sqrt", &arguments, f64::sqrt)
+}
+
+/// Integer powers with a non-negative exponent stay integers, and fail when they overflow.
+fn pow(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    if let (Value::Int(base), Value::Int(exponent)) = (&arguments[0], &arguments[1]) {
+        if *exponent >= 0 {
+            return u32::try_from(*exponent)
+                .ok()
+                .and_then(|exponent| base.checked_pow(exponent))
+                .map(Value::Int)
+                .ok_or_else(|| RuntimeError::IntegerOverflow(vm.current_line()).into());
+        }
+    }
+
+    let base = number(vm, "pow", &arguments[0])?;
+    let exponent = number(vm, "pow", &arguments[1])?;
+
+    Ok(Value::Number(base.powf(exponent)))
+}
+
+fn abs(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    match arguments[0] {
+        Value::Int(integer) => integer
+            .checked_abs()
+            .map(Value::Int)
+            .ok_or_else(|| RuntimeError::IntegerOverflow(vm.current_line()).into()),
+        _ => float_function(vm, "abs", &arguments, f64::abs),
+    }
+}
+
+fn floor(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    rounding_function(vm, "floor", &arguments, f64::floor)
+}
+
+fn ceil(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    rounding_function(vm, "ceil", &arguments, f64::ceil)
+}
+
+/// Halfway cases are rounded away from zero.
+fn round(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    rounding_function(vm, "round", &arguments, f64::round)
+}
+
+/// Returns the argument that compares first, keeping its type. NaN arguments are ignored unless
+/// they are all NaN.
+fn extreme(vm: &VirtualMachine, name: &str, arguments: Vec<Value>, preferred: std::cmp::Ordering) -> Result<Value> {
+    let mut result: Option<(Value, f64)> = None;
+
+    for argument in arguments {
+        let value = number(vm, name, &argument)?;
+
+        match &result {
+            Some((_, current)) if current.is_nan() || value.partial_cmp(current) == Some(preferred) => result = Some((argument, value)),
+            None => result = Some((argument, value)),
+            Some(_) => (),
+        }
+    }
+
+    Ok(result.map(|(argument, _)| argument).unwrap_or_default())
+}
+
+fn min(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    extreme(vm, "min", arguments, std::cmp::Ordering::Less)
+}
+
+fn max(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    extreme(vm, "max", arguments, std::cmp::Ordering::Greater)
+}
+
+fn sin(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "sin", &arguments, f64::sin)
+}
+
+fn cos(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "cos", &arguments, f64::cos)
+}
+
+fn tan(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "tan", &arguments, f64::tan)
+}
+
+fn asin(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "asin", &arguments, f64::asin)
+}
+
+fn acos(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "acos", &arguments, f64::acos)
+}
+
+fn atan(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "atan", &arguments, f64::atan)
+}
+
+/// Angle of the point `(x, y)`, the arguments are `y` first like in C.
+fn atan2(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let y = number(vm, "atan2", &arguments[0])?;
+    let x = number(vm, "atan2", &arguments[1])?;
+
+    Ok(Value::Number(y.atan2(x)))
+}
+
+fn exp(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "exp", &arguments, f64::exp)
+}
+
+/// Natural logarithm.
+fn log(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "log", &arguments, f64::ln)
+}
+
+fn log2(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "log2", &arguments, f64::log2)
+}
+
+fn log10(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    float_function(vm, "log10", &arguments, f64::log10)
+}
+
+fn isnan(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Bool(number(vm, "isnan", &arguments[0])?.is_nan()))
+}
+
+fn isfinite(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Bool(number(vm, "isfinite", &arguments[0])?.is_finite()))
+}
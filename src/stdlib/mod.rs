@@ -0,0 +1,73 @@
+pub mod collections;
+mod conversion;
+mod coroutine;
+mod io;
+mod json;
+mod math;
+pub mod prelude;
+mod process;
+pub mod random;
+pub mod re;
+mod reflection;
+pub mod string;
+mod time;
+
+use crate::value::native::NativeFn;
+use crate::value::native::NativeFunction;
+use crate::value::object::Object;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use std::rc::Rc;
+
+/// Defines the native functions available to every script.
+pub fn register(vm: &mut VirtualMachine) {
+    vm.define_native("int", 1, conversion::int);
+    vm.define_native("float", 1, conversion::float);
+    vm.define_native("repr", 1, conversion::repr);
+    vm.define_native("type_of", 1, reflection::type_of);
+    vm.define_native("fields", 1, reflection::fields);
+    vm.define_native("has_field", 2, reflection::has_field);
+    vm.define_native("get_field", 2, reflection::get_field);
+    vm.define_native("set_field", 3, reflection::set_field);
+    vm.define_native("class_of", 1, reflection::class_of);
+    vm.define_native("methods", 1, reflection::methods);
+    vm.define_native("superclass", 1, reflection::superclass);
+    vm.define_native("arity", 1, reflection::arity);
+    vm.define_native("name", 1, reflection::name);
+    vm.define_native("Fiber", 1, coroutine::fiber);
+    vm.define_variadic_native("Set", 0, collections::set);
+    vm.define_variadic_native("sort", 1, collections::sort);
+    vm.define_native("reverse", 1, collections::reverse);
+    vm.define_variadic_native("zip", 0, collections::zip);
+    vm.define_native("enumerate", 1, collections::enumerate);
+    vm.define_native("sum", 1, collections::sum);
+    vm.define_variadic_native("any", 1, collections::any);
+    vm.define_variadic_native("all", 1, collections::all);
+    vm.define_native("unique", 1, collections::unique);
+    vm.define_variadic_native("format", 1, string::format);
+    vm.define_native_module(math::module());
+    vm.define_native_module(io::module());
+    vm.define_native_module(json::module());
+    vm.define_native_module(re::module());
+    vm.define_native("clock", 0, time::clock);
+    vm.define_native_module(time::module());
+    vm.define_native_module(random::module());
+    vm.define_variadic_native("input", 0, process::input);
+    vm.define_native("exit", 1, process::exit);
+    vm.define_native_module(process::env_module());
+    vm.define_native_module(process::stdin_module());
+    vm.set_arguments(vec![]);
+}
+
+/// Member of a module built in Rust. The native is named after both in messages, like
+/// `math.sqrt`.
+fn function(module: &str, name: &'static str, arity: u8, function: NativeFn) -> (&'static str, Value) {
+    let native = NativeFunction::new(&format!("{module}.{name}"), arity, function);
+    (name, Value::Object(Object::Native(Rc::new(native))))
+}
+
+/// Like function(), for a native that takes `arity` or more arguments.
+fn variadic(module: &str, name: &'static str, arity: u8, function: NativeFn) -> (&'static str, Value) {
+    let native = NativeFunction::variadic(&format!("{module}.{name}"), arity, function);
+    (name, Value::Object(Object::Native(Rc::new(native))))
+}
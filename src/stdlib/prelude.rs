@@ -0,0 +1,17 @@
+use crate::chunk::Chunk;
+
+const SOURCE: &str = include_str!("prelude.lox");
+
+thread_local! {
+    /// The prelude is compiled once per thread, every VM runs a copy of the same bytecode.
+    static CHUNK: Chunk = {
+        let mut chunk = Chunk::new();
+        crate::compile(&mut chunk, SOURCE).expect("the prelude compiles");
+        chunk
+    };
+}
+
+/// Bytecode of the prelude, the functions written in Lox that every script can use.
+pub fn chunk() -> Chunk {
+    CHUNK.with(Chunk::clone)
+}
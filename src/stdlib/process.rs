@@ -0,0 +1,82 @@
+use super::function;
+use crate::error::RuntimeError;
+use crate::value::module::Module;
+use crate::value::object::Object;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+use std::io::BufRead;
+use std::io::Write;
+
+/// The `env` module, to read the environment variables of the process that the embedder allows,
+/// see `VirtualMachine::set_env_policy()`.
+pub fn env_module() -> Module {
+    Module::native("env", vec![function("env", "get", 1, get)])
+}
+
+/// The `stdin` module.
+pub fn stdin_module() -> Module {
+    Module::native("stdin", vec![function("stdin", "read_line", 0, read_line)])
+}
+
+/// Value of an environment variable, or nil if it isn't set.
+fn get(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let Value::Object(Object::Str(name)) = &arguments[0] else {
+        return Err(RuntimeError::InvalidArgument("env.get".to_string(), "a string".to_string(), vm.current_line()).into());
+    };
+
+    if !vm.env_policy().check(name) {
+        return Err(RuntimeError::EnvAccessDenied(name.to_string(), vm.current_line()).into());
+    }
+
+    Ok(std::env::var(name).map(Value::from).unwrap_or_default())
+}
+
+/// Next line of the standard input without its line ending, or nil at the end of the input.
+fn read_line(vm: &mut VirtualMachine, _arguments: Vec<Value>) -> Result<Value> {
+    let mut line = String::new();
+    let read = std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|error| RuntimeError::FileError(format!("Could not read the standard input: {error}"), vm.current_line()))?;
+
+    if read == 0 {
+        return Ok(Value::Nil);
+    }
+
+    let length = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(length);
+
+    Ok(line.into())
+}
+
+/// `input(prompt?)` prints the prompt without a newline and reads a line like
+/// `stdin.read_line()`.
+pub fn input(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    if arguments.len() > 1 {
+        return Err(RuntimeError::WrongArity("input".to_string(), "0 to 1".to_string(), arguments.len(), vm.current_line()).into());
+    }
+
+    if let Some(prompt) = arguments.into_iter().next() {
+        let prompt = vm.stringify(prompt)?;
+        print!("{prompt}");
+        let _ = std::io::stdout().flush();
+    }
+
+    read_line(vm, vec![])
+}
+
+/// Stops the script with the given exit code, from 0 to 255 like the codes of processes. It can't
+/// be caught by `try`, the embedder receives it as `RuntimeError::Exit`.
+pub fn exit(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let code = match arguments[0] {
+        Value::Int(code) => u8::try_from(code).ok(),
+        _ => None,
+    };
+    let Some(code) = code else {
+        let expected = "an exit code from 0 to 255".to_string();
+        return Err(RuntimeError::InvalidArgument("exit".to_string(), expected, vm.current_line()).into());
+    };
+
+    Err(RuntimeError::Exit(code).into())
+}
@@ -0,0 +1,152 @@
+use super::function;
+use crate::error::RuntimeError;
+use crate::value::module::Module;
+use crate::value::object::Object;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+
+/// The `random` module. Every VM has its own generator, seeded from the clock until a script
+/// calls `random.seed(n)`, after which the sequence is the same on every platform.
+pub fn module() -> Module {
+    Module::native(
+        "random",
+        vec![
+            function("random", "seed", 1, seed),
+            function("random", "random", 0, random),
+            function("random", "randint", 2, randint),
+            function("random", "choice", 1, choice),
+            function("random", "shuffle", 1, shuffle),
+        ],
+    )
+}
+
+/// xoshiro256** generator, its state is filled from the seed with splitmix64.
+#[derive(Debug)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut seed = seed;
+        let mut splitmix = || {
+            seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+
+        Self {
+            state: [splitmix(), splitmix(), splitmix(), splitmix()],
+        }
+    }
+
+    /// Generator seeded from the system clock, for runs that don't need to be reproducible.
+    pub fn from_time() -> Self {
+        let nanoseconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+
+        Self::new(nanoseconds)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = *s1 << 17;
+
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+
+        result
+    }
+
+    /// Float in `[0, 1)`, made from the 53 high bits.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Integer in `[0, bound)`, without the bias of a plain modulo.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        let limit = u64::MAX - u64::MAX % bound;
+
+        loop {
+            let value = self.next_u64();
+            if value < limit {
+                return value % bound;
+            }
+        }
+    }
+}
+
+fn invalid_argument(vm: &VirtualMachine, name: &str, expected: &str) -> anyhow::Error {
+    RuntimeError::InvalidArgument(format!("random.{name}"), expected.to_string(), vm.current_line()).into()
+}
+
+fn seed(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let Value::Int(seed) = arguments[0] else {
+        return Err(invalid_argument(vm, "seed", "an integer"));
+    };
+
+    *vm.rng() = Rng::new(seed as u64);
+    Ok(Value::Nil)
+}
+
+fn random(vm: &mut VirtualMachine, _arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Number(vm.rng().next_f64()))
+}
+
+/// Integer between both arguments, including them.
+fn randint(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let (Value::Int(low), Value::Int(high)) = (&arguments[0], &arguments[1]) else {
+        return Err(invalid_argument(vm, "randint", "two integers"));
+    };
+
+    if low > high {
+        return Err(invalid_argument(vm, "randint", "a lower bound not greater than the upper bound"));
+    }
+
+    let span = high.abs_diff(*low);
+    let offset = match span.checked_add(1) {
+        Some(bound) => vm.rng().below(bound),
+        None => vm.rng().next_u64(),
+    };
+
+    Ok(Value::Int(low.wrapping_add_unsigned(offset)))
+}
+
+fn choice(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let Value::Object(Object::List(list)) = &arguments[0] else {
+        return Err(invalid_argument(vm, "choice", "a non-empty list"));
+    };
+
+    let length = list.borrow().len();
+    if length == 0 {
+        return Err(invalid_argument(vm, "choice", "a non-empty list"));
+    }
+
+    let index = vm.rng().below(length as u64) as usize;
+    Ok(list.borrow()[index].clone())
+}
+
+/// Shuffles the list in place with the Fisher-Yates algorithm.
+fn shuffle(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let Value::Object(Object::List(list)) = &arguments[0] else {
+        return Err(invalid_argument(vm, "shuffle", "a list"));
+    };
+
+    let mut list = list.borrow_mut();
+    for index in (1..list.len()).rev() {
+        let other = vm.rng().below(index as u64 + 1) as usize;
+        list.swap(index, other);
+    }
+
+    Ok(Value::Nil)
+}
@@ -0,0 +1,203 @@
+use super::function;
+use crate::error::RuntimeError;
+use crate::regex::is_word_char;
+use crate::regex::Captures;
+use crate::regex::Regex;
+use crate::value::map::Map;
+use crate::value::module::Module;
+use crate::value::object::Object;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+use std::rc::Rc;
+
+/// The `re` module, `re.compile(pattern)` returns a regex object. See the `regex` module of the
+/// crate for the syntax. There are no string escapes in Lox, so `"\d+"` is already a backslash
+/// followed by a `d`.
+pub fn module() -> Module {
+    Module::native("re", vec![function("re", "compile", 1, compile)])
+}
+
+fn compile(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let Value::Object(Object::Str(pattern)) = &arguments[0] else {
+        return Err(invalid_argument(vm, "re.compile", "a string"));
+    };
+
+    let regex = Regex::new(pattern).map_err(|message| RuntimeError::InvalidRegex(message, vm.current_line()))?;
+    Ok(Value::Object(Object::Regex(Rc::new(regex))))
+}
+
+/// Methods of regex objects. A match is a list of the matched text followed by the text of every
+/// group, nil for the groups that didn't take part in it:
+///
+/// - `match(text)` matches at the start of the text only, `search(text)` anywhere. Both return
+///   the first match or nil.
+/// - `captures(text)` is like `search()`, but returns a map of the groups by index and by name.
+/// - `find_all(text)` returns every match, as strings when the pattern has no groups.
+/// - `replace(text, replacement)` replaces every match. The replacement is a string where `$1`,
+///   `$name` or `${name}` is the text of a group and `$$` is a dollar sign, or a function called
+///   with each match that returns the replacement.
+/// - `split(text)` returns the text between the matches.
+pub fn invoke_method(vm: &mut VirtualMachine, regex: &Regex, name: String, arguments: Vec<Value>) -> Result<Value> {
+    let arity = match name.as_str() {
+        "match" | "search" | "captures" | "find_all" | "split" => 1,
+        "replace" => 2,
+        _ => return Err(RuntimeError::UndefinedMethod(name, vm.current_line()).into()),
+    };
+
+    if arguments.len() != arity {
+        return Err(RuntimeError::WrongArity(name, arity.to_string(), arguments.len(), vm.current_line()).into());
+    }
+
+    let Value::Object(Object::Str(text)) = &arguments[0] else {
+        return Err(invalid_argument(vm, &name, "a string"));
+    };
+    let text: Vec<char> = text.chars().collect();
+
+    let result = match name.as_str() {
+        "match" | "search" => match regex.captures_at(&text, 0, name == "match") {
+            Some(captures) => groups(&text, &captures),
+            None => Value::Nil,
+        },
+        "captures" => match regex.captures_at(&text, 0, false) {
+            Some(captures) => group_map(regex, &text, &captures),
+            None => Value::Nil,
+        },
+        "find_all" => regex
+            .captures_iter(&text)
+            .map(|captures| match regex.group_count() {
+                0 => group_text(&text, &captures, 0),
+                _ => groups(&text, &captures),
+            })
+            .collect::<Vec<Value>>()
+            .into(),
+        "replace" => replace(vm, regex, &text, &arguments[1])?,
+        "split" => {
+            let mut parts = Vec::new();
+            let mut last = 0;
+
+            for captures in regex.captures_iter(&text) {
+                let Some((start, end)) = captures[0] else { continue };
+                // NOTE: Empty matches at the ends or right after another match don't split.
+                if start == end && (start == last || start == text.len()) {
+                    continue;
+                }
+
+                parts.push(text[last..start].iter().collect::<String>().into());
+                last = end;
+            }
+
+            parts.push(text[last..].iter().collect::<String>().into());
+            parts.into()
+        }
+        _ => return Err(RuntimeError::UndefinedMethod(name, vm.current_line()).into()),
+    };
+
+    Ok(result)
+}
+
+fn group_text(text: &[char], captures: &Captures, index: usize) -> Value {
+    match captures.get(index).copied().flatten() {
+        Some((start, end)) => text[start..end].iter().collect::<String>().into(),
+        None => Value::Nil,
+    }
+}
+
+fn groups(text: &[char], captures: &Captures) -> Value {
+    (0..captures.len())
+        .map(|index| group_text(text, captures, index))
+        .collect::<Vec<Value>>()
+        .into()
+}
+
+fn group_map(regex: &Regex, text: &[char], captures: &Captures) -> Value {
+    let mut map = Map::new();
+    for index in 0..captures.len() {
+        map.insert(Value::Int(index as i64), group_text(text, captures, index));
+    }
+    for (name, index) in regex.named_groups() {
+        map.insert(name.to_string().into(), group_text(text, captures, index));
+    }
+
+    map.into()
+}
+
+fn replace(vm: &mut VirtualMachine, regex: &Regex, text: &[char], replacement: &Value) -> Result<Value> {
+    let mut result = String::new();
+    let mut last = 0;
+
+    for captures in regex.captures_iter(text) {
+        let Some((start, end)) = captures[0] else { continue };
+        result.extend(&text[last..start]);
+
+        match replacement {
+            Value::Object(Object::Str(template)) => expand(vm, regex, template, text, &captures, &mut result)?,
+            callback => {
+                let value = vm.call_value(callback.clone(), vec![groups(text, &captures)])?;
+                result.push_str(&vm.stringify(value)?);
+            }
+        }
+
+        last = end;
+    }
+
+    result.extend(&text[last..]);
+    Ok(result.into())
+}
+
+/// Writes the replacement template with the groups of the match filled in.
+fn expand(vm: &VirtualMachine, regex: &Regex, template: &str, text: &[char], captures: &Captures, result: &mut String) -> Result<()> {
+    let mut characters = template.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        if character != '$' {
+            result.push(character);
+            continue;
+        }
+
+        let reference: String = match characters.peek() {
+            Some('$') => {
+                characters.next();
+                result.push('$');
+                continue;
+            }
+            Some('{') => {
+                characters.next();
+                let reference: String = characters.by_ref().take_while(|&character| character != '}').collect();
+                reference
+            }
+            Some(&character) if is_word_char(character) => {
+                let mut reference = String::new();
+                while let Some(character) = characters.next_if(|&character| is_word_char(character)) {
+                    reference.push(character);
+                }
+                reference
+            }
+            _ => return Err(invalid_replacement(vm, "'$' must be followed by a group or '$'")),
+        };
+
+        let index = match reference.parse::<usize>() {
+            Ok(index) => Some(index),
+            Err(_) => regex.named_groups().find(|(name, _)| *name == reference).map(|(_, index)| index),
+        };
+
+        match index.filter(|index| *index < captures.len()) {
+            Some(index) => {
+                if let Value::Object(Object::Str(group)) = group_text(text, captures, index) {
+                    result.push_str(&group);
+                }
+            }
+            None => return Err(invalid_replacement(vm, &format!("no group '{reference}'"))),
+        }
+    }
+
+    Ok(())
+}
+
+fn invalid_argument(vm: &VirtualMachine, name: &str, expected: &str) -> anyhow::Error {
+    RuntimeError::InvalidArgument(name.to_string(), expected.to_string(), vm.current_line()).into()
+}
+
+fn invalid_replacement(vm: &VirtualMachine, message: &str) -> anyhow::Error {
+    RuntimeError::InvalidArgument("replace".to_string(), format!("a valid replacement, {message}"), vm.current_line()).into()
+}
@@ -0,0 +1,148 @@
+use crate::error::RuntimeError;
+use crate::value::class::Class;
+use crate::value::object::Object;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+use std::rc::Rc;
+
+/// Name of the type of a value, like `"int"`, `"string"` or `"instance"`.
+pub fn type_of(_vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    Ok(arguments[0].type_name().to_string().into())
+}
+
+fn invalid_argument(vm: &VirtualMachine, name: &str, expected: &str) -> anyhow::Error {
+    RuntimeError::InvalidArgument(name.to_string(), expected.to_string(), vm.current_line()).into()
+}
+
+/// Name of a public field.
+fn field_argument<'a>(vm: &VirtualMachine, name: &str, value: &'a Value) -> Result<&'a str> {
+    match value {
+        Value::Object(Object::Str(string)) if !is_private(string) => Ok(string),
+        _ => Err(invalid_argument(vm, name, "a public field name")),
+    }
+}
+
+fn is_private(name: &str) -> bool {
+    name.contains('#')
+}
+
+/// Class of an instance, or the class itself.
+fn class_argument(vm: &VirtualMachine, name: &str, value: &Value) -> Result<Rc<Class>> {
+    match value {
+        Value::Object(Object::Class(class)) => Ok(class.clone()),
+        Value::Object(Object::Instance(instance)) => Ok(instance.class.clone()),
+        _ => Err(invalid_argument(vm, name, "a class or an instance")),
+    }
+}
+
+/// Public names, sorted.
+fn sorted_names<'a>(names: impl Iterator<Item = &'a String>) -> Value {
+    let mut names: Vec<&String> = names.filter(|name| !is_private(name)).collect();
+    names.sort();
+
+    names.into_iter().map(|name| Value::from(name.clone())).collect::<Vec<Value>>().into()
+}
+
+/// Names of the public fields of an instance sorted, or of a variant in declaration order.
+/// Private members, `#name`, are stored under the name of their class, like `Account#balance`.
+/// The reflection functions neither list them nor read or write them.
+pub fn fields(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    match &arguments[0] {
+        Value::Object(Object::Instance(instance)) => Ok(sorted_names(instance.fields.borrow().keys())),
+        Value::Object(Object::Variant(variant)) => {
+            let names = &variant.enumeration.variants[variant.index].fields;
+            Ok(names.iter().cloned().map(Value::from).collect::<Vec<Value>>().into())
+        }
+        _ => Err(invalid_argument(vm, "fields", "an instance or a variant")),
+    }
+}
+
+/// Only looks at the fields, getters and methods of the class don't count.
+pub fn has_field(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let name = field_argument(vm, "has_field", &arguments[1])?;
+
+    match &arguments[0] {
+        Value::Object(Object::Instance(instance)) => Ok(instance.fields.borrow().contains_key(name).into()),
+        Value::Object(Object::Variant(variant)) => Ok(variant.field(name).is_some().into()),
+        _ => Ok(false.into()),
+    }
+}
+
+/// Reads a field without going through the getters of the class, like `object.name` would.
+pub fn get_field(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let name = field_argument(vm, "get_field", &arguments[1])?;
+
+    let value = match &arguments[0] {
+        Value::Object(Object::Instance(instance)) => instance.fields.borrow().get(name).cloned(),
+        Value::Object(Object::Variant(variant)) => variant.field(name),
+        _ => return Err(invalid_argument(vm, "get_field", "an instance or a variant")),
+    };
+
+    value.ok_or_else(|| RuntimeError::UndefinedProperty(name.to_string(), vm.current_line()).into())
+}
+
+/// Assigns a field without going through the setters of the class. A callable field hides the
+/// method of the same name, which is how tests replace a method of a single instance.
+pub fn set_field(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let name = field_argument(vm, "set_field", &arguments[1])?;
+    let Value::Object(Object::Instance(instance)) = &arguments[0] else {
+        return Err(RuntimeError::ExpectedInstance(vm.current_line()).into());
+    };
+
+    instance.fields.borrow_mut().insert(name.to_string(), arguments[2].clone());
+    Ok(arguments[2].clone())
+}
+
+/// Class of an instance, enum of a variant, nil for anything else.
+pub fn class_of(_vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    Ok(match &arguments[0] {
+        Value::Object(Object::Instance(instance)) => Value::Object(Object::Class(instance.class.clone())),
+        Value::Object(Object::Variant(variant)) => Value::Object(Object::Enum(variant.enumeration.clone())),
+        _ => Value::Nil,
+    })
+}
+
+/// Sorted names of the public instance methods of a class, including the inherited ones.
+pub fn methods(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let class = class_argument(vm, "methods", &arguments[0])?;
+    let methods = class.methods.borrow();
+
+    Ok(sorted_names(methods.keys()))
+}
+
+/// Superclass of a class, nil if it doesn't have one.
+pub fn superclass(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let class = class_argument(vm, "superclass", &arguments[0])?;
+    let superclass = class.superclass.borrow().clone();
+
+    Ok(superclass.map(|class| Value::Object(Object::Class(class))).unwrap_or_default())
+}
+
+/// Number of named parameters of a callable, a class takes the ones of its `init()`.
+pub fn arity(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let arity = match &arguments[0] {
+        Value::Object(Object::Closure(closure)) => closure.function.arity,
+        Value::Object(Object::BoundMethod(bound)) => bound.method.function.arity,
+        Value::Object(Object::Native(native)) => native.arity,
+        Value::Object(Object::Class(class)) => class.methods.borrow().get("init").map(|init| init.function.arity).unwrap_or(0),
+        _ => return Err(invalid_argument(vm, "arity", "a function or a class")),
+    };
+
+    Ok(Value::Int(i64::from(arity)))
+}
+
+/// Declared name of a function, class, enum or module. Lambdas are named "anonymous".
+pub fn name(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let name = match &arguments[0] {
+        Value::Object(Object::Closure(closure)) => closure.function.name.clone().unwrap_or_default(),
+        Value::Object(Object::BoundMethod(bound)) => bound.method.function.name.clone().unwrap_or_default(),
+        Value::Object(Object::Native(native)) => native.name.clone(),
+        Value::Object(Object::Class(class)) => class.name.clone(),
+        Value::Object(Object::Enum(enumeration)) => enumeration.name.clone(),
+        Value::Object(Object::Module(module)) => module.name(),
+        _ => return Err(invalid_argument(vm, "name", "a function, class, enum or module")),
+    };
+
+    Ok(name.into())
+}
@@ -0,0 +1,309 @@
+use crate::error::RuntimeError;
+use crate::value::object::Object;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+
+/// Longest string `repeat()` builds, in bytes. Longer ones are almost certainly a mistake, and
+/// would exhaust the memory instead of failing.
+const MAX_REPEAT_LENGTH: usize = 1 << 30;
+
+/// Largest width and precision of a `format()` placeholder. Rust's own formatting, which applies
+/// the precision of numbers, doesn't take larger ones.
+const MAX_FORMAT_WIDTH: usize = u16::MAX as usize;
+
+/// Methods of strings, like `"a,b".split(",")`. Lengths and indexes count characters, not bytes,
+/// so `"héllo".len()` is 5.
+pub fn invoke_method(vm: &mut VirtualMachine, string: &str, name: String, arguments: Vec<Value>) -> Result<Value> {
+    let (required, arity) = match name.as_str() {
+        "len" | "upper" | "lower" | "trim" | "chars" => (0, 0),
+        "split" => (0, 1),
+        "join" | "find" | "starts_with" | "ends_with" | "contains" | "repeat" => (1, 1),
+        "substring" => (1, 2),
+        "replace" => (2, 2),
+        _ => return Err(RuntimeError::UndefinedMethod(name, vm.current_line()).into()),
+    };
+
+    if arguments.len() < required || arguments.len() > arity {
+        let expected = match required == arity {
+            true => arity.to_string(),
+            false => format!("{required} to {arity}"),
+        };
+        return Err(RuntimeError::WrongArity(name, expected, arguments.len(), vm.current_line()).into());
+    }
+
+    let result = match name.as_str() {
+        "len" => Value::Int(string.chars().count() as i64),
+        "upper" => string.to_uppercase().into(),
+        "lower" => string.to_lowercase().into(),
+        "trim" => string.trim().to_string().into(),
+        "chars" => string
+            .chars()
+            .map(|character| character.to_string().into())
+            .collect::<Vec<Value>>()
+            .into(),
+        "split" => match arguments.first() {
+            Some(separator) => {
+                let separator = string_argument(vm, &name, separator)?;
+                if separator.is_empty() {
+                    return Err(invalid_argument(vm, &name, "a non-empty string"));
+                }
+
+                string.split(separator).map(|part| part.to_string().into()).collect::<Vec<Value>>().into()
+            }
+            None => string
+                .split_whitespace()
+                .map(|part| part.to_string().into())
+                .collect::<Vec<Value>>()
+                .into(),
+        },
+        "join" => {
+            let Value::Object(Object::List(list)) = &arguments[0] else {
+                return Err(invalid_argument(vm, &name, "a list"));
+            };
+
+            let items = list.borrow().clone();
+            let mut parts = Vec::with_capacity(items.len());
+            for item in items {
+                parts.push(vm.stringify(item)?);
+            }
+
+            parts.join(string).into()
+        }
+        "find" => {
+            let needle = string_argument(vm, &name, &arguments[0])?;
+            match string.find(needle) {
+                Some(offset) => Value::Int(string[..offset].chars().count() as i64),
+                None => Value::Nil,
+            }
+        }
+        "starts_with" => string.starts_with(string_argument(vm, &name, &arguments[0])?).into(),
+        "ends_with" => string.ends_with(string_argument(vm, &name, &arguments[0])?).into(),
+        "contains" => string.contains(string_argument(vm, &name, &arguments[0])?).into(),
+        "repeat" => {
+            let count = match arguments[0] {
+                Value::Int(count) => usize::try_from(count).ok(),
+                _ => None,
+            };
+            let Some(count) = count else {
+                return Err(invalid_argument(vm, &name, "a non-negative integer"));
+            };
+
+            match string.len().checked_mul(count) {
+                Some(length) if length <= MAX_REPEAT_LENGTH => string.repeat(count).into(),
+                _ => return Err(RuntimeError::StringTooLong(MAX_REPEAT_LENGTH, vm.current_line()).into()),
+            }
+        }
+        "substring" => {
+            let length = string.chars().count();
+            let start = char_index(vm, &arguments[0], length)?;
+            let end = match arguments.get(1) {
+                Some(end) => char_index(vm, end, length)?,
+                None => length,
+            };
+
+            if start > end {
+                return Err(RuntimeError::IndexOutOfBounds(start, end, vm.current_line()).into());
+            }
+
+            string.chars().skip(start).take(end - start).collect::<String>().into()
+        }
+        "replace" => {
+            let from = string_argument(vm, &name, &arguments[0])?;
+            let to = string_argument(vm, &name, &arguments[1])?;
+            if from.is_empty() {
+                return Err(invalid_argument(vm, &name, "a non-empty string"));
+            }
+
+            string.replace(from, to).into()
+        }
+        _ => return Err(RuntimeError::UndefinedMethod(name, vm.current_line()).into()),
+    };
+
+    Ok(result)
+}
+
+/// Character of a string, `"héllo"[1]` is `"é"`.
+pub fn char_at(vm: &VirtualMachine, string: &str, index: &Value) -> Result<Value> {
+    let length = string.chars().count();
+    let index = char_index(vm, index, length)?;
+
+    match string.chars().nth(index) {
+        Some(character) => Ok(character.to_string().into()),
+        None => Err(RuntimeError::IndexOutOfBounds(index, length, vm.current_line()).into()),
+    }
+}
+
+/// Index of a character, the length itself is accepted as the end of the string.
+fn char_index(vm: &VirtualMachine, index: &Value, length: usize) -> Result<usize> {
+    let Some(index) = index.as_index() else {
+        return Err(RuntimeError::ExpectedIndex(vm.current_line()).into());
+    };
+
+    if index > length {
+        return Err(RuntimeError::IndexOutOfBounds(index, length, vm.current_line()).into());
+    }
+
+    Ok(index)
+}
+
+fn string_argument<'a>(vm: &VirtualMachine, name: &str, value: &'a Value) -> Result<&'a str> {
+    match value {
+        Value::Object(Object::Str(string)) => Ok(string),
+        _ => Err(invalid_argument(vm, name, "a string")),
+    }
+}
+
+fn invalid_argument(vm: &VirtualMachine, name: &str, expected: &str) -> anyhow::Error {
+    RuntimeError::InvalidArgument(name.to_string(), expected.to_string(), vm.current_line()).into()
+}
+
+/// Formats a string like `format("{} has {:.2}", name, total)`. Each `{}` takes the next argument,
+/// `{0}` takes one by position, and `{{` and `}}` are literal braces. After a colon comes an
+/// optional fill character and alignment (`<`, `>` or `^`), a `0` to pad numbers with zeros, the
+/// width and the precision: `{:>8.2}`. Numbers are aligned to the right by default and the rest
+/// to the left. The precision is the number of decimals of a number, or the maximum number of
+/// characters of anything else. Neither can be more than 65535.
+pub fn format(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let mut arguments = arguments.into_iter();
+    let Some(Value::Object(Object::Str(template))) = arguments.next() else {
+        return Err(invalid_argument(vm, "format", "a string template"));
+    };
+
+    let arguments: Vec<Value> = arguments.collect();
+    let mut result = String::new();
+    let mut next_argument = 0;
+    let mut characters = template.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '{' if characters.peek() == Some(&'{') => {
+                characters.next();
+                result.push('{');
+            }
+            '}' if characters.peek() == Some(&'}') => {
+                characters.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match characters.next() {
+                        Some('}') => break,
+                        Some(character) => placeholder.push(character),
+                        None => return Err(invalid_format(vm, "unclosed '{'")),
+                    }
+                }
+
+                let (position, spec) = placeholder.split_once(':').unwrap_or((&placeholder, ""));
+                let index = match position {
+                    "" => {
+                        next_argument += 1;
+                        next_argument - 1
+                    }
+                    position => position
+                        .parse()
+                        .map_err(|_| invalid_format(vm, &format!("invalid placeholder '{{{placeholder}}}'")))?,
+                };
+
+                let Some(argument) = arguments.get(index).cloned() else {
+                    return Err(invalid_format(vm, &format!("no argument for placeholder {index}")));
+                };
+
+                let spec = FormatSpec::parse(spec).ok_or_else(|| invalid_format(vm, &format!("invalid placeholder '{{{placeholder}}}'")))?;
+                result.push_str(&spec.apply(vm, argument)?);
+            }
+            '}' => return Err(invalid_format(vm, "unmatched '}'")),
+            character => result.push(character),
+        }
+    }
+
+    Ok(result.into())
+}
+
+fn invalid_format(vm: &VirtualMachine, message: &str) -> anyhow::Error {
+    RuntimeError::InvalidFormat(message.to_string(), vm.current_line()).into()
+}
+
+#[derive(Default)]
+struct FormatSpec {
+    fill: Option<char>,
+    align: Option<char>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl FormatSpec {
+    fn parse(spec: &str) -> Option<Self> {
+        let mut result = Self::default();
+        let characters: Vec<char> = spec.chars().collect();
+        let mut position = 0;
+
+        let is_align = |character: Option<&char>| matches!(character, Some('<' | '>' | '^'));
+        if is_align(characters.get(1)) {
+            result.fill = characters.first().copied();
+            result.align = characters.get(1).copied();
+            position = 2;
+        } else if is_align(characters.first()) {
+            result.align = characters.first().copied();
+            position = 1;
+        }
+
+        if characters.get(position) == Some(&'0') {
+            result.zero = true;
+            position += 1;
+        }
+
+        let rest: String = characters[position..].iter().collect();
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(precision.parse().ok()?)),
+            None => (rest.as_str(), None),
+        };
+
+        if !width.is_empty() {
+            result.width = width.parse().ok()?;
+        }
+        result.precision = precision;
+
+        if result.width > MAX_FORMAT_WIDTH || result.precision.is_some_and(|precision| precision > MAX_FORMAT_WIDTH) {
+            return None;
+        }
+
+        Some(result)
+    }
+
+    fn apply(&self, vm: &mut VirtualMachine, value: Value) -> Result<String> {
+        let is_number = matches!(value, Value::Int(_) | Value::Number(_));
+
+        let text = match (self.precision, value.as_f64()) {
+            (Some(precision), Some(number)) => format!("{number:.precision$}"),
+            (Some(precision), None) => vm.stringify(value)?.chars().take(precision).collect(),
+            (None, _) => vm.stringify(value)?,
+        };
+
+        let length = text.chars().count();
+        if length >= self.width {
+            return Ok(text);
+        }
+
+        let padding = self.width - length;
+        if self.zero && is_number && self.align.is_none() {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+            return Ok(format!("{sign}{}{digits}", "0".repeat(padding)));
+        }
+
+        let fill = self.fill.unwrap_or(' ').to_string();
+        let align = self.align.unwrap_or(if is_number { '>' } else { '<' });
+        let (left, right) = match align {
+            '>' => (padding, 0),
+            '^' => (padding / 2, padding - padding / 2),
+            _ => (0, padding),
+        };
+
+        Ok(format!("{}{text}{}", fill.repeat(left), fill.repeat(right)))
+    }
+}
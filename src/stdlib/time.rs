@@ -0,0 +1,43 @@
+use super::function;
+use crate::error::RuntimeError;
+use crate::value::module::Module;
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// The `time` module.
+pub fn module() -> Module {
+    Module::native("time", vec![function("time", "now", 0, now), function("time", "sleep", 1, sleep)])
+}
+
+/// Seconds since the VM started, from a monotonic clock. Meant for measuring durations.
+pub fn clock(vm: &mut VirtualMachine, _arguments: Vec<Value>) -> Result<Value> {
+    Ok(Value::Number(vm.uptime().as_secs_f64()))
+}
+
+/// Seconds since the Unix epoch, from the wall clock.
+fn now(_vm: &mut VirtualMachine, _arguments: Vec<Value>) -> Result<Value> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or_default();
+
+    Ok(Value::Number(seconds))
+}
+
+/// Pauses the script for the given number of milliseconds.
+fn sleep(vm: &mut VirtualMachine, arguments: Vec<Value>) -> Result<Value> {
+    let duration = arguments[0]
+        .as_f64()
+        .and_then(|milliseconds| Duration::try_from_secs_f64(milliseconds / 1000.0).ok());
+    let Some(duration) = duration else {
+        let expected = "a non-negative number of milliseconds".to_string();
+        return Err(RuntimeError::InvalidArgument("time.sleep".to_string(), expected, vm.current_line()).into());
+    };
+
+    std::thread::sleep(duration);
+    Ok(Value::Nil)
+}
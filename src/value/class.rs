@@ -0,0 +1,100 @@
+use crate::value::function::Closure;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Class created by a `class` declaration. The members are added one by one while the class body
+/// runs, and a subclass starts with a copy of the members of its superclass.
+#[derive(Debug, Default)]
+pub struct Class {
+    pub name: String,
+    /// Text of the `///` comments before the declaration.
+    pub doc: Option<String>,
+    pub methods: RefCell<HashMap<String, Rc<Closure>>>,
+    /// Methods declared with `static`, called on the class itself.
+    pub static_methods: RefCell<HashMap<String, Rc<Closure>>>,
+    /// Accessors declared with `get name { }`, read like fields.
+    pub getters: RefCell<HashMap<String, Rc<Closure>>>,
+    /// Accessors declared with `set name(value) { }`, assigned like fields.
+    pub setters: RefCell<HashMap<String, Rc<Closure>>>,
+    /// Fields declared with `var name = value;`, in declaration order. Each initializer is a
+    /// method that returns the value of the field, it runs for every new instance.
+    pub fields: RefCell<Vec<(String, Rc<Closure>)>>,
+    /// Class named after `<` in the declaration, set once the body starts running.
+    pub superclass: RefCell<Option<Rc<Class>>>,
+}
+
+impl Class {
+    pub fn new(name: String) -> Self {
+        Self { name, ..Self::default() }
+    }
+
+    pub fn add_member(&self, kind: MemberKind, name: String, closure: Rc<Closure>) {
+        let table = match kind {
+            MemberKind::Method => &self.methods,
+            MemberKind::Static => &self.static_methods,
+            MemberKind::Getter => &self.getters,
+            MemberKind::Setter => &self.setters,
+            MemberKind::Field => {
+                let mut fields = self.fields.borrow_mut();
+                fields.retain(|(field, _)| *field != name);
+                fields.push((name, closure));
+                return;
+            }
+        };
+
+        table.borrow_mut().insert(name, closure);
+    }
+
+    /// Copies the members of the superclass, the ones declared by this class replace them.
+    pub fn inherit(&self, superclass: Rc<Class>) {
+        let tables = [
+            (&self.methods, &superclass.methods),
+            (&self.static_methods, &superclass.static_methods),
+            (&self.getters, &superclass.getters),
+            (&self.setters, &superclass.setters),
+        ];
+
+        for (table, inherited) in tables {
+            table
+                .borrow_mut()
+                .extend(inherited.borrow().iter().map(|(name, closure)| (name.clone(), closure.clone())));
+        }
+
+        self.fields.borrow_mut().extend(superclass.fields.borrow().iter().cloned());
+        self.superclass.replace(Some(superclass));
+    }
+}
+
+/// Kind of class member, see OP_MEMBER.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberKind {
+    Method,
+    Static,
+    Getter,
+    Setter,
+    Field,
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: RefCell<HashMap<String, Value>>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self {
+            class,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+/// Method read from an instance, it remembers the instance it was read from.
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Value,
+    pub method: Rc<Closure>,
+}
@@ -0,0 +1,57 @@
+use crate::value::function::Upvalue;
+use crate::value::Value;
+use crate::vm::CallFrame;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Function call that can be suspended with `yield` and resumed later. Calling a function that
+/// contains `yield` creates one, and so does `Fiber(function)` for any function.
+///
+/// While suspended, the frame and its stack slots are kept here. The slots are stored relative to
+/// the frame, they are moved back on top of the stack when the coroutine is resumed. Upvalues
+/// pointing to the slots move with them, so closures keep sharing the locals of the coroutine.
+#[derive(Debug)]
+pub struct Coroutine {
+    pub name: String,
+    pub state: CoroutineState,
+    pub(crate) frame: Option<CallFrame>,
+    pub(crate) stack: Vec<Value>,
+    /// Upvalues pointing to the saved stack slots while the coroutine is suspended.
+    pub(crate) upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CoroutineState {
+    /// Not started yet, the value passed to the first resume is discarded.
+    Created,
+    /// Stopped at a `yield`, the value passed to resume becomes the result of the yield.
+    Suspended,
+    Running,
+    /// Returned or threw an exception, resuming it again produces nil.
+    Done,
+}
+
+impl Coroutine {
+    pub(crate) fn new(name: String, frame: CallFrame, stack: Vec<Value>) -> Self {
+        Self {
+            name,
+            state: CoroutineState::Created,
+            frame: Some(frame),
+            stack,
+            upvalues: vec![],
+        }
+    }
+}
+
+/// A suspended coroutine that is never resumed again closes its upvalues, so the closures that
+/// captured its locals keep their values.
+impl Drop for Coroutine {
+    fn drop(&mut self) {
+        for upvalue in self.upvalues.drain(..) {
+            let mut upvalue = upvalue.borrow_mut();
+            if let Upvalue::Suspended(_, slot) = *upvalue {
+                *upvalue = Upvalue::Closed(self.stack.get(slot).cloned().unwrap_or_default());
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::value::Value;
+use std::fmt::Display;
+use std::rc::Rc;
+
+/// Enum created by an `enum` declaration, like `enum Shape { Circle(radius), Rect(w, h) }`.
+#[derive(Debug)]
+pub struct Enum {
+    pub name: String,
+    /// Text of the `///` comments before the declaration.
+    pub doc: Option<String>,
+    pub variants: Vec<VariantDeclaration>,
+}
+
+#[derive(Debug)]
+pub struct VariantDeclaration {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+impl Enum {
+    pub fn variant(&self, name: &str) -> Option<(usize, &VariantDeclaration)> {
+        self.variants.iter().enumerate().find(|(_, variant)| variant.name == name)
+    }
+}
+
+/// Value of an enum, like `Shape.Circle(2)`. Variants are compared and hashed by their enum, their
+/// name and their values, so `Color.Red == Color.Red`.
+#[derive(Debug)]
+pub struct Variant {
+    pub enumeration: Rc<Enum>,
+    pub index: usize,
+    pub values: Vec<Value>,
+}
+
+impl Variant {
+    pub fn name(&self) -> &str {
+        &self.enumeration.variants[self.index].name
+    }
+
+    /// Value of the field with the given name.
+    pub fn field(&self, name: &str) -> Option<Value> {
+        let fields = &self.enumeration.variants[self.index].fields;
+        fields.iter().position(|field| field == name).map(|index| self.values[index].clone())
+    }
+}
+
+impl PartialEq for Variant {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.enumeration, &other.enumeration) && self.index == other.index && self.values == other.values
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.enumeration.name, self.name())?;
+
+        if !self.values.is_empty() {
+            let values: Vec<String> = self.values.iter().map(Value::repr).collect();
+            write!(f, "({})", values.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
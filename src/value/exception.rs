@@ -0,0 +1,10 @@
+use crate::Line;
+
+/// Error raised by the VM or by a native function, converted to a value so that scripts can catch
+/// it with `try`/`catch`. Its message, line and trace are read with methods of the same name.
+#[derive(Debug)]
+pub struct Exception {
+    pub message: String,
+    pub line: Line,
+    pub trace: Vec<String>,
+}
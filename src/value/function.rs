@@ -0,0 +1,79 @@
+use crate::chunk::Chunk;
+use crate::value::coroutine::Coroutine;
+use crate::value::module::Module;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::rc::Rc;
+use std::rc::Weak;
+
+/// Compiled function. Named functions come from `fun name() {}` declarations, and the ones
+/// created by `fun () {}` expressions and arrow lambdas are named "anonymous". The top level code
+/// of a script is a function without a name.
+#[derive(Debug, Default)]
+pub struct Function {
+    pub name: Option<String>,
+    /// Number of named parameters, the rest parameter isn't counted.
+    pub arity: u8,
+    /// Number of parameters without a default value, they come before the ones with a default.
+    pub required: u8,
+    /// The last parameter is `...name`, it collects the extra arguments into a list.
+    pub has_rest: bool,
+    /// The body contains `yield`, so calling the function creates a coroutine instead of running it.
+    pub is_generator: bool,
+    /// Text of the `///` comments before the declaration.
+    pub doc: Option<String>,
+    pub chunk: Chunk,
+    /// Variables captured from the enclosing functions, in the order OP_GET_UPVALUE indexes them.
+    pub upvalues: Vec<UpvalueSource>,
+}
+
+impl Function {
+    pub fn accepts(&self, argument_count: usize) -> bool {
+        argument_count >= usize::from(self.required) && (argument_count <= usize::from(self.arity) || self.has_rest)
+    }
+
+    /// Accepted number of arguments, as reported by arity errors: `2`, `1 to 3` or `at least 1`.
+    pub fn expected_arguments(&self) -> String {
+        match (self.has_rest, self.required == self.arity) {
+            (true, _) => format!("at least {}", self.required),
+            (false, true) => self.arity.to_string(),
+            (false, false) => format!("{} to {}", self.required, self.arity),
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "<fn {name}>"),
+            None => write!(f, "<script>"),
+        }
+    }
+}
+
+/// Where a closure finds a captured variable when it is created: a local slot of the enclosing
+/// function, or one of the upvalues of the enclosing closure.
+#[derive(Debug, Clone, Copy)]
+pub struct UpvalueSource {
+    pub is_local: bool,
+    pub index: usize,
+}
+
+/// Function together with the variables it captured and the module whose globals it uses.
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    pub module: Rc<Module>,
+}
+
+/// Captured variable. It points to a stack slot while the variable is alive, and holds the value
+/// itself once the variable goes out of scope. The local of a suspended coroutine is in the stack
+/// saved by the coroutine, at a slot relative to its frame.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Suspended(Weak<RefCell<Coroutine>>, usize),
+    Closed(Value),
+}
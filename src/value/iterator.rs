@@ -0,0 +1,48 @@
+use crate::value::map::Map;
+use crate::value::set::Set;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Lines;
+use std::rc::Rc;
+
+/// Iteration state of a built-in collection, created by OP_GET_ITERATOR for `for-in` loops.
+///
+/// Lists, maps and sets are read through their shared reference, so elements added while looping are
+/// visited too. Maps yield their keys, sets their values and strings one string per character.
+///
+/// The lines of a file, created by `io.lines()`, are read one by one while iterating.
+#[derive(Clone, Debug)]
+pub enum NativeIterator {
+    List(Rc<RefCell<Vec<Value>>>, usize),
+    Map(Rc<RefCell<Map>>, usize),
+    Set(Rc<RefCell<Set>>, usize),
+    Str(Vec<char>, usize),
+    Lines(Rc<RefCell<Lines<BufReader<File>>>>),
+}
+
+impl NativeIterator {
+    /// Returns the next element or None when the iterator is exhausted. Only reading the lines
+    /// of a file can fail.
+    pub fn next_value(&mut self) -> std::io::Result<Option<Value>> {
+        let value = match self {
+            NativeIterator::List(list, index) => list.borrow().get(*index).cloned(),
+            NativeIterator::Map(map, index) => map.borrow().get_index(*index).map(|(key, _)| key.clone()),
+            NativeIterator::Set(set, index) => set.borrow().get_index(*index).cloned(),
+            NativeIterator::Str(characters, index) => characters.get(*index).map(|character| character.to_string().into()),
+            NativeIterator::Lines(lines) => return lines.borrow_mut().next().transpose().map(|line| line.map(Value::from)),
+        };
+
+        if value.is_some() {
+            match self {
+                NativeIterator::List(_, index) | NativeIterator::Map(_, index) | NativeIterator::Set(_, index) | NativeIterator::Str(_, index) => {
+                    *index += 1
+                }
+                NativeIterator::Lines(_) => (),
+            }
+        }
+
+        Ok(value)
+    }
+}
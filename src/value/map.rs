@@ -0,0 +1,79 @@
+use crate::value::Value;
+use std::collections::HashMap;
+
+/// An insertion-ordered hash map keyed by hashable values.
+///
+/// Entries are kept in a vector so that `keys()`, `values()` and printing follow the order in
+/// which the keys were first inserted, while the index table keeps lookups constant time.
+#[derive(Clone, Default, Debug)]
+pub struct Map {
+    entries: Vec<(Value, Value)>,
+    indices: HashMap<Value, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        self.indices.get(key).map(|&index| &self.entries[index].1)
+    }
+
+    /// Entry at the given position in insertion order.
+    pub fn get_index(&self, index: usize) -> Option<&(Value, Value)> {
+        self.entries.get(index)
+    }
+
+    pub fn contains_key(&self, key: &Value) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    /// Inserts the entry and returns the previous value of the key, if any. The caller is
+    /// responsible for checking that the key is hashable.
+    pub fn insert(&mut self, key: Value, value: Value) -> Option<Value> {
+        match self.indices.get(&key) {
+            Some(&index) => Some(std::mem::replace(&mut self.entries[index].1, value)),
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Removes the entry and returns its value. The entries after it are shifted to keep the
+    /// insertion order.
+    pub fn remove(&mut self, key: &Value) -> Option<Value> {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+
+        for (key, _) in &self.entries[index..] {
+            if let Some(position) = self.indices.get_mut(key) {
+                *position -= 1;
+            }
+        }
+
+        Some(value)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.entries.iter()
+    }
+}
@@ -1,8 +1,24 @@
+pub mod class;
+pub mod coroutine;
+pub mod enumeration;
+pub mod exception;
+pub mod function;
+pub mod iterator;
+pub mod map;
+pub mod module;
+pub mod native;
 pub mod object;
+pub mod set;
 
+use crate::value::map::Map;
 use crate::value::object::Object;
+use crate::value::set::Set;
 use derive_more::derive::Debug;
 use derive_more::derive::Display;
+use std::cell::RefCell;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::rc::Rc;
 
 #[derive(Clone, Default, Display, Debug)]
 #[display("{_0}")]
@@ -13,10 +29,114 @@ pub enum Value {
     #[default]
     Nil,
     Number(f64),
+    Int(i64),
     #[debug("{}", _0)]
     Object(Object),
 }
 
+impl Value {
+    /// Every value can be used as a map key except NaN, which is not equal to itself, and the
+    /// variants that hold it, since they are compared by their values.
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            Value::Number(number) => !number.is_nan(),
+            Value::Object(Object::Variant(variant)) => variant.values.iter().all(Value::is_hashable),
+            _ => true,
+        }
+    }
+
+    /// Nil and false are falsey, every other value is truthy.
+    pub fn is_falsey(&self) -> bool {
+        matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// Numeric value as a float, integers are promoted.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Int(integer) => Some(*integer as f64),
+            _ => None,
+        }
+    }
+
+    /// Integral value as a list or string index, like `2` or `2.0`.
+    pub fn as_index(&self) -> Option<usize> {
+        let integer = match self {
+            Value::Int(integer) => Some(*integer),
+            Value::Number(number) => float_to_exact_int(*number),
+            _ => None,
+        };
+
+        integer.and_then(|integer| usize::try_from(integer).ok())
+    }
+
+    /// Name of the type of the value, as returned by `type_of()`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::Number(_) => "float",
+            Value::Int(_) => "int",
+            Value::Object(object) => object.type_name(),
+        }
+    }
+
+    /// Representation used when the value is printed inside a collection, strings are quoted.
+    pub fn repr(&self) -> String {
+        match self {
+            Value::Object(Object::Str(string)) => format!("{string:?}"),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Int(integer), Value::Number(number)) | (Value::Number(number), Value::Int(integer)) => {
+                float_to_exact_int(*number) == Some(*integer)
+            }
+            (Value::Object(a), Value::Object(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+// NOTE: NaN breaks reflexivity, so it is rejected before being used as a key. See is_hashable().
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // NOTE: Integers and integral floats are equal, so both are hashed as integers.
+        match self {
+            Value::Int(integer) => (0u8, integer).hash(state),
+            Value::Number(number) => match float_to_exact_int(*number) {
+                Some(integer) => (0u8, integer).hash(state),
+                None => (1u8, number.to_bits()).hash(state),
+            },
+            Value::Bool(boolean) => (2u8, boolean).hash(state),
+            Value::Nil => 3u8.hash(state),
+            Value::Object(object) => (4u8, object).hash(state),
+        }
+    }
+}
+
+/// Converts the float to an integer only if no precision is lost.
+pub fn float_to_exact_int(number: f64) -> Option<i64> {
+    // NOTE: i64::MIN is a power of two, so the bounds are exact as floats.
+    let in_range = number >= i64::MIN as f64 && number < -(i64::MIN as f64);
+
+    if number.fract() == 0.0 && in_range {
+        Some(number as i64)
+    } else {
+        None
+    }
+}
+
 impl From<bool> for Value {
     fn from(value: bool) -> Self {
         Self::Bool(value)
@@ -29,8 +149,32 @@ impl From<f64> for Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
 impl From<String> for Value {
     fn from(value: String) -> Self {
         Self::Object(Object::Str(value))
     }
 }
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Self::Object(Object::List(Rc::new(RefCell::new(value))))
+    }
+}
+
+impl From<Map> for Value {
+    fn from(value: Map) -> Self {
+        Self::Object(Object::Map(Rc::new(RefCell::new(value))))
+    }
+}
+
+impl From<Set> for Value {
+    fn from(value: Set) -> Self {
+        Self::Object(Object::Set(Rc::new(RefCell::new(value))))
+    }
+}
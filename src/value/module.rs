@@ -0,0 +1,61 @@
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// A script file with its own global variables. The main script has no path when it doesn't come
+/// from a file, like in the REPL.
+///
+/// Only the globals declared with `export` can be read from the modules that import it.
+#[derive(Debug, Default)]
+pub struct Module {
+    pub path: Option<PathBuf>,
+    /// Name of the modules built in Rust, like `math`, they have no path.
+    pub builtin: Option<String>,
+    pub globals: RefCell<HashMap<String, Value>>,
+    /// Globals declared with `const`, they can't be assigned or defined again.
+    pub constants: RefCell<HashSet<String>>,
+    pub exports: RefCell<HashSet<String>>,
+}
+
+impl Module {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path, ..Default::default() }
+    }
+
+    /// Module built in Rust, all its members are exported.
+    pub fn native(name: &str, members: Vec<(&str, Value)>) -> Self {
+        let module = Self {
+            builtin: Some(name.to_string()),
+            ..Default::default()
+        };
+
+        for (member, value) in members {
+            module.globals.borrow_mut().insert(member.to_string(), value);
+            module.exports.borrow_mut().insert(member.to_string());
+        }
+
+        module
+    }
+
+    pub fn get_export(&self, name: &str) -> Option<Value> {
+        if !self.exports.borrow().contains(name) {
+            return None;
+        }
+
+        self.globals.borrow().get(name).cloned()
+    }
+
+    /// Name used in messages, the file name of the module.
+    pub fn name(&self) -> String {
+        if let Some(name) = &self.builtin {
+            return name.clone();
+        }
+
+        match &self.path {
+            Some(path) => path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned(),
+            None => "script".to_string(),
+        }
+    }
+}
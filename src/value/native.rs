@@ -0,0 +1,48 @@
+use crate::value::Value;
+use crate::vm::VirtualMachine;
+use anyhow::Result;
+
+pub type NativeFn = fn(&mut VirtualMachine, Vec<Value>) -> Result<Value>;
+
+/// Function implemented in Rust and exposed to scripts through a global variable.
+///
+/// The VM checks the arity before calling it, so the function can index its arguments directly.
+#[derive(Debug)]
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: u8,
+    /// Extra arguments after the first `arity` ones are accepted, like with a rest parameter.
+    pub has_rest: bool,
+    pub function: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn new(name: &str, arity: u8, function: NativeFn) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            has_rest: false,
+            function,
+        }
+    }
+
+    /// Native that takes `arity` or more arguments.
+    pub fn variadic(name: &str, arity: u8, function: NativeFn) -> Self {
+        Self {
+            has_rest: true,
+            ..Self::new(name, arity, function)
+        }
+    }
+
+    pub fn accepts(&self, argument_count: usize) -> bool {
+        argument_count == usize::from(self.arity) || (self.has_rest && argument_count > usize::from(self.arity))
+    }
+
+    /// Accepted number of arguments, as reported by arity errors: `2` or `at least 1`.
+    pub fn expected_arguments(&self) -> String {
+        match self.has_rest {
+            true => format!("at least {}", self.arity),
+            false => self.arity.to_string(),
+        }
+    }
+}
@@ -1,7 +1,146 @@
-use derive_more::Display;
+use crate::regex::Regex;
+use crate::value::class::BoundMethod;
+use crate::value::class::Class;
+use crate::value::class::Instance;
+use crate::value::coroutine::Coroutine;
+use crate::value::enumeration::Enum;
+use crate::value::enumeration::Variant;
+use crate::value::exception::Exception;
+use crate::value::function::Closure;
+use crate::value::iterator::NativeIterator;
+use crate::value::map::Map;
+use crate::value::module::Module;
+use crate::value::native::NativeFunction;
+use crate::value::set::Set;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::rc::Rc;
 
-#[derive(Clone, Display)]
+/// Heap values. Strings and enum variants are compared and hashed by content, every other object
+/// by identity.
+#[derive(Clone, Debug)]
 pub enum Object {
-    #[display("{_0}")]
     Str(String),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<Map>>),
+    Set(Rc<RefCell<Set>>),
+    Iterator(Rc<RefCell<NativeIterator>>),
+    Native(Rc<NativeFunction>),
+    Closure(Rc<Closure>),
+    Coroutine(Rc<RefCell<Coroutine>>),
+    Exception(Rc<Exception>),
+    Module(Rc<Module>),
+    Class(Rc<Class>),
+    Instance(Rc<Instance>),
+    BoundMethod(Rc<BoundMethod>),
+    Enum(Rc<Enum>),
+    Variant(Rc<Variant>),
+    Regex(Rc<Regex>),
+}
+
+impl Object {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Str(_) => "string",
+            Object::List(_) => "list",
+            Object::Map(_) => "map",
+            Object::Set(_) => "set",
+            Object::Iterator(_) => "iterator",
+            Object::Native(_) | Object::Closure(_) | Object::BoundMethod(_) => "function",
+            Object::Coroutine(_) => "coroutine",
+            Object::Exception(_) => "exception",
+            Object::Module(_) => "module",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::Enum(_) => "enum",
+            Object::Variant(_) => "variant",
+            Object::Regex(_) => "regex",
+        }
+    }
+
+    /// Address used to compare and hash objects by identity.
+    pub(crate) fn address(&self) -> *const () {
+        match self {
+            Object::Str(string) => string.as_ptr() as *const (),
+            Object::List(list) => Rc::as_ptr(list) as *const (),
+            Object::Map(map) => Rc::as_ptr(map) as *const (),
+            Object::Set(set) => Rc::as_ptr(set) as *const (),
+            Object::Iterator(iterator) => Rc::as_ptr(iterator) as *const (),
+            Object::Native(native) => Rc::as_ptr(native) as *const (),
+            Object::Closure(closure) => Rc::as_ptr(closure) as *const (),
+            Object::Coroutine(coroutine) => Rc::as_ptr(coroutine) as *const (),
+            Object::Exception(exception) => Rc::as_ptr(exception) as *const (),
+            Object::Module(module) => Rc::as_ptr(module) as *const (),
+            Object::Class(class) => Rc::as_ptr(class) as *const (),
+            Object::Instance(instance) => Rc::as_ptr(instance) as *const (),
+            Object::BoundMethod(method) => Rc::as_ptr(method) as *const (),
+            Object::Enum(enumeration) => Rc::as_ptr(enumeration) as *const (),
+            Object::Variant(variant) => Rc::as_ptr(variant) as *const (),
+            Object::Regex(regex) => Rc::as_ptr(regex) as *const (),
+        }
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Str(a), Object::Str(b)) => a == b,
+            (Object::Str(_), _) | (_, Object::Str(_)) => false,
+            (Object::Variant(a), Object::Variant(b)) => a == b,
+            _ => std::ptr::eq(self.address(), other.address()),
+        }
+    }
+}
+
+impl Hash for Object {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Object::Str(string) => string.hash(state),
+            Object::Variant(variant) => {
+                Rc::as_ptr(&variant.enumeration).hash(state);
+                variant.index.hash(state);
+                variant.values.hash(state);
+            }
+            _ => self.address().hash(state),
+        }
+    }
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Object::Str(string) => write!(f, "{string}"),
+            Object::List(list) => {
+                let items: Vec<String> = list.borrow().iter().map(Value::repr).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Object::Map(map) => {
+                let entries: Vec<String> = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key.repr(), value.repr()))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Object::Set(set) => {
+                let items: Vec<String> = set.borrow().iter().map(Value::repr).collect();
+                write!(f, "Set([{}])", items.join(", "))
+            }
+            Object::Iterator(_) => write!(f, "<iterator>"),
+            Object::Native(native) => write!(f, "<native fn {}>", native.name),
+            Object::Closure(closure) => write!(f, "{}", closure.function),
+            Object::Coroutine(coroutine) => write!(f, "<coroutine {}>", coroutine.borrow().name),
+            Object::Exception(exception) => write!(f, "{}", exception.message),
+            Object::Module(module) => write!(f, "<module {}>", module.name()),
+            Object::Class(class) => write!(f, "<class {}>", class.name),
+            Object::Instance(instance) => write!(f, "<{} instance>", instance.class.name),
+            Object::BoundMethod(method) => write!(f, "{}", method.method.function),
+            Object::Enum(enumeration) => write!(f, "<enum {}>", enumeration.name),
+            Object::Variant(variant) => write!(f, "{variant}"),
+            Object::Regex(regex) => write!(f, "<regex {}>", regex.pattern()),
+        }
+    }
 }
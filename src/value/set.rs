@@ -0,0 +1,76 @@
+use crate::value::map::Map;
+use crate::value::Value;
+
+/// An insertion-ordered set of hashable values, created by `Set()` or `Set(list)`. It is a map
+/// whose values are all nil, so it iterates and prints in the order the values were added.
+///
+/// Like lists and maps, a set is itself hashable by identity: a set can hold another set, but it
+/// only finds that same object, not a different set with the same values.
+#[derive(Clone, Default, Debug)]
+pub struct Set {
+    items: Map,
+}
+
+impl Set {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn contains(&self, value: &Value) -> bool {
+        self.items.contains_key(value)
+    }
+
+    /// Adds the value and tells whether it was missing. The caller is responsible for checking
+    /// that the value is hashable.
+    pub fn insert(&mut self, value: Value) -> bool {
+        self.items.insert(value, Value::Nil).is_none()
+    }
+
+    /// Removes the value and tells whether it was there.
+    pub fn remove(&mut self, value: &Value) -> bool {
+        self.items.remove(value).is_some()
+    }
+
+    /// Value at the given position in insertion order.
+    pub fn get_index(&self, index: usize) -> Option<&Value> {
+        self.items.get_index(index).map(|(value, _)| value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Value> {
+        self.items.keys()
+    }
+
+    /// Values of both sets, the ones of this set first.
+    pub fn union(&self, other: &Set) -> Set {
+        self.iter().chain(other.iter()).cloned().collect()
+    }
+
+    /// Values of this set that are also in the other one.
+    pub fn intersection(&self, other: &Set) -> Set {
+        self.iter().filter(|value| other.contains(value)).cloned().collect()
+    }
+
+    /// Values of this set that are not in the other one.
+    pub fn difference(&self, other: &Set) -> Set {
+        self.iter().filter(|value| !other.contains(value)).cloned().collect()
+    }
+}
+
+impl FromIterator<Value> for Set {
+    fn from_iter<I: IntoIterator<Item = Value>>(iterator: I) -> Self {
+        let mut set = Set::new();
+        for value in iterator {
+            set.insert(value);
+        }
+
+        set
+    }
+}
@@ -2,95 +2,515 @@ use crate::chunk::Chunk;
 use crate::chunk::Code;
 use crate::error::RuntimeError;
 use crate::opcode::OpCode;
+use crate::stdlib;
+use crate::stdlib::random::Rng;
+use crate::value::class::BoundMethod;
+use crate::value::class::Class;
+use crate::value::class::Instance;
+use crate::value::class::MemberKind;
+use crate::value::coroutine::Coroutine;
+use crate::value::coroutine::CoroutineState;
+use crate::value::enumeration::Enum;
+use crate::value::enumeration::Variant;
+use crate::value::exception::Exception;
+use crate::value::float_to_exact_int;
+use crate::value::function::Closure;
+use crate::value::function::Function;
+use crate::value::function::Upvalue;
+use crate::value::iterator::NativeIterator;
+use crate::value::map::Map;
+use crate::value::module::Module;
+use crate::value::native::NativeFn;
+use crate::value::native::NativeFunction;
 use crate::value::object::Object;
 use crate::value::Value;
+use crate::vm::policy::EnvPolicy;
+use crate::vm::policy::FilePolicy;
 use crate::Line;
 use anyhow::Result;
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+use std::time::Instant;
+
+pub mod policy;
+
+/// Maximum number of nested calls, deeper recursion is reported as a stack overflow.
+const FRAMES_MAX: usize = 1024;
 
 pub struct VirtualMachine {
     stack: Vec<Value>,
-    globals: HashMap<String, Value>,
+    frames: Vec<CallFrame>,
+    /// Upvalues that still point to a stack slot, they are closed when the slot is discarded.
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    /// Module of the main script, the code of every frame uses the globals of its own module.
+    module: Rc<Module>,
+    /// Natives, visible from every module.
+    builtins: HashMap<String, Value>,
+    /// Modules that have been imported, so each one is only compiled and run once.
+    modules: HashMap<PathBuf, Rc<Module>>,
+    /// Modules being run, from the main script to the innermost import.
+    loading: Vec<PathBuf>,
     current_line: Line,
+    /// Value being thrown by OP_THROW, until a handler receives it, see catch_error().
+    thrown: Option<Value>,
+    /// Files the `io` module may access, none by default.
+    file_policy: FilePolicy,
+    /// Environment variables the `env` module may read, none by default.
+    env_policy: EnvPolicy,
+    /// When the VM was created, `clock()` counts from it.
+    started_at: Instant,
+    /// Generator of the `random` module.
+    rng: Rng,
+    /// Class returned by the `next()` method of an iterator instance once it is exhausted.
+    stop_iteration: Rc<Class>,
+}
+
+/// Function being run. Its local slots start at `slot_base`, where the function itself is stored.
+#[derive(Debug)]
+pub(crate) struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    slot_base: usize,
+    /// Arguments passed by the caller, the parameters after them take their default values.
+    argument_count: usize,
+    /// Handlers installed by the try statements of this call, they are dropped when it returns.
+    handlers: Vec<Handler>,
+    /// Coroutine running this frame, if any, and what resumed it.
+    coroutine: Option<Rc<RefCell<Coroutine>>>,
+    resumer: Resumer,
+    /// Temporaries set aside by the match expressions being evaluated, see begin_match().
+    matches: Vec<SavedTemporaries>,
+}
+
+/// Values an expression left on the stack below a match subject, and the height of the stack
+/// relative to the frame before the subject was pushed.
+#[derive(Debug)]
+struct SavedTemporaries {
+    height: usize,
+    values: Vec<Value>,
+}
+
+/// Instruction that resumed a coroutine. It decides what the caller receives when the coroutine
+/// yields or returns.
+#[derive(Debug, Clone, Copy)]
+enum Resumer {
+    /// A `next()` or `resume()` call, it receives the yielded or the returned value.
+    Call,
+    /// OP_FOR_ITER, a yielded value is the next element and returning ends the loop, so the
+    /// caller jumps by the given offset.
+    ForIter(usize),
+}
+
+impl CallFrame {
+    fn new(closure: Rc<Closure>, slot_base: usize, argument_count: usize) -> Self {
+        Self {
+            closure,
+            ip: 0,
+            slot_base,
+            argument_count,
+            handlers: vec![],
+            coroutine: None,
+            resumer: Resumer::Call,
+            matches: vec![],
+        }
+    }
+
+    /// Moves the frame to a new position in the stack, the handlers keep their relative heights.
+    fn relocate(&mut self, slot_base: usize) {
+        for handler in &mut self.handlers {
+            handler.stack_height = handler.stack_height - self.slot_base + slot_base;
+        }
+
+        self.slot_base = slot_base;
+    }
+
+    /// Line of the instruction being executed in this frame.
+    fn line(&self) -> Line {
+        let index = self.ip.saturating_sub(1);
+        self.closure.function.chunk.codes.get(index).map(|code| code.1).unwrap_or_default()
+    }
+}
+
+/// Exception handler installed by a `try` statement.
+#[derive(Debug)]
+pub(crate) struct Handler {
+    /// Instruction that receives the exception.
+    target: usize,
+    /// Stack height to restore before pushing the exception.
+    stack_height: usize,
+    /// Match expressions being evaluated when the handler was installed, the ones started later
+    /// are abandoned when it catches an exception.
+    match_depth: usize,
 }
 
 impl VirtualMachine {
+    /// VM with the natives and the prelude, the library functions written in Lox.
     pub fn initialize() -> Self {
-        Self {
-            // TODO: ADD STACKOVERFLOW ERROR
+        let mut vm = Self::without_prelude();
+        vm.load_prelude();
+        vm
+    }
+
+    /// VM with only the natives, for embedders that want to provide their own library.
+    pub fn without_prelude() -> Self {
+        let mut vm = Self {
             stack: Vec::with_capacity(u8::MAX.into()),
-            globals: HashMap::new(),
+            frames: vec![],
+            open_upvalues: vec![],
+            module: Rc::new(Module::new(None)),
+            builtins: HashMap::new(),
+            modules: HashMap::new(),
+            loading: vec![],
             current_line: 0,
+            thrown: None,
+            file_policy: FilePolicy::default(),
+            env_policy: EnvPolicy::default(),
+            started_at: Instant::now(),
+            rng: Rng::from_time(),
+            stop_iteration: Rc::new(Class::new("StopIteration".to_string())),
+        };
+
+        let stop_iteration = Value::Object(Object::Class(vm.stop_iteration.clone()));
+        vm.builtins.insert("StopIteration".to_string(), stop_iteration);
+        stdlib::register(&mut vm);
+        vm
+    }
+
+    /// Runs the prelude in a module of its own, its globals become builtins.
+    fn load_prelude(&mut self) {
+        let module = Rc::new(Module::native("prelude", vec![]));
+        self.run_in_module(stdlib::prelude::chunk(), module.clone()).expect("the prelude runs");
+
+        for (name, value) in module.globals.borrow().iter() {
+            self.builtins.insert(name.clone(), value.clone());
         }
     }
 
+    /// Runs a chunk as the top level code of the main script.
     pub fn run(&mut self, chunk: Chunk) -> Result<()> {
-        for Code(opcode, line) in chunk.codes.into_iter() {
-            self.current_line = line;
+        self.thrown = None;
+        self.run_in_module(chunk, self.module.clone())
+    }
+
+    /// Runs a chunk in a new frame on top of the current ones, like when a module is imported.
+    /// Errors that escape it aren't caught by the handlers of the frames below, they are returned.
+    fn run_in_module(&mut self, chunk: Chunk, module: Rc<Module>) -> Result<()> {
+        let function = Rc::new(Function {
+            chunk,
+            ..Function::default()
+        });
+        let closure = Rc::new(Closure {
+            function,
+            upvalues: vec![],
+            module,
+        });
 
-            match opcode {
-                OpCode::Return => break,
-                OpCode::Pop => self.drop_stack_value()?,
-                OpCode::Constant(value) => self.stack.push(value),
-                OpCode::Nil => self.stack.push(Value::Nil),
-                OpCode::True => self.stack.push(true.into()),
-                OpCode::False => self.stack.push(false.into()),
-                OpCode::Add => self.execute_addition()?,
-                OpCode::Substract | OpCode::Multiply | OpCode::Divide => self.execute_binary_operation(opcode)?,
-                OpCode::Negate => self.execute_number_negation()?,
-                OpCode::Equal => self.verify_equality()?,
-                OpCode::Less | OpCode::Greater => self.interpret_binary_boolean_operation(opcode)?,
-                OpCode::Print => self.print_value(),
-                OpCode::Not => self.execute_boolean_negation()?,
-                OpCode::DefineGlobal(identifier) => self.define_global_variable(identifier),
-                OpCode::GetGlobal(identifier) => self.get_global_variable(identifier)?,
-                OpCode::SetGlobal(identifier) => self.set_global_variable(identifier)?,
+        let base = self.stack.len();
+        let depth = self.frames.len();
+        self.stack.push(Value::Object(Object::Closure(closure.clone())));
+        self.frames.push(CallFrame::new(closure, base, 0));
+
+        let result = self.run_frames(depth);
+
+        self.close_upvalues(base);
+        self.truncate_frames(depth);
+        self.stack.truncate(base);
+
+        result
+    }
+
+    /// Executes instructions until the frames above `depth` have returned.
+    fn run_frames(&mut self, depth: usize) -> Result<()> {
+        while self.frames.len() > depth {
+            let Some(Code(opcode, line)) = self.read_code() else {
+                break;
             };
+
+            self.current_line = line;
+
+            // NOTE: Errors are thrown as exceptions, they only abort the script when no handler
+            // catches them.
+            if let Err(error) = self.execute_instruction(opcode) {
+                self.catch_error(error, depth)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_code(&mut self) -> Option<Code> {
+        let frame = self.frames.last_mut()?;
+        let code = frame.closure.function.chunk.codes.get(frame.ip).cloned();
+        frame.ip += 1;
+
+        code
+    }
+
+    /// Discards the frames above the given number. Coroutines whose frame is discarded, because
+    /// an error escaped from them, can't be resumed again.
+    fn truncate_frames(&mut self, length: usize) {
+        for frame in self.frames.drain(length.min(self.frames.len())..) {
+            if let Some(coroutine) = frame.coroutine {
+                let mut coroutine = coroutine.borrow_mut();
+                coroutine.state = CoroutineState::Done;
+                coroutine.stack.clear();
+            }
         }
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("instructions always run inside a frame")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("instructions always run inside a frame")
+    }
+
+    /// Module whose globals are used by the code being run.
+    fn current_module(&self) -> &Rc<Module> {
+        self.frames.last().map(|frame| &frame.closure.module).unwrap_or(&self.module)
+    }
+
+    fn execute_instruction(&mut self, opcode: OpCode) -> Result<()> {
+        match opcode {
+            OpCode::Return => self.return_from_frame(),
+            OpCode::Pop => self.drop_stack_value()?,
+            OpCode::Dup => self.stack.push(self.stack.last().cloned().unwrap_or_default()),
+            OpCode::Constant(value) => self.stack.push(value),
+            OpCode::Nil => self.stack.push(Value::Nil),
+            OpCode::True => self.stack.push(true.into()),
+            OpCode::False => self.stack.push(false.into()),
+            OpCode::Add => self.execute_addition()?,
+            OpCode::Substract | OpCode::Multiply | OpCode::Divide => self.execute_binary_operation(opcode)?,
+            OpCode::BitAnd | OpCode::BitOr | OpCode::BitXor | OpCode::ShiftLeft | OpCode::ShiftRight => self.execute_bitwise_operation(opcode)?,
+            OpCode::BitNot => self.execute_bitwise_negation()?,
+            OpCode::Negate => self.execute_number_negation()?,
+            OpCode::Equal => self.verify_equality()?,
+            OpCode::Less | OpCode::Greater => self.interpret_binary_boolean_operation(opcode)?,
+            OpCode::Print => self.print_value()?,
+            OpCode::Not => self.execute_boolean_negation()?,
+            OpCode::DefineGlobal(identifier) => self.define_global_variable(identifier, false)?,
+            OpCode::DefineConstant(identifier) => self.define_global_variable(identifier, true)?,
+            OpCode::GetGlobal(identifier) => self.get_global_variable(identifier)?,
+            OpCode::SetGlobal(identifier) => self.set_global_variable(identifier)?,
+            OpCode::Export(identifier) => drop(self.current_module().exports.borrow_mut().insert(identifier)),
+            OpCode::Import(path) => self.import_module(path)?,
+            OpCode::GetProperty(name) => self.get_property(name)?,
+            OpCode::Destructure(name) => self.destructure(name)?,
+            OpCode::SetProperty(name) => self.set_property(name)?,
+            OpCode::GetSuper(name) => self.get_super(name)?,
+            OpCode::Class(name, doc) => {
+                let class = Class { doc, ..Class::new(name) };
+                self.stack.push(Value::Object(Object::Class(Rc::new(class))));
+            }
+            OpCode::Inherit => self.inherit()?,
+            OpCode::Member(name, kind) => self.add_member(name, kind),
+            OpCode::IsVariant(name, field_count) => self.is_variant(name, field_count)?,
+            OpCode::GetVariantField(index) => {
+                let value = match self.stack.pop() {
+                    Some(Value::Object(Object::Variant(variant))) => variant.values.get(index).cloned(),
+                    _ => None,
+                };
+
+                self.stack.push(value.unwrap_or_default());
+            }
+            OpCode::NoMatch => {
+                let value = self.stack.pop().unwrap_or_default();
+                return Err(RuntimeError::NoMatchingArm(value.repr(), self.current_line).into());
+            }
+            OpCode::BeginMatch(slot) => self.begin_match(slot),
+            OpCode::EndMatch(slot) => self.end_match(slot),
+            OpCode::GetLocal(slot) => self.get_local_variable(slot),
+            OpCode::SetLocal(slot) => self.set_local_variable(slot),
+            OpCode::GetUpvalue(index) => self.get_upvalue(index),
+            OpCode::SetUpvalue(index) => self.set_upvalue(index),
+            OpCode::CloseUpvalue => {
+                self.close_upvalues(self.stack.len().saturating_sub(1));
+                self.stack.pop();
+            }
+            OpCode::Closure(function) => self.create_closure(function),
+            OpCode::BuildList(count) => self.build_list(count),
+            OpCode::BuildMap(count) => self.build_map(count)?,
+            OpCode::GetIndex => self.get_index()?,
+            OpCode::SetIndex => self.set_index()?,
+            OpCode::Call(argument_count) => self.call_from_stack(argument_count.into())?,
+            OpCode::CallSpread(list_count) => {
+                let argument_count = self.spread_arguments(list_count)?;
+                self.call_from_stack(argument_count)?;
+            }
+            OpCode::Invoke(name, argument_count) => self.invoke_method(name, argument_count.into())?,
+            OpCode::InvokeSpread(name, list_count) => {
+                let argument_count = self.spread_arguments(list_count)?;
+                self.invoke_method(name, argument_count)?;
+            }
+            OpCode::GetIterator => self.get_iterator()?,
+            OpCode::ForIter(slot, offset) => self.iterate(slot, offset)?,
+            OpCode::Loop(offset) => self.frame_mut().ip -= offset,
+            OpCode::Jump(offset) => self.frame_mut().ip += offset,
+            OpCode::JumpIfFalse(offset) => {
+                if self.stack.last().is_some_and(Value::is_falsey) {
+                    self.frame_mut().ip += offset;
+                }
+            }
+            OpCode::DefaultParameter(slot, offset) => {
+                let frame = self.frame_mut();
+
+                if slot <= frame.argument_count {
+                    frame.ip += offset;
+                }
+            }
+            OpCode::PushHandler(offset, slots) => self.push_handler(offset, slots),
+            OpCode::PopHandler => drop(self.frame_mut().handlers.pop()),
+            OpCode::Yield => self.yield_value()?,
+            OpCode::Throw => {
+                let value = self.stack.pop().unwrap_or_default();
+                self.throw_value(value)?;
+            }
+            OpCode::EndFinally => self.end_finally()?,
+        };
 
         Ok(())
     }
 
+    /// Two integers produce an integer, the operation fails if it overflows. Any other pair of
+    /// numbers is promoted to floats. Division always produces a float.
     fn execute_binary_operation(&mut self, opcode: OpCode) -> Result<()> {
-        let (Some(Value::Number(b)), Some(Value::Number(a))) = (self.stack.pop(), self.stack.pop()) else {
+        let (method, reflected) = match opcode {
+            OpCode::Substract => ("__sub__", "__rsub__"),
+            OpCode::Multiply => ("__mul__", "__rmul__"),
+            _ => ("__div__", "__rdiv__"),
+        };
+
+        if self.call_binary_operator_method(method, reflected)? {
+            return Ok(());
+        }
+
+        let (Some(b), Some(a)) = (self.stack.pop(), self.stack.pop()) else {
             return Err(RuntimeError::ExpectedNumber(self.current_line).into());
         };
 
+        let result = match (a, b) {
+            (Value::Int(a), Value::Int(b)) if !matches!(opcode, OpCode::Divide) => {
+                let result = match opcode {
+                    OpCode::Substract => a.checked_sub(b),
+                    OpCode::Multiply => a.checked_mul(b),
+                    _ => unreachable!(),
+                };
+
+                Value::Int(result.ok_or(RuntimeError::IntegerOverflow(self.current_line))?)
+            }
+            (a, b) => {
+                let (Some(a), Some(b)) = (a.as_f64(), b.as_f64()) else {
+                    return Err(RuntimeError::ExpectedNumber(self.current_line).into());
+                };
+
+                let result = match opcode {
+                    OpCode::Substract => a - b,
+                    OpCode::Multiply => a * b,
+                    OpCode::Divide => a / b,
+                    _ => unreachable!(),
+                };
+
+                Value::Number(result)
+            }
+        };
+
+        self.stack.push(result);
+
+        Ok(())
+    }
+
+    /// Bitwise operators work on integers and on floats without a fractional part.
+    fn execute_bitwise_operation(&mut self, opcode: OpCode) -> Result<()> {
+        let (Some(b), Some(a)) = (self.stack.pop(), self.stack.pop()) else {
+            return Err(RuntimeError::ExpectedInteger(self.current_line).into());
+        };
+
+        let (a, b) = (self.verify_integer(&a)?, self.verify_integer(&b)?);
+
         let result = match opcode {
-            OpCode::Substract => a - b,
-            OpCode::Multiply => a * b,
-            OpCode::Divide => a / b,
+            OpCode::BitAnd => a & b,
+            OpCode::BitOr => a | b,
+            OpCode::BitXor => a ^ b,
+            OpCode::ShiftLeft | OpCode::ShiftRight => {
+                let shift = u32::try_from(b).ok().filter(|shift| *shift < i64::BITS);
+                let Some(shift) = shift else {
+                    return Err(RuntimeError::ShiftOutOfRange(b, self.current_line).into());
+                };
+
+                match opcode {
+                    OpCode::ShiftLeft => a << shift,
+                    _ => a >> shift,
+                }
+            }
             _ => unreachable!(),
         };
 
-        self.stack.push(result.into());
+        self.stack.push(Value::Int(result));
+        Ok(())
+    }
 
+    fn execute_bitwise_negation(&mut self) -> Result<()> {
+        let value = self.stack.pop().unwrap_or_default();
+        let integer = self.verify_integer(&value)?;
+
+        self.stack.push(Value::Int(!integer));
         Ok(())
     }
 
+    fn verify_integer(&self, value: &Value) -> Result<i64> {
+        let integer = match value {
+            Value::Int(integer) => Some(*integer),
+            Value::Number(number) => float_to_exact_int(*number),
+            _ => None,
+        };
+
+        Ok(integer.ok_or(RuntimeError::ExpectedInteger(self.current_line))?)
+    }
+
     fn execute_number_negation(&mut self) -> Result<()> {
-        let Some(Value::Number(number)) = self.stack.pop() else {
-            return Err(RuntimeError::ExpectedNumber(self.current_line).into());
+        if self.call_operator_method("__neg__", 0)? {
+            return Ok(());
+        }
+
+        let result = match self.stack.pop() {
+            Some(Value::Number(number)) => Value::Number(-number),
+            Some(Value::Int(integer)) => Value::Int(integer.checked_neg().ok_or(RuntimeError::IntegerOverflow(self.current_line))?),
+            _ => return Err(RuntimeError::ExpectedNumber(self.current_line).into()),
         };
 
-        self.stack.push((-number).into());
+        self.stack.push(result);
         Ok(())
     }
 
     fn execute_boolean_negation(&mut self) -> Result<()> {
-        let is_falsey = match self.stack.pop() {
-            Some(Value::Nil) | Some(Value::Bool(false)) => true,
-            _ => false,
-        };
+        let is_falsey = self.stack.pop().is_none_or(|value| value.is_falsey());
 
         self.stack.push(is_falsey.into());
         Ok(())
     }
 
     fn execute_addition(&mut self) -> Result<()> {
+        if self.call_binary_operator_method("__add__", "__radd__")? {
+            return Ok(());
+        }
+
         match (self.stack.pop(), self.stack.pop()) {
-            (Some(Value::Number(a)), Some(Value::Number(b))) => self.stack.push(Value::from(a + b)),
-            (Some(Value::Object(Object::Str(a))), Some(Value::Object(Object::Str(b)))) => self.stack.push(format!("{a}{b}").into()),
+            (Some(Value::Int(b)), Some(Value::Int(a))) => {
+                let result = a.checked_add(b).ok_or(RuntimeError::IntegerOverflow(self.current_line))?;
+                self.stack.push(Value::from(result));
+            }
+            (Some(Value::Object(Object::Str(b))), Some(Value::Object(Object::Str(a)))) => self.stack.push(format!("{a}{b}").into()),
+            (Some(b), Some(a)) => match (a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b)) => self.stack.push(Value::from(a + b)),
+                _ => return Err(RuntimeError::ExpectedNumberOrString(self.current_line).into()),
+            },
             _ => return Err(RuntimeError::ExpectedNumberOrString(self.current_line).into()),
         };
 
@@ -102,14 +522,33 @@ impl VirtualMachine {
         Ok(())
     }
 
+    /// Integers are compared exactly, mixed operands are compared as floats. Instances are
+    /// compared with `__lt__` and `__gt__`, `a > b` falls back to `b < a` and `a < b` to `b > a`.
     fn interpret_binary_boolean_operation(&mut self, opcode: OpCode) -> Result<()> {
-        let (Some(Value::Number(b)), Some(Value::Number(a))) = (self.stack.pop(), self.stack.pop()) else {
+        let (method, reflected) = match opcode {
+            OpCode::Less => ("__lt__", "__gt__"),
+            _ => ("__gt__", "__lt__"),
+        };
+
+        if self.call_binary_operator_method(method, reflected)? {
+            return Ok(());
+        }
+
+        let (Some(b), Some(a)) = (self.stack.pop(), self.stack.pop()) else {
             return Err(RuntimeError::ExpectedNumber(self.current_line).into());
         };
 
+        let ordering = match (&a, &b) {
+            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+            _ => match (a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b)) => a.partial_cmp(&b),
+                _ => return Err(RuntimeError::ExpectedNumber(self.current_line).into()),
+            },
+        };
+
         let result = match opcode {
-            OpCode::Less => a < b,
-            OpCode::Greater => a > b,
+            OpCode::Less => ordering == Some(Ordering::Less),
+            OpCode::Greater => ordering == Some(Ordering::Greater),
             _ => unreachable!(),
         };
 
@@ -119,34 +558,153 @@ impl VirtualMachine {
     }
 
     fn verify_equality(&mut self) -> Result<()> {
+        if self.call_binary_operator_method("__eq__", "__eq__")? {
+            return Ok(());
+        }
+
         let (Some(b), Some(a)) = (self.stack.pop(), self.stack.pop()) else {
             return Err(RuntimeError::ExpectedValue(self.current_line).into());
         };
 
-        let result = match (a, b) {
-            (Value::Nil, Value::Nil) => true,
-            (Value::Number(x), Value::Number(y)) => x == y,
-            (Value::Bool(x), Value::Bool(y)) => x == y,
-            (Value::Object(Object::Str(x)), Value::Object(Object::Str(y))) => x.eq(&y),
-            _ => false,
-        };
+        self.stack.push((a == b).into());
 
-        self.stack.push(result.into());
+        Ok(())
+    }
+
+    fn print_value(&mut self) -> Result<()> {
+        let value = self.stack.pop().unwrap_or_default();
+        println!("{}", self.stringify(value)?);
 
         Ok(())
     }
 
-    fn print_value(&mut self) {
-        println!("{}", self.stack.pop().unwrap_or_default());
+    /// Text of a value as printed, instances with a `__str__` method are converted by it, also
+    /// inside lists, maps, sets and variants.
+    pub(crate) fn stringify(&mut self, value: Value) -> Result<String> {
+        self.stringify_nested(value, &mut vec![], false)
+    }
+
+    /// Items of containers are quoted, like `["a"]`. `open` holds the containers being written,
+    /// one found inside itself is written as `[...]`, `{...}` or `Set(...)`.
+    fn stringify_nested(&mut self, value: Value, open: &mut Vec<*const ()>, quoted: bool) -> Result<String> {
+        let Value::Object(object) = &value else {
+            return Ok(value.to_string());
+        };
+
+        let address = object.address();
+        let is_open = open.contains(&address);
+
+        let text = match object {
+            Object::Str(_) if quoted => value.repr(),
+            Object::Instance(instance) => {
+                let method = instance.class.methods.borrow().get("__str__").cloned();
+                let Some(method) = method else {
+                    return Ok(value.to_string());
+                };
+
+                let method = Value::Object(Object::BoundMethod(Rc::new(BoundMethod {
+                    receiver: value.clone(),
+                    method,
+                })));
+                self.call_value(method, vec![])?.to_string()
+            }
+            Object::List(_) if is_open => "[...]".to_string(),
+            Object::List(list) => {
+                let items = list.borrow().clone();
+                format!("[{}]", self.stringify_items(items, open, address)?.join(", "))
+            }
+            Object::Set(_) if is_open => "Set(...)".to_string(),
+            Object::Set(set) => {
+                let items = set.borrow().iter().cloned().collect();
+                format!("Set([{}])", self.stringify_items(items, open, address)?.join(", "))
+            }
+            Object::Map(_) if is_open => "{...}".to_string(),
+            Object::Map(map) => {
+                let (keys, values) = map.borrow().iter().cloned().unzip();
+                let keys = self.stringify_items(keys, open, address)?;
+                let values = self.stringify_items(values, open, address)?;
+                let entries: Vec<String> = keys.into_iter().zip(values).map(|(key, value)| format!("{key}: {value}")).collect();
+                format!("{{{}}}", entries.join(", "))
+            }
+            Object::Variant(variant) if !variant.values.is_empty() => {
+                let values = self.stringify_items(variant.values.clone(), open, address)?;
+                format!("{}.{}({})", variant.enumeration.name, variant.name(), values.join(", "))
+            }
+            _ => value.to_string(),
+        };
+
+        Ok(text)
+    }
+
+    fn stringify_items(&mut self, items: Vec<Value>, open: &mut Vec<*const ()>, container: *const ()) -> Result<Vec<String>> {
+        open.push(container);
+        let texts = items.into_iter().map(|item| self.stringify_nested(item, open, true)).collect();
+        open.pop();
+
+        texts
+    }
+
+    /// Operators fall back to the special methods of instances, like `__add__` for `a + b`. The
+    /// operands are still on the stack with the receiver first, the method is called with the
+    /// other ones as arguments and its result replaces them. Returns false, leaving the stack
+    /// untouched, when the receiver isn't an instance defining the method.
+    fn call_operator_method(&mut self, name: &str, argument_count: usize) -> Result<bool> {
+        let receiver_slot = self.stack.len().saturating_sub(argument_count + 1);
+        let Some(Value::Object(Object::Instance(instance))) = self.stack.get(receiver_slot) else {
+            return Ok(false);
+        };
+
+        let method = instance.class.methods.borrow().get(name).cloned();
+        let Some(method) = method else {
+            return Ok(false);
+        };
+
+        self.call_closure(method, receiver_slot, argument_count)?;
+        Ok(true)
+    }
+
+    /// Binary operators try the method of the left operand, then the reflected method of the
+    /// right one, called with the left operand as its argument: `1 + v` calls `v.__radd__(1)`.
+    fn call_binary_operator_method(&mut self, name: &str, reflected: &str) -> Result<bool> {
+        if self.call_operator_method(name, 1)? {
+            return Ok(true);
+        }
+
+        let length = self.stack.len();
+        self.stack.swap(length.saturating_sub(1), length.saturating_sub(2));
+
+        if self.call_operator_method(reflected, 1)? {
+            return Ok(true);
+        }
+
+        self.stack.swap(length.saturating_sub(1), length.saturating_sub(2));
+        Ok(false)
     }
 
-    fn define_global_variable(&mut self, identifier: String) {
-        self.globals.insert(identifier, self.stack.pop().unwrap_or_default());
+    /// Constants are checked here as well as by the compiler, since a global can be declared by
+    /// an earlier chunk, like a previous line of the REPL.
+    fn define_global_variable(&mut self, identifier: String, is_constant: bool) -> Result<()> {
+        let value = self.stack.pop().unwrap_or_default();
+        let module = self.current_module().clone();
+
+        if module.constants.borrow().contains(&identifier) {
+            return Err(RuntimeError::AssignToConstant(identifier, self.current_line).into());
+        }
+
+        if is_constant {
+            module.constants.borrow_mut().insert(identifier.clone());
+        }
+
+        module.globals.borrow_mut().insert(identifier, value);
+        Ok(())
     }
 
+    /// Globals of the current module shadow the natives.
     fn get_global_variable(&mut self, identifier: String) -> Result<()> {
-        match self.globals.get(&identifier) {
-            Some(value) => self.stack.push(value.to_owned()),
+        let value = self.current_module().globals.borrow().get(&identifier).cloned();
+
+        match value.or_else(|| self.builtins.get(&identifier).cloned()) {
+            Some(value) => self.stack.push(value),
             None => return Err(RuntimeError::UndefinedVariable(identifier, self.current_line).into()),
         }
 
@@ -154,11 +712,1204 @@ impl VirtualMachine {
     }
 
     fn set_global_variable(&mut self, identifier: String) -> Result<()> {
-        match self.globals.get_mut(&identifier) {
-            Some(value) => *value = self.stack.pop().unwrap_or_default(),
+        if self.current_module().constants.borrow().contains(&identifier) {
+            return Err(RuntimeError::AssignToConstant(identifier, self.current_line).into());
+        }
+
+        match self.current_module().globals.borrow_mut().get_mut(&identifier) {
+            // NOTE: Assignment is an expression, so the value stays on the stack.
+            Some(value) => *value = self.stack.last().cloned().unwrap_or_default(),
             None => return Err(RuntimeError::UndefinedVariable(identifier, self.current_line).into()),
         }
 
         Ok(())
     }
+
+    fn get_local_variable(&mut self, slot: usize) {
+        let value = self.stack.get(self.frame().slot_base + slot).cloned().unwrap_or_default();
+        self.stack.push(value);
+    }
+
+    fn set_local_variable(&mut self, slot: usize) {
+        let value = self.stack.last().cloned().unwrap_or_default();
+        let slot = self.frame().slot_base + slot;
+
+        if let Some(local) = self.stack.get_mut(slot) {
+            *local = value;
+        }
+    }
+
+    // NOTE: Closures.
+
+    /// Captures the variables listed by the function: locals of the running frame, or upvalues of
+    /// the running closure. The new closure uses the globals of the module where it is created.
+    fn create_closure(&mut self, function: Rc<Function>) {
+        let slot_base = self.frame().slot_base;
+        let enclosing = self.frame().closure.clone();
+
+        let upvalues = function
+            .upvalues
+            .iter()
+            .map(|source| match source.is_local {
+                true => self.capture_upvalue(slot_base + source.index),
+                false => enclosing.upvalues[source.index].clone(),
+            })
+            .collect();
+
+        let closure = Closure {
+            function,
+            upvalues,
+            module: enclosing.module.clone(),
+        };
+
+        self.stack.push(Value::Object(Object::Closure(Rc::new(closure))));
+    }
+
+    /// Closures that capture the same variable share its upvalue, so they see each other's
+    /// assignments.
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let existing = self
+            .open_upvalues
+            .iter()
+            .find(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(open) if open == slot));
+
+        if let Some(upvalue) = existing {
+            return upvalue.clone();
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.push(upvalue.clone());
+        upvalue
+    }
+
+    /// Moves the values of the slots from `first_slot` upwards into the upvalues that point to
+    /// them, before the slots are discarded.
+    fn close_upvalues(&mut self, first_slot: usize) {
+        let stack = &self.stack;
+
+        self.open_upvalues.retain(|upvalue| {
+            let mut upvalue = upvalue.borrow_mut();
+
+            match *upvalue {
+                Upvalue::Open(slot) if slot >= first_slot => {
+                    *upvalue = Upvalue::Closed(stack.get(slot).cloned().unwrap_or_default());
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+
+    fn get_upvalue(&mut self, index: usize) {
+        let upvalue = self.frame().closure.upvalues[index].clone();
+
+        let value = match &*upvalue.borrow() {
+            Upvalue::Open(slot) => self.stack.get(*slot).cloned().unwrap_or_default(),
+            Upvalue::Suspended(coroutine, slot) => coroutine
+                .upgrade()
+                .and_then(|coroutine| coroutine.borrow().stack.get(*slot).cloned())
+                .unwrap_or_default(),
+            Upvalue::Closed(value) => value.clone(),
+        };
+
+        self.stack.push(value);
+    }
+
+    fn set_upvalue(&mut self, index: usize) {
+        let value = self.stack.last().cloned().unwrap_or_default();
+        let upvalue = self.frame().closure.upvalues[index].clone();
+
+        let mut upvalue = upvalue.borrow_mut();
+
+        match &mut *upvalue {
+            Upvalue::Open(slot) => {
+                if let Some(local) = self.stack.get_mut(*slot) {
+                    *local = value;
+                }
+            }
+            Upvalue::Suspended(coroutine, slot) => {
+                if let Some(coroutine) = coroutine.upgrade() {
+                    if let Some(local) = coroutine.borrow_mut().stack.get_mut(*slot) {
+                        *local = value;
+                    }
+                }
+            }
+            Upvalue::Closed(closed) => *closed = value,
+        }
+    }
+
+    // NOTE: Modules.
+
+    /// Imports are resolved relative to the directory of the importing module, or to the working
+    /// directory when the importer isn't a file. A module is compiled and run the first time it is
+    /// imported, later imports reuse it.
+    fn import_module(&mut self, path: String) -> Result<()> {
+        let directory = self
+            .current_module()
+            .path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let Ok(resolved) = directory.join(&path).canonicalize() else {
+            return Err(RuntimeError::ModuleNotFound(path, self.current_line).into());
+        };
+
+        if let Some(module) = self.modules.get(&resolved) {
+            self.stack.push(Value::Object(Object::Module(module.clone())));
+            return Ok(());
+        }
+
+        if let Some(start) = self.loading.iter().position(|loading| *loading == resolved) {
+            let cycle: Vec<String> = self.loading[start..]
+                .iter()
+                .chain([&resolved])
+                .map(|path| path.display().to_string())
+                .collect();
+            return Err(RuntimeError::ImportCycle(cycle.join(" -> "), self.current_line).into());
+        }
+
+        let Ok(source) = read_to_string(&resolved) else {
+            return Err(RuntimeError::ModuleNotFound(path, self.current_line).into());
+        };
+
+        let mut chunk = Chunk::new();
+        if crate::compile(&mut chunk, &source).is_err() {
+            return Err(RuntimeError::ModuleCompileError(path, self.current_line).into());
+        }
+
+        let module = Rc::new(Module::new(Some(resolved.clone())));
+        let line = self.current_line;
+        self.loading.push(resolved.clone());
+
+        let result = self.run_in_module(chunk, module.clone());
+
+        self.loading.pop();
+        self.current_line = line;
+        result?;
+
+        self.modules.insert(resolved, module.clone());
+        self.stack.push(Value::Object(Object::Module(module)));
+
+        Ok(())
+    }
+
+    fn get_export(&self, module: &Module, name: String) -> Result<Value> {
+        match module.get_export(&name) {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::UndefinedExport(module.name(), name, self.current_line).into()),
+        }
+    }
+
+    /// Sets the file of the main script, imports are then resolved relative to it. The main
+    /// script gets a new module, so the globals defined so far are dropped.
+    pub fn set_script_path(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or(path.to_path_buf());
+
+        self.module = Rc::new(Module::new(Some(path.clone())));
+        self.loading = vec![path];
+    }
+
+    // NOTE: Classes.
+
+    /// The subclass is on top of the superclass, which stays on the stack as the `super` local.
+    fn inherit(&mut self) -> Result<()> {
+        let subclass = self.stack.pop();
+        let (Some(Value::Object(Object::Class(subclass))), Some(Value::Object(Object::Class(superclass)))) = (subclass, self.stack.last()) else {
+            return Err(RuntimeError::ExpectedSuperclass(self.current_line).into());
+        };
+
+        subclass.inherit(superclass.clone());
+        Ok(())
+    }
+
+    /// The closure of the member is on top of the class being declared.
+    fn add_member(&mut self, name: String, kind: MemberKind) {
+        let closure = self.stack.pop();
+
+        if let (Some(Value::Object(Object::Closure(closure))), Some(Value::Object(Object::Class(class)))) = (closure, self.stack.last()) {
+            class.add_member(kind, name, closure);
+        }
+    }
+
+    /// Instances look up their fields first, then their getters and methods. Reading a getter
+    /// calls it, and reading a method binds it to the instance. Classes have their static
+    /// methods as properties, modules their exports and variants their fields. Reading a variant
+    /// of an enum creates it, like calling it without values.
+    fn get_property(&mut self, name: String) -> Result<()> {
+        let receiver = self.stack.pop().unwrap_or_default();
+
+        let value = match &receiver {
+            Value::Object(Object::Instance(instance)) => {
+                let field = instance.fields.borrow().get(&name).cloned();
+                let getter = instance.class.getters.borrow().get(&name).cloned();
+                let method = instance.class.methods.borrow().get(&name).cloned();
+
+                match (field, getter, method) {
+                    (Some(value), _, _) => value,
+                    (None, Some(getter), _) => {
+                        let slot_base = self.stack.len();
+                        self.stack.push(receiver.clone());
+                        return self.call_closure(getter, slot_base, 0);
+                    }
+                    (None, None, Some(method)) => Value::Object(Object::BoundMethod(Rc::new(BoundMethod {
+                        receiver: receiver.clone(),
+                        method,
+                    }))),
+                    (None, None, None) => return Err(RuntimeError::UndefinedProperty(name, self.current_line).into()),
+                }
+            }
+            Value::Object(Object::Class(class)) => match class.static_methods.borrow().get(&name) {
+                Some(method) => Value::Object(Object::Closure(method.clone())),
+                None => return Err(RuntimeError::UndefinedProperty(name, self.current_line).into()),
+            },
+            Value::Object(Object::Module(module)) => self.get_export(module, name)?,
+            Value::Object(Object::Enum(enumeration)) => self.create_variant(enumeration.clone(), name, vec![])?,
+            Value::Object(Object::Variant(variant)) => match variant.field(&name) {
+                Some(value) => value,
+                None => return Err(RuntimeError::UndefinedProperty(name, self.current_line).into()),
+            },
+            _ => return Err(RuntimeError::ExpectedProperties(receiver.type_name(), self.current_line).into()),
+        };
+
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Reads a name of an object pattern, like `var {x} = value;`. Maps are read by key, like
+    /// `value["x"]`, anything else by property.
+    fn destructure(&mut self, name: String) -> Result<()> {
+        if let Some(Value::Object(Object::Map(_))) = self.stack.last() {
+            self.stack.push(name.into());
+            return self.get_index();
+        }
+
+        self.get_property(name)
+    }
+
+    /// Assigning a property with a setter calls it, the setter returns the assigned value. A
+    /// property with only a getter can't be assigned, any other assignment sets a field.
+    fn set_property(&mut self, name: String) -> Result<()> {
+        let (Some(value), Some(receiver)) = (self.stack.pop(), self.stack.pop()) else {
+            return Err(RuntimeError::ExpectedValue(self.current_line).into());
+        };
+
+        let Value::Object(Object::Instance(instance)) = &receiver else {
+            return Err(RuntimeError::ExpectedInstance(self.current_line).into());
+        };
+
+        let setter = instance.class.setters.borrow().get(&name).cloned();
+        if let Some(setter) = setter {
+            let slot_base = self.stack.len();
+            self.stack.push(receiver.clone());
+            self.stack.push(value);
+            return self.call_closure(setter, slot_base, 1);
+        }
+
+        if instance.class.getters.borrow().contains_key(&name) {
+            return Err(RuntimeError::ReadOnlyProperty(name, self.current_line).into());
+        }
+
+        instance.fields.borrow_mut().insert(name, value.clone());
+
+        // NOTE: Assignment is an expression, so the value stays on the stack.
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// `super.name` binds a method of the superclass, which is on top of the receiver.
+    fn get_super(&mut self, name: String) -> Result<()> {
+        let (Some(Value::Object(Object::Class(superclass))), Some(receiver)) = (self.stack.pop(), self.stack.pop()) else {
+            return Err(RuntimeError::ExpectedSuperclass(self.current_line).into());
+        };
+
+        let Some(method) = superclass.methods.borrow().get(&name).cloned() else {
+            return Err(RuntimeError::UndefinedProperty(name, self.current_line).into());
+        };
+
+        self.stack
+            .push(Value::Object(Object::BoundMethod(Rc::new(BoundMethod { receiver, method }))));
+        Ok(())
+    }
+
+    /// The new instance replaces the class in the callee slot. Its fields are initialized in
+    /// declaration order, then `init` runs with the arguments of the call.
+    fn instantiate(&mut self, class: Rc<Class>, slot_base: usize, argument_count: usize) -> Result<()> {
+        let instance = Rc::new(Instance::new(class.clone()));
+        let receiver = Value::Object(Object::Instance(instance.clone()));
+        self.stack[slot_base] = receiver.clone();
+
+        let fields = class.fields.borrow().clone();
+        for (name, initializer) in fields {
+            let value = self.call_value(
+                Value::Object(Object::BoundMethod(Rc::new(BoundMethod {
+                    receiver: receiver.clone(),
+                    method: initializer,
+                }))),
+                vec![],
+            )?;
+            instance.fields.borrow_mut().insert(name, value);
+        }
+
+        let initializer = class.methods.borrow().get("init").cloned();
+        match initializer {
+            Some(initializer) => self.call_closure(initializer, slot_base, argument_count),
+            None if argument_count != 0 => Err(RuntimeError::WrongArity(class.name.clone(), 0.to_string(), argument_count, self.current_line).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Calls a value from Rust and runs it to completion, for natives and for the VM itself when
+    /// it needs a result right away. Errors that the callee doesn't catch are returned.
+    ///
+    /// Calls can be nested, the callee may call a native that calls back into Lox in turn. An
+    /// embedder can also call the functions of a script once it has run:
+    ///
+    /// ```
+    /// use lox::value::Value;
+    /// use lox::vm::VirtualMachine;
+    ///
+    /// let mut vm = VirtualMachine::initialize();
+    /// lox::interpret("fun add(a, b) { return a + b; }", false, &mut vm).unwrap();
+    ///
+    /// let add = vm.get_global("add").unwrap();
+    /// let sum = vm.call_value(add, vec![Value::Int(1), Value::Int(2)]).unwrap();
+    /// assert_eq!(sum, Value::Int(3));
+    /// ```
+    pub fn call_value(&mut self, callee: Value, arguments: Vec<Value>) -> Result<Value> {
+        let argument_count = arguments.len();
+        let slot_base = self.stack.len();
+        let depth = self.frames.len();
+        let line = self.current_line;
+
+        self.stack.push(callee);
+        self.stack.extend(arguments);
+
+        let result = self.call_from_stack(argument_count).and_then(|()| self.run_frames(depth));
+        self.current_line = line;
+
+        if result.is_err() {
+            self.close_upvalues(slot_base);
+            self.truncate_frames(depth);
+        }
+
+        let value = self.stack.pop().unwrap_or_default();
+        self.stack.truncate(slot_base);
+
+        result.map(|()| value)
+    }
+
+    // NOTE: Enums.
+
+    /// Variants are created with a value for each of their fields.
+    fn create_variant(&self, enumeration: Rc<Enum>, name: String, values: Vec<Value>) -> Result<Value> {
+        let Some((index, declaration)) = enumeration.variant(&name) else {
+            return Err(RuntimeError::UndefinedProperty(name, self.current_line).into());
+        };
+
+        if declaration.fields.len() != values.len() {
+            let name = format!("{}.{name}", enumeration.name);
+            let expected = declaration.fields.len().to_string();
+            return Err(RuntimeError::WrongArity(name, expected, values.len(), self.current_line).into());
+        }
+
+        let variant = Variant { enumeration, index, values };
+
+        Ok(Value::Object(Object::Variant(Rc::new(variant))))
+    }
+
+    /// Tests a match subject against a variant pattern, the enum is on top of the subject. A
+    /// pattern with the wrong number of fields is an error, since it could never match.
+    fn is_variant(&mut self, name: String, field_count: usize) -> Result<()> {
+        let (Some(Value::Object(Object::Enum(enumeration))), Some(subject)) = (self.stack.pop(), self.stack.pop()) else {
+            return Err(RuntimeError::ExpectedEnum(self.current_line).into());
+        };
+
+        let Some((index, declaration)) = enumeration.variant(&name) else {
+            return Err(RuntimeError::UndefinedProperty(name, self.current_line).into());
+        };
+
+        if declaration.fields.len() != field_count {
+            let name = format!("{}.{name}", enumeration.name);
+            let expected = declaration.fields.len().to_string();
+            return Err(RuntimeError::WrongArity(name, expected, field_count, self.current_line).into());
+        }
+
+        let matches = match subject {
+            Value::Object(Object::Variant(variant)) => Rc::ptr_eq(&variant.enumeration, &enumeration) && variant.index == index,
+            _ => false,
+        };
+
+        self.stack.push(matches.into());
+        Ok(())
+    }
+
+    /// The subject of a match expression and the bindings of its arms are locals, starting at the
+    /// given slot. The expression around the match may have left temporaries on the stack, like
+    /// the left operand of `a + match (x) { ... }`, which would sit where the subject goes. They
+    /// are set aside until the match ends, so the compiler knows the slots of the locals.
+    fn begin_match(&mut self, slot: usize) {
+        let subject = self.stack.pop().unwrap_or_default();
+        let slot_base = self.frame().slot_base;
+        let height = self.stack.len();
+
+        let values = match height > slot_base + slot {
+            true => self.stack.split_off(slot_base + slot),
+            false => {
+                // NOTE: A variable being declared, like `var x = match ...`, has no value yet.
+                self.stack.resize(slot_base + slot, Value::Nil);
+                vec![]
+            }
+        };
+
+        self.stack.push(subject);
+        let saved = SavedTemporaries {
+            height: height - slot_base,
+            values,
+        };
+        self.frame_mut().matches.push(saved);
+    }
+
+    /// Discards the locals of the match and puts the temporaries back below its result.
+    fn end_match(&mut self, slot: usize) {
+        let result = self.stack.pop().unwrap_or_default();
+        let slot_base = self.frame().slot_base;
+        let Some(saved) = self.frame_mut().matches.pop() else {
+            return;
+        };
+
+        self.close_upvalues(slot_base + slot);
+        self.stack.truncate((slot_base + saved.height).min(slot_base + slot));
+        self.stack.extend(saved.values);
+        self.stack.push(result);
+    }
+
+    // NOTE: Collections.
+
+    fn build_list(&mut self, count: usize) {
+        let elements = self.stack.split_off(self.stack.len().saturating_sub(count));
+        self.stack.push(elements.into());
+    }
+
+    fn build_map(&mut self, count: usize) -> Result<()> {
+        let entries = self.stack.split_off(self.stack.len().saturating_sub(count * 2));
+        let mut map = Map::new();
+
+        for pair in entries.chunks_exact(2) {
+            let key = self.verify_hashable(pair[0].clone())?;
+            map.insert(key, pair[1].clone());
+        }
+
+        self.stack.push(map.into());
+        Ok(())
+    }
+
+    fn get_index(&mut self) -> Result<()> {
+        if self.call_operator_method("__index__", 1)? {
+            return Ok(());
+        }
+
+        let (Some(index), Some(collection)) = (self.stack.pop(), self.stack.pop()) else {
+            return Err(RuntimeError::ExpectedValue(self.current_line).into());
+        };
+
+        let value = match collection {
+            Value::Object(Object::List(list)) => {
+                let list = list.borrow();
+                let index = self.verify_list_index(&index, list.len())?;
+                list[index].clone()
+            }
+            Value::Object(Object::Map(map)) => match map.borrow().get(&index) {
+                Some(value) => value.clone(),
+                None => return Err(RuntimeError::UndefinedKey(index.repr(), self.current_line).into()),
+            },
+            Value::Object(Object::Str(string)) => stdlib::string::char_at(self, &string, &index)?,
+            other => return Err(RuntimeError::ExpectedIndexable(other.type_name(), self.current_line).into()),
+        };
+
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Instances are assigned with `__setindex__(index, value)`, the assignment still evaluates
+    /// to the assigned value.
+    fn set_index(&mut self) -> Result<()> {
+        let collection_slot = self.stack.len().saturating_sub(3);
+        if let Some(Value::Object(Object::Instance(instance))) = self.stack.get(collection_slot) {
+            let method = instance.class.methods.borrow().get("__setindex__").cloned();
+
+            if let Some(method) = method {
+                let mut arguments = self.stack.split_off(collection_slot);
+                let receiver = arguments.remove(0);
+                let value = arguments[1].clone();
+
+                self.call_value(Value::Object(Object::BoundMethod(Rc::new(BoundMethod { receiver, method }))), arguments)?;
+                self.stack.push(value);
+                return Ok(());
+            }
+        }
+
+        let (Some(value), Some(index), Some(collection)) = (self.stack.pop(), self.stack.pop(), self.stack.pop()) else {
+            return Err(RuntimeError::ExpectedValue(self.current_line).into());
+        };
+
+        match collection {
+            Value::Object(Object::List(list)) => {
+                let index = self.verify_list_index(&index, list.borrow().len())?;
+                list.borrow_mut()[index] = value.clone();
+            }
+            Value::Object(Object::Map(map)) => {
+                let key = self.verify_hashable(index)?;
+                map.borrow_mut().insert(key, value.clone());
+            }
+            other => return Err(RuntimeError::ExpectedIndexable(other.type_name(), self.current_line).into()),
+        }
+
+        // NOTE: Assignment is an expression, so the value stays on the stack.
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn verify_hashable(&self, key: Value) -> Result<Value> {
+        if !key.is_hashable() {
+            return Err(RuntimeError::UnhashableKey(key.repr(), self.current_line).into());
+        }
+
+        Ok(key)
+    }
+
+    fn verify_list_index(&self, index: &Value, length: usize) -> Result<usize> {
+        let Some(index) = index.as_index() else {
+            return Err(RuntimeError::ExpectedIndex(self.current_line).into());
+        };
+
+        if index >= length {
+            return Err(RuntimeError::IndexOutOfBounds(index, length, self.current_line).into());
+        }
+
+        Ok(index)
+    }
+
+    // NOTE: Calls.
+
+    /// The callee sits below the arguments on the stack. A native replaces both with its result
+    /// right away, a closure gets a new frame whose first slots are the callee and the arguments.
+    fn call_from_stack(&mut self, argument_count: usize) -> Result<()> {
+        let callee_slot = self.stack.len().saturating_sub(argument_count + 1);
+
+        match self.stack.get(callee_slot).cloned() {
+            Some(Value::Object(Object::Closure(closure))) => self.call_closure(closure, callee_slot, argument_count),
+            Some(Value::Object(Object::Class(class))) => self.instantiate(class, callee_slot, argument_count),
+            Some(Value::Object(Object::BoundMethod(bound))) => {
+                self.stack[callee_slot] = bound.receiver.clone();
+                self.call_closure(bound.method.clone(), callee_slot, argument_count)
+            }
+            Some(Value::Object(Object::Native(native))) => {
+                if !native.accepts(argument_count) {
+                    let expected = native.expected_arguments();
+                    return Err(RuntimeError::WrongArity(native.name.clone(), expected, argument_count, self.current_line).into());
+                }
+
+                let arguments = self.stack.split_off(callee_slot + 1);
+                self.stack.pop();
+
+                let result = (native.function)(self, arguments)?;
+                self.stack.push(result);
+
+                Ok(())
+            }
+            other => Err(RuntimeError::ExpectedCallable(other.unwrap_or_default().type_name(), self.current_line).into()),
+        }
+    }
+
+    /// Missing optional arguments are filled with nil, the function replaces them with their
+    /// default values. Extra arguments are collected into the list of the rest parameter.
+    fn call_closure(&mut self, closure: Rc<Closure>, slot_base: usize, argument_count: usize) -> Result<()> {
+        let function = &closure.function;
+
+        if !function.accepts(argument_count) {
+            let name = function.name.clone().unwrap_or_default();
+            return Err(RuntimeError::WrongArity(name, function.expected_arguments(), argument_count, self.current_line).into());
+        }
+
+        if self.frames.len() >= FRAMES_MAX {
+            return Err(RuntimeError::StackOverflow(self.current_line).into());
+        }
+
+        self.prepare_arguments(function, slot_base);
+
+        if function.is_generator {
+            let coroutine = self.create_coroutine(closure, slot_base, argument_count);
+            self.stack.push(coroutine);
+            return Ok(());
+        }
+
+        self.frames.push(CallFrame::new(closure, slot_base, argument_count));
+        Ok(())
+    }
+
+    fn prepare_arguments(&mut self, function: &Function, slot_base: usize) {
+        let parameters_end = slot_base + 1 + usize::from(function.arity);
+        self.stack.resize(self.stack.len().max(parameters_end), Value::Nil);
+
+        if function.has_rest {
+            let rest = self.stack.split_off(parameters_end);
+            self.stack.push(rest.into());
+        }
+    }
+
+    /// Replaces the lists on top of the stack with their items, the arguments of a call, and
+    /// returns their number. Unlike the arguments written in the call, it isn't limited to 255.
+    fn spread_arguments(&mut self, list_count: u8) -> Result<usize> {
+        let lists = self.stack.split_off(self.stack.len().saturating_sub(list_count.into()));
+        let mut arguments = vec![];
+
+        for list in lists {
+            let Value::Object(Object::List(list)) = list else {
+                return Err(RuntimeError::ExpectedSpreadList(self.current_line).into());
+            };
+
+            arguments.extend(list.borrow().iter().cloned());
+        }
+
+        let argument_count = arguments.len();
+        self.stack.extend(arguments);
+        Ok(argument_count)
+    }
+
+    /// The result replaces the slots of the returning frame, starting with the callee. A
+    /// coroutine that returns is done, and a loop that resumed it ends.
+    fn return_from_frame(&mut self) {
+        let result = self.stack.pop().unwrap_or_default();
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+
+        self.close_upvalues(frame.slot_base);
+        self.stack.truncate(frame.slot_base);
+
+        if let Some(coroutine) = frame.coroutine {
+            coroutine.borrow_mut().state = CoroutineState::Done;
+
+            if let Resumer::ForIter(offset) = frame.resumer {
+                self.frame_mut().ip += offset;
+                return;
+            }
+        }
+
+        self.stack.push(result);
+    }
+
+    // NOTE: Coroutines.
+
+    /// Moves the slots of a call, the callee and its arguments, into a new coroutine that runs
+    /// the call when it is resumed.
+    fn create_coroutine(&mut self, closure: Rc<Closure>, slot_base: usize, argument_count: usize) -> Value {
+        let name = closure.function.name.clone().unwrap_or_default();
+        let stack = self.stack.split_off(slot_base);
+        let frame = CallFrame::new(closure, 0, argument_count);
+
+        Value::Object(Object::Coroutine(Rc::new(RefCell::new(Coroutine::new(name, frame, stack)))))
+    }
+
+    /// Creates a coroutine that calls the given function without arguments, for `Fiber(function)`.
+    pub(crate) fn create_fiber(&mut self, function: Value) -> Result<Value> {
+        let Value::Object(Object::Closure(closure)) = &function else {
+            return Err(RuntimeError::InvalidArgument("Fiber".to_string(), "a function".to_string(), self.current_line).into());
+        };
+
+        let closure = closure.clone();
+        if !closure.function.accepts(0) {
+            let name = closure.function.name.clone().unwrap_or_default();
+            return Err(RuntimeError::WrongArity(name, closure.function.expected_arguments(), 0, self.current_line).into());
+        }
+
+        let slot_base = self.stack.len();
+        self.stack.push(function);
+        self.prepare_arguments(&closure.function, slot_base);
+
+        Ok(self.create_coroutine(closure, slot_base, 0))
+    }
+
+    /// Moves the saved slots of the coroutine back on top of the stack and continues its frame.
+    /// The value becomes the result of the yield that suspended it.
+    fn resume_coroutine(&mut self, coroutine: Rc<RefCell<Coroutine>>, value: Value, resumer: Resumer) -> Result<()> {
+        let mut suspended = coroutine.borrow_mut();
+
+        match suspended.state {
+            CoroutineState::Running => return Err(RuntimeError::CoroutineRunning(self.current_line).into()),
+            CoroutineState::Done => {
+                match resumer {
+                    Resumer::Call => self.stack.push(Value::Nil),
+                    Resumer::ForIter(offset) => self.frame_mut().ip += offset,
+                }
+
+                return Ok(());
+            }
+            CoroutineState::Created | CoroutineState::Suspended => (),
+        }
+
+        if self.frames.len() >= FRAMES_MAX {
+            return Err(RuntimeError::StackOverflow(self.current_line).into());
+        }
+
+        let Some(mut frame) = suspended.frame.take() else {
+            return Err(RuntimeError::CoroutineRunning(self.current_line).into());
+        };
+
+        let slot_base = self.stack.len();
+        frame.relocate(slot_base);
+        frame.coroutine = Some(coroutine.clone());
+        frame.resumer = resumer;
+
+        for upvalue in suspended.upvalues.drain(..) {
+            let Upvalue::Suspended(_, slot) = *upvalue.borrow() else { continue };
+            *upvalue.borrow_mut() = Upvalue::Open(slot_base + slot);
+            self.open_upvalues.push(upvalue);
+        }
+
+        self.stack.append(&mut suspended.stack);
+        if suspended.state == CoroutineState::Suspended {
+            self.stack.push(value);
+        }
+
+        suspended.state = CoroutineState::Running;
+        self.frames.push(frame);
+
+        Ok(())
+    }
+
+    /// Saves the frame of the running coroutine and its stack slots, and hands the value to the
+    /// code that resumed it. The upvalues pointing to the slots are kept open, they now point into
+    /// the saved stack until the coroutine is resumed.
+    fn yield_value(&mut self) -> Result<()> {
+        let Some(coroutine) = self.frames.last().and_then(|frame| frame.coroutine.clone()) else {
+            return Err(RuntimeError::YieldOutsideCoroutine(self.current_line).into());
+        };
+
+        let value = self.stack.pop().unwrap_or_default();
+        let mut frame = self.frames.pop().expect("the coroutine has a frame");
+        frame.coroutine = None;
+
+        let slot_base = frame.slot_base;
+        let mut upvalues = vec![];
+        self.open_upvalues.retain(|upvalue| {
+            let slot = match *upvalue.borrow() {
+                Upvalue::Open(slot) if slot >= slot_base => slot,
+                _ => return true,
+            };
+
+            *upvalue.borrow_mut() = Upvalue::Suspended(Rc::downgrade(&coroutine), slot - slot_base);
+            upvalues.push(upvalue.clone());
+            false
+        });
+
+        let stack = self.stack.split_off(slot_base);
+        frame.relocate(0);
+
+        let mut suspended = coroutine.borrow_mut();
+        suspended.state = CoroutineState::Suspended;
+        suspended.frame = Some(frame);
+        suspended.stack = stack;
+        suspended.upvalues = upvalues;
+
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Registers a function implemented in Rust as a global variable of every module.
+    pub fn define_native(&mut self, name: &str, arity: u8, function: NativeFn) {
+        let native = NativeFunction::new(name, arity, function);
+        self.builtins.insert(name.to_string(), Value::Object(Object::Native(Rc::new(native))));
+    }
+
+    /// Defines a native that takes `arity` or more arguments.
+    pub fn define_variadic_native(&mut self, name: &str, arity: u8, function: NativeFn) {
+        let native = NativeFunction::variadic(name, arity, function);
+        self.builtins.insert(name.to_string(), Value::Object(Object::Native(Rc::new(native))));
+    }
+
+    /// Sets the `args` list of the scripts, the command line arguments after the script path.
+    pub fn set_arguments(&mut self, arguments: Vec<String>) {
+        let arguments: Vec<Value> = arguments.into_iter().map(Value::from).collect();
+        self.builtins.insert("args".to_string(), arguments.into());
+    }
+
+    /// Defines a global holding a module built in Rust, like `math`.
+    pub fn define_native_module(&mut self, module: Module) {
+        let name = module.name();
+        self.builtins.insert(name, Value::Object(Object::Module(Rc::new(module))));
+    }
+
+    /// Grants the scripts access to the filesystem through the `io` module.
+    pub fn set_file_policy(&mut self, policy: FilePolicy) {
+        self.file_policy = policy;
+    }
+
+    pub fn file_policy(&self) -> &FilePolicy {
+        &self.file_policy
+    }
+
+    /// Grants the scripts access to environment variables through the `env` module.
+    pub fn set_env_policy(&mut self, policy: EnvPolicy) {
+        self.env_policy = policy;
+    }
+
+    pub fn env_policy(&self) -> &EnvPolicy {
+        &self.env_policy
+    }
+
+    /// Time since the VM was created, from a monotonic clock.
+    pub(crate) fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Line of the instruction being executed, used by natives to report errors.
+    pub fn current_line(&self) -> Line {
+        self.current_line
+    }
+
+    // NOTE: Exceptions.
+
+    fn push_handler(&mut self, offset: usize, slots: usize) {
+        let frame = self.frame_mut();
+        let handler = Handler {
+            target: frame.ip + offset,
+            stack_height: frame.slot_base + slots,
+            match_depth: frame.matches.len(),
+        };
+
+        frame.handlers.push(handler);
+    }
+
+    /// Throwing is reported as an error, so it unwinds like the errors raised by the VM. The value
+    /// is kept aside for the handler that catches it, see catch_error().
+    fn throw_value(&mut self, value: Value) -> Result<()> {
+        let error = RuntimeError::UncaughtException(value.to_string(), self.current_line);
+        self.thrown = Some(value);
+
+        Err(error.into())
+    }
+
+    /// Unwinds to the innermost handler among the frames above `depth`: the frames above the
+    /// handler are discarded, the stack is restored to the height it had when the handler was
+    /// installed and the exception is pushed on top. If no handler catches it, the error is
+    /// returned.
+    ///
+    /// Thrown values are received as they are. Errors raised by the VM or by natives become
+    /// exception values, with the call stack at the point of the error. Calls to `exit()` are
+    /// never caught.
+    fn catch_error(&mut self, error: anyhow::Error, depth: usize) -> Result<()> {
+        if let Some(RuntimeError::Exit(_)) = error.downcast_ref() {
+            return Err(error);
+        }
+
+        let thrown = self.thrown.take();
+
+        let Some(handler_frame) = self.frames[depth..].iter().rposition(|frame| !frame.handlers.is_empty()) else {
+            // NOTE: The value is kept for the handlers of an enclosing run, see call_value().
+            self.thrown = thrown;
+            return Err(error);
+        };
+
+        let value = thrown.unwrap_or_else(|| {
+            let exception = Exception {
+                message: error.to_string(),
+                line: self.current_line,
+                trace: self.stack_trace(),
+            };
+
+            Value::Object(Object::Exception(Rc::new(exception)))
+        });
+
+        self.truncate_frames(depth + handler_frame + 1);
+        let frame = self.frame_mut();
+        let Some(handler) = frame.handlers.pop() else {
+            return Err(error);
+        };
+
+        frame.ip = handler.target;
+        frame.matches.truncate(handler.match_depth);
+        self.close_upvalues(handler.stack_height);
+        self.stack.truncate(handler.stack_height);
+        self.stack.push(value);
+
+        Ok(())
+    }
+
+    /// One line per frame, from the innermost call to the main script.
+    fn stack_trace(&self) -> Vec<String> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| match &frame.closure.function.name {
+                Some(name) => format!("[line {}] in {name}()", frame.line()),
+                None => format!("[line {}] in script", frame.line()),
+            })
+            .collect()
+    }
+
+    /// The finally block runs with two hidden locals on top of the stack: the pending exception
+    /// and a flag telling whether it has to be thrown again once the block is done.
+    fn end_finally(&mut self) -> Result<()> {
+        let (flag, value) = (self.stack.pop(), self.stack.pop());
+
+        if let Some(Value::Bool(true)) = flag {
+            self.throw_value(value.unwrap_or_default())?;
+        }
+
+        Ok(())
+    }
+
+    // NOTE: Iteration.
+
+    fn get_iterator(&mut self) -> Result<()> {
+        let iterator = match self.stack.pop() {
+            Some(Value::Object(Object::List(list))) => NativeIterator::List(list, 0),
+            Some(Value::Object(Object::Map(map))) => NativeIterator::Map(map, 0),
+            Some(Value::Object(Object::Set(set))) => NativeIterator::Set(set, 0),
+            Some(Value::Object(Object::Str(string))) => NativeIterator::Str(string.chars().collect(), 0),
+            Some(value @ (Value::Object(Object::Coroutine(_)) | Value::Object(Object::Iterator(_)))) => {
+                self.stack.push(value);
+                return Ok(());
+            }
+            Some(Value::Object(Object::Instance(instance))) if instance.class.methods.borrow().contains_key("iter") => {
+                // NOTE: iter() returns an instance with a next() method, or any other iterable.
+                match self.call_instance_method(instance, "iter")? {
+                    iterator @ Value::Object(Object::Instance(_)) => self.stack.push(iterator),
+                    iterable => {
+                        self.stack.push(iterable);
+                        return self.get_iterator();
+                    }
+                }
+
+                return Ok(());
+            }
+            other => return Err(RuntimeError::ExpectedIterable(other.unwrap_or_default().type_name(), self.current_line).into()),
+        };
+
+        self.stack.push(Value::Object(Object::Iterator(Rc::new(RefCell::new(iterator)))));
+        Ok(())
+    }
+
+    /// Advances the iterator stored in the given local slot. The next element is pushed, or the
+    /// loop is exited by jumping the given offset once the iterator is exhausted. A coroutine is
+    /// resumed, and it pushes or jumps itself when it yields or returns. An instance is asked for
+    /// its `next()` element, it returns `StopIteration` when there are no more.
+    fn iterate(&mut self, slot: usize, offset: usize) -> Result<()> {
+        let next = match self.stack.get(self.frame().slot_base + slot).cloned() {
+            Some(Value::Object(Object::Iterator(iterator))) => {
+                let next = iterator.borrow_mut().next_value();
+                next.map_err(|error| RuntimeError::FileError(format!("Could not read line: {error}"), self.current_line))?
+            }
+            Some(Value::Object(Object::Coroutine(coroutine))) => return self.resume_coroutine(coroutine, Value::Nil, Resumer::ForIter(offset)),
+            Some(Value::Object(Object::Instance(instance))) => {
+                let next = self.call_instance_method(instance, "next")?;
+                let stop = Value::Object(Object::Class(self.stop_iteration.clone()));
+                (next != stop).then_some(next)
+            }
+            _ => None,
+        };
+
+        match next {
+            Some(value) => self.stack.push(value),
+            None => self.frame_mut().ip += offset,
+        }
+
+        Ok(())
+    }
+
+    // NOTE: Methods.
+
+    /// Calls a method of an instance without arguments and runs it to completion, for the
+    /// protocols the VM drives itself.
+    fn call_instance_method(&mut self, instance: Rc<Instance>, name: &str) -> Result<Value> {
+        let method = instance.class.methods.borrow().get(name).cloned();
+        let Some(method) = method else {
+            return Err(RuntimeError::UndefinedMethod(name.to_string(), self.current_line).into());
+        };
+
+        let receiver = Value::Object(Object::Instance(instance));
+        let method = Value::Object(Object::BoundMethod(Rc::new(BoundMethod { receiver, method })));
+        self.call_value(method, vec![])
+    }
+
+    /// Calls a method. The receiver sits below the arguments on the stack, both are replaced by
+    /// the result. Methods of instances and classes run in a new frame whose first slot is the
+    /// receiver, the methods of built-in objects are run right away.
+    fn invoke_method(&mut self, name: String, argument_count: usize) -> Result<()> {
+        let receiver_slot = self.stack.len().saturating_sub(argument_count + 1);
+
+        match self.stack.get(receiver_slot).cloned() {
+            Some(Value::Object(Object::Instance(instance))) => return self.invoke_instance_method(instance, name, receiver_slot, argument_count),
+            Some(Value::Object(Object::Enum(enumeration))) => {
+                let values = self.stack.split_off(receiver_slot + 1);
+                let variant = self.create_variant(enumeration, name, values)?;
+                self.stack[receiver_slot] = variant;
+                return Ok(());
+            }
+            Some(Value::Object(Object::Class(class))) => {
+                let Some(method) = class.static_methods.borrow().get(&name).cloned() else {
+                    return Err(RuntimeError::UndefinedMethod(name, self.current_line).into());
+                };
+
+                self.stack[receiver_slot] = Value::Object(Object::Closure(method.clone()));
+                return self.call_closure(method, receiver_slot, argument_count);
+            }
+            _ => (),
+        }
+
+        let arguments = self.stack.split_off(self.stack.len().saturating_sub(argument_count));
+        let Some(receiver) = self.stack.pop() else {
+            return Err(RuntimeError::ExpectedValue(self.current_line).into());
+        };
+
+        let result = match receiver {
+            Value::Object(Object::List(list)) => self.invoke_list_method(&mut list.borrow_mut(), name, arguments)?,
+            Value::Object(Object::Map(map)) => self.invoke_map_method(&mut map.borrow_mut(), name, arguments)?,
+            Value::Object(Object::Str(string)) => stdlib::string::invoke_method(self, &string, name, arguments)?,
+            Value::Object(Object::Set(set)) => stdlib::collections::invoke_set_method(self, &set, name, arguments)?,
+            Value::Object(Object::Regex(regex)) => stdlib::re::invoke_method(self, &regex, name, arguments)?,
+            Value::Object(Object::Exception(exception)) => self.invoke_exception_method(&exception, name, arguments)?,
+            Value::Object(Object::Coroutine(coroutine)) => return self.invoke_coroutine_method(coroutine, name, arguments),
+            Value::Object(Object::Module(module)) => {
+                let function = self.get_export(&module, name)?;
+                let argument_count = arguments.len();
+
+                self.stack.push(function);
+                self.stack.extend(arguments);
+                return self.call_from_stack(argument_count);
+            }
+            _ => return Err(RuntimeError::UndefinedMethod(name, self.current_line).into()),
+        };
+
+        self.stack.push(result);
+        Ok(())
+    }
+
+    /// A field or a getter holding a function is called like a method, so they shadow the methods
+    /// of the class.
+    fn invoke_instance_method(&mut self, instance: Rc<Instance>, name: String, receiver_slot: usize, argument_count: usize) -> Result<()> {
+        let field = instance.fields.borrow().get(&name).cloned();
+        if let Some(field) = field {
+            self.stack[receiver_slot] = field;
+            return self.call_from_stack(argument_count);
+        }
+
+        let getter = instance.class.getters.borrow().get(&name).cloned();
+        if let Some(getter) = getter {
+            let receiver = Value::Object(Object::Instance(instance));
+            let method = Value::Object(Object::BoundMethod(Rc::new(BoundMethod { receiver, method: getter })));
+            self.stack[receiver_slot] = self.call_value(method, vec![])?;
+            return self.call_from_stack(argument_count);
+        }
+
+        let method = instance.class.methods.borrow().get(&name).cloned();
+        match method {
+            Some(method) => self.call_closure(method, receiver_slot, argument_count),
+            None => Err(RuntimeError::UndefinedMethod(name, self.current_line).into()),
+        }
+    }
+
+    /// `pop()` removes the last item and returns it, or nil when the list is empty.
+    fn invoke_list_method(&self, list: &mut Vec<Value>, name: String, arguments: Vec<Value>) -> Result<Value> {
+        let expected_arity = match name.as_str() {
+            "len" | "pop" => 0,
+            "push" => 1,
+            _ => return Err(RuntimeError::UndefinedMethod(name, self.current_line).into()),
+        };
+
+        if arguments.len() != expected_arity {
+            let expected = expected_arity.to_string();
+            return Err(RuntimeError::WrongArity(name, expected, arguments.len(), self.current_line).into());
+        }
+
+        let result = match name.as_str() {
+            "len" => Value::Int(list.len() as i64),
+            "pop" => list.pop().unwrap_or_default(),
+            "push" => {
+                list.extend(arguments);
+                Value::Nil
+            }
+            _ => unreachable!(),
+        };
+
+        Ok(result)
+    }
+
+    fn invoke_map_method(&self, map: &mut Map, name: String, arguments: Vec<Value>) -> Result<Value> {
+        let expected_arity = match name.as_str() {
+            "keys" | "values" | "len" => 0,
+            "has" | "remove" => 1,
+            _ => return Err(RuntimeError::UndefinedMethod(name, self.current_line).into()),
+        };
+
+        if arguments.len() != expected_arity {
+            let expected = expected_arity.to_string();
+            return Err(RuntimeError::WrongArity(name, expected, arguments.len(), self.current_line).into());
+        }
+
+        let result = match name.as_str() {
+            "keys" => map.keys().cloned().collect::<Vec<Value>>().into(),
+            "len" => Value::Int(map.len() as i64),
+            "values" => map.values().cloned().collect::<Vec<Value>>().into(),
+            "has" => map.contains_key(&arguments[0]).into(),
+            "remove" => map.remove(&arguments[0]).unwrap_or_default(),
+            _ => unreachable!(),
+        };
+
+        Ok(result)
+    }
+
+    /// `next()` and `resume(value)` continue the coroutine, its next yielded value replaces the
+    /// receiver. `done()` tells whether it has finished.
+    fn invoke_coroutine_method(&mut self, coroutine: Rc<RefCell<Coroutine>>, name: String, arguments: Vec<Value>) -> Result<()> {
+        let expected = match name.as_str() {
+            "next" | "done" => "0",
+            "resume" => "0 to 1",
+            _ => return Err(RuntimeError::UndefinedMethod(name, self.current_line).into()),
+        };
+
+        if arguments.len() > usize::from(name == "resume") {
+            return Err(RuntimeError::WrongArity(name, expected.to_string(), arguments.len(), self.current_line).into());
+        }
+
+        match name.as_str() {
+            "done" => {
+                let done = coroutine.borrow().state == CoroutineState::Done;
+                self.stack.push(done.into());
+                Ok(())
+            }
+            _ => {
+                let value = arguments.into_iter().next().unwrap_or_default();
+                self.resume_coroutine(coroutine, value, Resumer::Call)
+            }
+        }
+    }
+
+    fn invoke_exception_method(&self, exception: &Exception, name: String, arguments: Vec<Value>) -> Result<Value> {
+        let result = match name.as_str() {
+            "message" => exception.message.clone().into(),
+            "line" => Value::Int(exception.line.into()),
+            "trace" => exception.trace.iter().cloned().map(Value::from).collect::<Vec<Value>>().into(),
+            _ => return Err(RuntimeError::UndefinedMethod(name, self.current_line).into()),
+        };
+
+        if !arguments.is_empty() {
+            return Err(RuntimeError::WrongArity(name, 0.to_string(), arguments.len(), self.current_line).into());
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the current value of a global variable of the main script, if it has been defined.
+    pub fn get_global(&self, identifier: &str) -> Option<Value> {
+        let value = self.module.globals.borrow().get(identifier).cloned();
+        value.or_else(|| self.builtins.get(identifier).cloned())
+    }
 }
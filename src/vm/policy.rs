@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Files the `io` module may touch. By default there are no allowed directories, so scripts can't
+/// access the filesystem at all until the embedder grants them a directory:
+///
+/// ```
+/// use lox::vm::policy::FilePolicy;
+///
+/// let policy = FilePolicy::default().allow("/tmp").read_only();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FilePolicy {
+    roots: Vec<PathBuf>,
+    read_only: bool,
+}
+
+impl FilePolicy {
+    /// Allows the files inside the given directory and its subdirectories.
+    pub fn allow(mut self, root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        self.roots.push(root.canonicalize().unwrap_or(root.to_path_buf()));
+        self
+    }
+
+    /// Files can be read but not created or modified.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Resolved path of a file a script wants to read, or None if it is outside the allowed
+    /// directories.
+    pub fn check_read(&self, path: &str) -> Option<PathBuf> {
+        let resolved = resolve(path)?;
+        self.roots.iter().any(|root| resolved.starts_with(root)).then_some(resolved)
+    }
+
+    /// Like check_read(), but also None in read-only mode.
+    pub fn check_write(&self, path: &str) -> Option<PathBuf> {
+        if self.read_only {
+            return None;
+        }
+
+        self.check_read(path)
+    }
+}
+
+/// Absolute path without `..` or symbolic links, so it can't escape a root by a detour. A file
+/// that doesn't exist yet is resolved through its directory.
+fn resolve(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    if let Ok(resolved) = path.canonicalize() {
+        return Some(resolved);
+    }
+
+    // NOTE: A dangling symbolic link can't be canonicalized, but writing to it would create the
+    // file it points to, wherever that is.
+    if path.symlink_metadata().is_ok() {
+        return None;
+    }
+
+    let name = path.file_name()?;
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize().ok()?,
+        _ => std::env::current_dir().ok()?,
+    };
+
+    Some(directory.join(name))
+}
+
+/// Environment variables the `env` module may read. Like files, none can be read by default, the
+/// embedder grants them by name or all at once, as the `lox` command line does:
+///
+/// ```
+/// use lox::vm::policy::EnvPolicy;
+///
+/// let policy = EnvPolicy::default().allow("HOME").allow("LANG");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EnvPolicy {
+    names: HashSet<String>,
+    all: bool,
+}
+
+impl EnvPolicy {
+    /// Allows the variable with the given name.
+    pub fn allow(mut self, name: impl Into<String>) -> Self {
+        self.names.insert(name.into());
+        self
+    }
+
+    /// Allows every variable.
+    pub fn allow_all(mut self) -> Self {
+        self.all = true;
+        self
+    }
+
+    pub fn check(&self, name: &str) -> bool {
+        self.all || self.names.contains(name)
+    }
+}
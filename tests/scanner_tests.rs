@@ -123,3 +123,51 @@ fn check_whitespace_are_ignored() {
     let mut s = Scanner::new("// This is a comment. \n\t");
     assert_eq!(s.next(), None);
 } */
+
+use lox::scanner::token::TokenKind;
+use lox::scanner::Scanner;
+
+#[test]
+fn check_doc_comments_attach_to_next_token() {
+    let mut s = Scanner::new("/// Adds two numbers.\n///\n/// Returns the sum.\nfun add // not a doc\n");
+    let token = s.next().unwrap();
+    assert_eq!(token.kind, TokenKind::Fun);
+    assert_eq!(token.doc.as_deref(), Some("Adds two numbers.\n\nReturns the sum."));
+    assert_eq!(s.next().unwrap().doc, None);
+}
+
+#[test]
+fn check_trivia_mode_keeps_whitespace_and_comments() {
+    let source = "var a; // note\n/* block /* nested */ */\n/// doc\n";
+    let tokens: Vec<_> = Scanner::with_trivia(source).collect();
+    let kinds: Vec<_> = tokens.iter().map(|token| token.kind).collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            TokenKind::Var,
+            TokenKind::Whitespace,
+            TokenKind::Identifier,
+            TokenKind::Semicolon,
+            TokenKind::Whitespace,
+            TokenKind::LineComment,
+            TokenKind::Whitespace,
+            TokenKind::BlockComment,
+            TokenKind::Whitespace,
+            TokenKind::DocComment,
+            TokenKind::Whitespace,
+            TokenKind::EOF,
+        ]
+    );
+    assert_eq!(tokens.iter().map(|token| token.source.as_str()).collect::<String>(), source);
+}
+
+#[test]
+fn check_shebang_line_is_skipped() {
+    let mut s = Scanner::new("#!/usr/bin/env lox\nprint 1;");
+    let token = s.next().unwrap();
+    assert_eq!((token.kind, token.line), (TokenKind::Print, 2));
+
+    let mut s = Scanner::with_trivia("#!/usr/bin/env lox\n");
+    assert_eq!(s.next().unwrap().kind, TokenKind::LineComment);
+}
@@ -62,3 +62,1223 @@ fn verify_vm_stack_error() {
     chunk.write(OperationCode::Add, 123);
     assert_eq!(InterpretResult::Ok, VirtualMachine::interpret(chunk));
 } */
+
+use lox::error::CompileError;
+use lox::error::RuntimeError;
+use lox::value::Value;
+use lox::vm::policy::EnvPolicy;
+use lox::vm::policy::FilePolicy;
+use lox::vm::VirtualMachine;
+
+fn run(source: &str) -> VirtualMachine {
+    let mut vm = VirtualMachine::initialize();
+    lox::interpret(source, false, &mut vm).unwrap();
+    vm
+}
+
+fn run_error(source: &str) -> anyhow::Error {
+    let mut vm = VirtualMachine::initialize();
+    lox::interpret(source, false, &mut vm).unwrap_err()
+}
+
+#[test]
+fn verify_map_literal_access_and_assignment() {
+    let vm = run(r#"var m = {"a": 1, 2: "two", nil: true}; m["b"] = m["a"] + 1; var b = m["b"]; var two = m[2];"#);
+
+    assert_eq!(vm.get_global("b"), Some(Value::from(2.0)));
+    assert_eq!(vm.get_global("two"), Some(Value::from("two".to_string())));
+    assert_eq!(vm.get_global("m").unwrap().to_string(), r#"{"a": 1, 2: "two", Nil: true, "b": 2}"#);
+}
+
+#[test]
+fn verify_map_methods() {
+    let vm = run(r#"var m = {"a": 1, "b": 2}; var removed = m.remove("a"); var has = m.has("a"); var keys = m.keys(); var values = m.values();"#);
+
+    assert_eq!(vm.get_global("removed"), Some(Value::from(1.0)));
+    assert_eq!(vm.get_global("has"), Some(Value::from(false)));
+    assert_eq!(vm.get_global("keys").unwrap().to_string(), r#"["b"]"#);
+    assert_eq!(vm.get_global("values").unwrap().to_string(), "[2]");
+}
+
+#[test]
+fn verify_brace_opens_block_in_statement_position() {
+    let vm = run(r#"var a = 1; { var a = {"x": 2}; print a; } var b = {};"#);
+
+    assert_eq!(vm.get_global("a"), Some(Value::from(1.0)));
+    assert_eq!(vm.get_global("b").unwrap().to_string(), "{}");
+}
+
+#[test]
+fn verify_map_keys_hash_by_value_and_objects_by_identity() {
+    let vm = run(r#"var l = [1]; var m = {0: "zero", l: "list"}; var zero = m[-0]; var same = m[l]; var other = m.has([1]);"#);
+
+    assert_eq!(vm.get_global("zero"), Some(Value::from("zero".to_string())));
+    assert_eq!(vm.get_global("same"), Some(Value::from("list".to_string())));
+    assert_eq!(vm.get_global("other"), Some(Value::from(false)));
+}
+
+#[test]
+fn verify_map_errors() {
+    let error = run_error(r#"var m = {}; m["missing"];"#);
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::UndefinedKey(r#""missing""#.to_string(), 1)));
+
+    let error = run_error("var m = {0 / 0: 1};");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::UnhashableKey("NaN".to_string(), 1)));
+}
+
+#[test]
+fn verify_for_in_over_lists_maps_and_strings() {
+    let vm = run(r#"
+        var total = 0;
+        for (n in [1, 2, 3]) total = total + n;
+
+        var keys = "";
+        var sum = 0;
+        var m = {"a": 1, "b": 2};
+        for (k in m) { keys = keys + k; sum = sum + m[k]; }
+
+        var reversed = "";
+        for (c in "héllo") reversed = c + reversed;
+        "#);
+
+    assert_eq!(vm.get_global("total"), Some(Value::from(6.0)));
+    assert_eq!(vm.get_global("keys"), Some(Value::from("ab".to_string())));
+    assert_eq!(vm.get_global("sum"), Some(Value::from(3.0)));
+    assert_eq!(vm.get_global("reversed"), Some(Value::from("olléh".to_string())));
+}
+
+#[test]
+fn verify_nested_for_in_with_locals() {
+    let vm =
+        run("var total = 0; { var factor = 10; for (a in [1, 2]) for (b in [3, 4]) { var product = a * b; total = total + product * factor; } }");
+
+    assert_eq!(vm.get_global("total"), Some(Value::from(210.0)));
+}
+
+#[test]
+fn verify_if_and_while_statements() {
+    let vm = run(r#"
+        var count = 0;
+        var evens = 0;
+        while (count < 5) {
+            if (count == 2 or count == 4) evens = evens + 1; else evens = evens;
+            count = count + 1;
+        }
+        var branch;
+        if (nil) branch = "then"; else branch = "else";
+    "#);
+
+    assert_eq!(vm.get_global("count"), Some(Value::Int(5)));
+    assert_eq!(vm.get_global("evens"), Some(Value::Int(2)));
+    assert_eq!(vm.get_global("branch"), Some(Value::from("else".to_string())));
+}
+
+#[test]
+fn verify_and_or_short_circuit() {
+    let vm = run(r#"
+        var calls = 0;
+        fun touch(value) { calls = calls + 1; return value; }
+        var results = [nil and touch(1), 1 and touch(2), 3 or touch(4), false or touch(5)];
+    "#);
+
+    assert_eq!(vm.get_global("results").unwrap().to_string(), "[Nil, 2, 3, 5]");
+    assert_eq!(vm.get_global("calls"), Some(Value::Int(2)));
+}
+
+#[test]
+fn verify_for_in_over_non_iterable() {
+    let error = run_error("for (x in 5) print x;");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ExpectedIterable("int", 1)));
+}
+
+#[test]
+fn verify_for_in_over_instances() {
+    let vm = run(r#"
+        class Countdown {
+            init(from) { this.from = from; }
+            iter() { return this; }
+            next() {
+                if (this.from == 0) return StopIteration;
+                this.from = this.from - 1;
+                return this.from + 1;
+            }
+        }
+        class Pair {
+            init(a, b) { this.a = a; this.b = b; }
+            iter() { return [this.a, this.b]; }
+        }
+        var counted = [];
+        for (n in Countdown(3)) counted.push(n);
+        var paired = [];
+        for (x in Pair(nil, 2)) paired.push(x);
+    "#);
+
+    assert_eq!(vm.get_global("counted").unwrap().to_string(), "[3, 2, 1]");
+    assert_eq!(vm.get_global("paired").unwrap().to_string(), "[Nil, 2]");
+
+    let error = run_error("class A {} for (x in A()) print x;");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ExpectedIterable("instance", 1)));
+}
+
+#[test]
+fn verify_integer_literals_and_promotion() {
+    let vm = run("var sum = 2 + 3; var mixed = 2 + 0.5; var quotient = 7 / 2; var big = 9007199254740993 + 0;");
+
+    assert!(matches!(vm.get_global("sum"), Some(Value::Int(5))));
+    assert!(matches!(vm.get_global("mixed"), Some(Value::Number(number)) if number == 2.5));
+    assert!(matches!(vm.get_global("quotient"), Some(Value::Number(number)) if number == 3.5));
+    assert!(matches!(vm.get_global("big"), Some(Value::Int(9007199254740993))));
+}
+
+#[test]
+fn verify_equality_across_integers_and_floats() {
+    let vm = run(r#"var same = 1 == 1.0; var precise = 9007199254740993 == 9007199254740992.0; var key = {1: "one"}[1.0];"#);
+
+    assert_eq!(vm.get_global("same"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("precise"), Some(Value::from(false)));
+    assert_eq!(vm.get_global("key"), Some(Value::from("one".to_string())));
+}
+
+#[test]
+fn verify_integer_conversions() {
+    let vm = run(r#"var truncated = int(-3.9); var parsed = int("42"); var promoted = float(2);"#);
+
+    assert!(matches!(vm.get_global("truncated"), Some(Value::Int(-3))));
+    assert!(matches!(vm.get_global("parsed"), Some(Value::Int(42))));
+    assert!(matches!(vm.get_global("promoted"), Some(Value::Number(number)) if number == 2.0));
+
+    let error = run_error(r#"int("abc");"#);
+    assert_eq!(
+        error.downcast_ref(),
+        Some(&RuntimeError::InvalidArgument(
+            "int".to_string(),
+            "a number or a numeric string".to_string(),
+            1
+        ))
+    );
+}
+
+#[test]
+fn verify_integer_overflow() {
+    let error = run_error("var a = 9223372036854775807; a + 1;");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::IntegerOverflow(1)));
+
+    let error = run_error("int(100000000000000000000.0);");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::IntegerOverflow(1)));
+}
+
+#[test]
+fn verify_radix_scientific_and_separated_literals() {
+    let vm = run("var hex = 0xFF; var binary = 0b1010; var octal = 0o17; var million = 1_000_000; var mask = 0xFFFF_FFFF_FFFF_FFFF; var tiny = 1e-9; var big = 2.5E3;");
+
+    assert!(matches!(vm.get_global("hex"), Some(Value::Int(255))));
+    assert!(matches!(vm.get_global("binary"), Some(Value::Int(10))));
+    assert!(matches!(vm.get_global("octal"), Some(Value::Int(15))));
+    assert!(matches!(vm.get_global("million"), Some(Value::Int(1_000_000))));
+    assert!(matches!(vm.get_global("mask"), Some(Value::Int(-1))));
+    assert!(matches!(vm.get_global("tiny"), Some(Value::Number(number)) if number == 1e-9));
+    assert!(matches!(vm.get_global("big"), Some(Value::Number(number)) if number == 2500.0));
+}
+
+#[test]
+fn verify_invalid_numeric_literals() {
+    let mut vm = VirtualMachine::initialize();
+
+    assert!(lox::interpret("var a = 1_;", false, &mut vm).is_err());
+    assert!(lox::interpret("var a = 0x;", false, &mut vm).is_err());
+    assert!(lox::interpret("var a = 0x1_0000_0000_0000_0000;", false, &mut vm).is_err());
+}
+
+#[test]
+fn verify_bitwise_operators() {
+    let vm = run(
+        "var a = 0xF0 | 0x0F; var b = 0xF0 & 0x3C; var c = 5 ^ 3; var d = ~0; var e = 1 << 4; var f = -16 >> 2; var g = 1 + 2 << 1; var h = 4.0 | 1;",
+    );
+
+    assert!(matches!(vm.get_global("a"), Some(Value::Int(0xFF))));
+    assert!(matches!(vm.get_global("b"), Some(Value::Int(0x30))));
+    assert!(matches!(vm.get_global("c"), Some(Value::Int(6))));
+    assert!(matches!(vm.get_global("d"), Some(Value::Int(-1))));
+    assert!(matches!(vm.get_global("e"), Some(Value::Int(16))));
+    assert!(matches!(vm.get_global("f"), Some(Value::Int(-4))));
+    assert!(matches!(vm.get_global("g"), Some(Value::Int(6))));
+    assert!(matches!(vm.get_global("h"), Some(Value::Int(5))));
+}
+
+#[test]
+fn verify_bitwise_operators_reject_non_integers() {
+    let error = run_error("1.5 & 1;");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ExpectedInteger(1)));
+
+    let error = run_error(r#"~"a";"#);
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ExpectedInteger(1)));
+
+    let error = run_error("1 << 64;");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ShiftOutOfRange(64, 1)));
+}
+
+#[test]
+fn verify_throw_and_catch() {
+    let vm = run(r#"var caught; try { throw "boom"; caught = "not thrown"; } catch (e) { caught = e; }"#);
+
+    assert_eq!(vm.get_global("caught"), Some(Value::from("boom".to_string())));
+}
+
+#[test]
+fn verify_runtime_errors_are_catchable() {
+    let vm = run(r#"var message; var line; try { int("abc"); } catch (e) { message = e.message(); line = e.line(); }"#);
+
+    assert_eq!(
+        vm.get_global("message"),
+        Some(Value::from(
+            "Invalid argument for int(): expected a number or a numeric string. [line 1] in script.".to_string()
+        ))
+    );
+    assert_eq!(vm.get_global("line"), Some(Value::from(1)));
+}
+
+#[test]
+fn verify_finally_runs_on_every_path() {
+    let vm = run(r#"
+        var log = "";
+        try { log = log + "a"; } finally { log = log + "b"; }
+        try { throw 1; } catch (e) { log = log + "c"; } finally { log = log + "d"; }
+        try {
+            try { throw "x"; } catch (e) { throw e + "y"; } finally { log = log + "e"; }
+        } catch (e) {
+            log = log + e;
+        }
+        "#);
+
+    assert_eq!(vm.get_global("log"), Some(Value::from("abcdexy".to_string())));
+}
+
+#[test]
+fn verify_unwinding_restores_locals() {
+    let vm = run(
+        "var result; { var a = 1; try { var b = 2; for (x in [3]) { var c = 4; throw a + b + x + c; } } catch (e) { var d = e; result = a + d; } }",
+    );
+
+    assert_eq!(vm.get_global("result"), Some(Value::from(11)));
+}
+
+#[test]
+fn verify_uncaught_exceptions() {
+    let error = run_error(r#"try { throw "inner"; } finally { print "cleanup"; }"#);
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::UncaughtException("inner".to_string(), 1)));
+
+    let error = run_error("try { } catch (e) { } undefined;");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::UndefinedVariable("undefined".to_string(), 1)));
+}
+
+/// Writes the files into a new temporary directory and returns its path.
+fn write_modules(test_name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let directory = std::env::temp_dir().join(format!("lox-{}-{test_name}", std::process::id()));
+
+    for (name, source) in files {
+        let path = directory.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+
+    directory
+}
+
+#[test]
+fn verify_import_reads_exports_relative_to_the_importer() {
+    let directory = write_modules(
+        "imports",
+        &[
+            (
+                "main.lox",
+                r#"import "lib/util.lox" as util; var name = util.name; var deep = util.helper.deep; var keys = util.m.keys();"#,
+            ),
+            (
+                "lib/util.lox",
+                r#"import "helper.lox" as helper_module; export var name = "util"; export var helper = helper_module; export var m = {"k": 1};"#,
+            ),
+            ("lib/helper.lox", r#"export var deep = "deep";"#),
+        ],
+    );
+
+    let mut vm = VirtualMachine::initialize();
+    lox::interpret_file(&directory.join("main.lox"), false, &mut vm).unwrap();
+
+    assert_eq!(vm.get_global("name"), Some(Value::from("util".to_string())));
+    assert_eq!(vm.get_global("deep"), Some(Value::from("deep".to_string())));
+    assert_eq!(vm.get_global("keys").unwrap().to_string(), r#"["k"]"#);
+}
+
+#[test]
+fn verify_modules_are_cached_and_have_their_own_globals() {
+    let directory = write_modules(
+        "cache",
+        &[
+            (
+                "main.lox",
+                r#"var counter = 0; import "counter.lox" as a; import "counter.lox" as b; var same = a == b; var count = a.count;"#,
+            ),
+            ("counter.lox", "var counter = 10; export var count = counter + 1;"),
+        ],
+    );
+
+    let mut vm = VirtualMachine::initialize();
+    lox::interpret_file(&directory.join("main.lox"), false, &mut vm).unwrap();
+
+    assert_eq!(vm.get_global("same"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("count"), Some(Value::from(11)));
+    assert_eq!(vm.get_global("counter"), Some(Value::from(0)));
+}
+
+#[test]
+fn verify_unexported_globals_are_hidden() {
+    let directory = write_modules(
+        "exports",
+        &[
+            ("main.lox", r#"import "hidden.lox" as hidden; hidden.secret;"#),
+            ("hidden.lox", "var secret = 1;"),
+        ],
+    );
+
+    let mut vm = VirtualMachine::initialize();
+    let error = lox::interpret_file(&directory.join("main.lox"), false, &mut vm).unwrap_err();
+
+    assert_eq!(
+        error.downcast_ref(),
+        Some(&RuntimeError::UndefinedExport("hidden.lox".to_string(), "secret".to_string(), 1))
+    );
+}
+
+#[test]
+fn verify_import_cycles_are_reported() {
+    let directory = write_modules(
+        "cycles",
+        &[
+            ("main.lox", r#"import "a.lox" as a;"#),
+            ("a.lox", r#"import "b.lox" as b;"#),
+            ("b.lox", r#"import "main.lox" as main;"#),
+        ],
+    );
+
+    let mut vm = VirtualMachine::initialize();
+    let error = lox::interpret_file(&directory.join("main.lox"), false, &mut vm).unwrap_err();
+
+    let Some(RuntimeError::ImportCycle(cycle, _)) = error.downcast_ref() else {
+        panic!("expected an import cycle, got {error}");
+    };
+
+    let files: Vec<&str> = cycle.split(" -> ").map(|path| path.rsplit('/').next().unwrap()).collect();
+    assert_eq!(files, ["main.lox", "a.lox", "b.lox", "main.lox"]);
+}
+
+#[test]
+fn verify_anonymous_functions_and_arrow_lambdas() {
+    let vm = run(
+        "var add = fun (a, b) { return a + b; }; var square = (x) => x * x; var answer = () => 42; \
+         var sum = add(1, 2); var squared = square(7); var result = answer(); var grouped = (1 + 2) * 3; \
+         var applied = ((f, x) => f(f(x)))((n) => n + 1, 0);",
+    );
+
+    assert_eq!(vm.get_global("sum"), Some(Value::from(3)));
+    assert_eq!(vm.get_global("squared"), Some(Value::from(49)));
+    assert_eq!(vm.get_global("result"), Some(Value::from(42)));
+    assert_eq!(vm.get_global("grouped"), Some(Value::from(9)));
+    assert_eq!(vm.get_global("applied"), Some(Value::from(2)));
+}
+
+#[test]
+fn verify_closures_capture_variables() {
+    let vm = run("fun counter() { var count = 0; return () => { count = count + 1; return count; }; } \
+         var first = counter(); var second = counter(); first(); first(); \
+         var a = first(); var b = second(); \
+         var curried = fun (a) { return fun (b) { return (c) => a + b + c; }; }; var total = curried(1)(2)(3);");
+
+    assert_eq!(vm.get_global("a"), Some(Value::from(3)));
+    assert_eq!(vm.get_global("b"), Some(Value::from(1)));
+    assert_eq!(vm.get_global("total"), Some(Value::from(6)));
+}
+
+#[test]
+fn verify_function_errors_are_reported() {
+    let error = run_error("fun add(a, b) { return a + b; }\nadd(1);");
+    assert_eq!(
+        error.downcast_ref(),
+        Some(&RuntimeError::WrongArity("add".to_string(), "2".to_string(), 1, 2))
+    );
+
+    let error = run_error("fun forever(n) { return forever(n + 1); }\nforever(0);");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::StackOverflow(1)));
+
+    let error = run_error("return 1;");
+    assert_eq!(error.downcast_ref(), Some(&CompileError::Failed));
+}
+
+#[test]
+fn verify_default_and_rest_parameters() {
+    let vm = run("fun f(a, b = a * 2, ...rest) { return [a, b, rest]; } \
+         var defaults = f(1); var passed = f(1, nil); var extra = f(1, 5, 6, 7); \
+         var arrow = ((x, y = [x]) => y)(4);");
+
+    assert_eq!(vm.get_global("defaults").unwrap().to_string(), "[1, 2, []]");
+    assert_eq!(vm.get_global("passed").unwrap().to_string(), "[1, Nil, []]");
+    assert_eq!(vm.get_global("extra").unwrap().to_string(), "[1, 5, [6, 7]]");
+    assert_eq!(vm.get_global("arrow").unwrap().to_string(), "[4]");
+}
+
+#[test]
+fn verify_spread_arguments() {
+    let vm = run("fun list(...items) { return items; } var xs = [2, 3]; \
+         var spread = list(...xs); var mixed = list(1, ...xs, 4, ...[]); var native = int(...[\"7\"]);");
+
+    assert_eq!(vm.get_global("spread").unwrap().to_string(), "[2, 3]");
+    assert_eq!(vm.get_global("mixed").unwrap().to_string(), "[1, 2, 3, 4]");
+    assert_eq!(vm.get_global("native"), Some(Value::from(7)));
+
+    let vm = run(
+        "fun count(first, ...rest) { return 1 + rest.len(); } var counted = count(...range(300)); \
+         var largest = math.max(...range(300));",
+    );
+    assert_eq!(vm.get_global("counted"), Some(Value::from(300)));
+    assert_eq!(vm.get_global("largest"), Some(Value::from(299)));
+
+    let error = run_error("fun f(a) {}\nf(...1);");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ExpectedSpreadList(2)));
+}
+
+#[test]
+fn verify_arity_errors_report_the_accepted_range() {
+    let error = run_error("fun f(a, b = 1) {}\nf(1, 2, 3);");
+    assert_eq!(
+        error.downcast_ref(),
+        Some(&RuntimeError::WrongArity("f".to_string(), "1 to 2".to_string(), 3, 2))
+    );
+
+    let error = run_error("var f = (a, ...rest) => a;\nf();");
+    assert_eq!(
+        error.downcast_ref(),
+        Some(&RuntimeError::WrongArity("anonymous".to_string(), "at least 1".to_string(), 0, 2))
+    );
+
+    let error = run_error("fun f(a = 1, b) {}");
+    assert_eq!(error.downcast_ref(), Some(&CompileError::Failed));
+}
+
+#[test]
+fn verify_generators_yield_lazily() {
+    let vm = run(
+        "var log = []; fun count(items) { for (x in items) { log = [log, x]; yield x * 10; } return \"end\"; } \
+         var g = count([1, 2]); var started = log; var first = g.next(); var second = g.next(); \
+         var finished = g.next(); var done = g.done(); var after = g.next(); \
+         var collected = []; for (v in count([3, 4])) { collected = [collected, v]; }",
+    );
+
+    assert_eq!(vm.get_global("started").unwrap().to_string(), "[]");
+    assert_eq!(vm.get_global("first"), Some(Value::from(10)));
+    assert_eq!(vm.get_global("second"), Some(Value::from(20)));
+    assert_eq!(vm.get_global("finished"), Some(Value::from("end".to_string())));
+    assert_eq!(vm.get_global("done"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("after"), Some(Value::Nil));
+    assert_eq!(vm.get_global("collected").unwrap().to_string(), "[[[], 30], 40]");
+}
+
+#[test]
+fn verify_fibers_receive_resumed_values() {
+    let vm = run("var f = Fiber(fun () { var a = yield 1; var b = yield a + 1; return a + b; }); \
+         var first = f.resume(\"ignored\"); var second = f.resume(10); var last = f.resume(20); var done = f.done(); \
+         fun failing() { yield 1; throw \"boom\"; } var g = failing(); g.next(); \
+         var caught = nil; try { g.next(); } catch (e) { caught = e; } var failed = g.done();");
+
+    assert_eq!(vm.get_global("first"), Some(Value::from(1)));
+    assert_eq!(vm.get_global("second"), Some(Value::from(11)));
+    assert_eq!(vm.get_global("last"), Some(Value::from(30)));
+    assert_eq!(vm.get_global("done"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("caught"), Some(Value::from("boom".to_string())));
+    assert_eq!(vm.get_global("failed"), Some(Value::from(true)));
+}
+
+#[test]
+fn verify_coroutines_share_captured_locals_across_yields() {
+    let vm = run("fun gen() { var x = 0; var inc = () => { x = x + 1; }; yield 1; inc(); yield x; } \
+         var g = gen(); var first = g.next(); var second = g.next(); \
+         fun exposed() { var x = 0; yield () => { x = x + 1; return x; }; yield x; } \
+         var e = exposed(); var bump = e.next(); bump(); var bumped = bump(); var seen = e.next(); \
+         fun dropped() { var y = 5; yield () => y; } var read = dropped().next(); var kept = read();");
+
+    assert_eq!(vm.get_global("first"), Some(Value::from(1)));
+    assert_eq!(vm.get_global("second"), Some(Value::from(1)));
+    assert_eq!(vm.get_global("bumped"), Some(Value::from(2)));
+    assert_eq!(vm.get_global("seen"), Some(Value::from(2)));
+    assert_eq!(vm.get_global("kept"), Some(Value::from(5)));
+}
+
+#[test]
+fn verify_running_coroutines_cant_be_resumed() {
+    let error = run_error("fun recurse() { yield me.next(); }\nvar me = recurse();\nme.next();");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::CoroutineRunning(1)));
+
+    let error = run_error("yield 1;");
+    assert_eq!(error.downcast_ref(), Some(&CompileError::Failed));
+}
+
+#[test]
+fn verify_classes_have_fields_static_methods_and_inheritance() {
+    let vm = run("class Animal { var legs = 4; var sound = \"...\"; init(name) { this.name = name; } \
+         speak() { return this.name + \" says \" + this.sound; } static create(name) { return Animal(name); } } \
+         class Dog < Animal { var sound = \"woof\"; speak() { return super.speak() + \"!\"; } } \
+         var dog = Dog(\"rex\"); var spoken = dog.speak(); var legs = dog.legs; \
+         var created = Animal.create(\"cat\").speak(); var bound = dog.speak; var called = bound();");
+
+    assert_eq!(vm.get_global("spoken"), Some(Value::from("rex says woof!".to_string())));
+    assert_eq!(vm.get_global("legs"), Some(Value::from(4)));
+    assert_eq!(vm.get_global("created"), Some(Value::from("cat says ...".to_string())));
+    assert_eq!(vm.get_global("called"), Some(Value::from("rex says woof!".to_string())));
+}
+
+#[test]
+fn verify_static_methods_have_no_receiver() {
+    let vm = run("class A { var x = 1; get() { fun inner() { return this.x; } return inner(); } } var read = A().get();");
+    assert_eq!(vm.get_global("read"), Some(Value::from(1)));
+
+    for source in [
+        "class A { static f() { return this; } }",
+        "class A { static f() { fun g() { return this; } return g(); } }",
+        "class A {} class B < A { static f() { var g = () => super.f; return g(); } }",
+    ] {
+        let error = run_error(source);
+        assert_eq!(error.downcast_ref(), Some(&CompileError::Failed));
+    }
+}
+
+#[test]
+fn verify_accessors_dispatch_property_reads_and_writes() {
+    let vm = run("class Counter { var #count = 0; get count { return this.#count; } \
+         set count(value) { if (value < 0) throw \"negative\"; this.#count = value; } \
+         increment() { this.#count = this.#count + 1; return this; } get() { return \"method\"; } } \
+         var counter = Counter(); counter.increment().increment(); var read = counter.count; \
+         var assigned = counter.count = 10; var written = counter.count; var method = counter.get(); \
+         var caught = nil; try { counter.count = -1; } catch (e) { caught = e; }");
+
+    assert_eq!(vm.get_global("read"), Some(Value::from(2)));
+    assert_eq!(vm.get_global("assigned"), Some(Value::from(10)));
+    assert_eq!(vm.get_global("written"), Some(Value::from(10)));
+    assert_eq!(vm.get_global("method"), Some(Value::from("method".to_string())));
+    assert_eq!(vm.get_global("caught"), Some(Value::from("negative".to_string())));
+
+    let error = run_error("class A { get x { return 1; } }\nA().x = 2;");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ReadOnlyProperty("x".to_string(), 2)));
+}
+
+#[test]
+fn verify_private_members_are_rejected_outside_their_class() {
+    let error = run_error("class A { var #secret = 1; }\nprint A().#secret;");
+    assert_eq!(error.downcast_ref(), Some(&CompileError::Failed));
+
+    let error = run_error("class A { init() { return 1; } }");
+    assert_eq!(error.downcast_ref(), Some(&CompileError::Failed));
+
+    let error = run_error("var x = 1;\nclass A < x {}");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ExpectedSuperclass(2)));
+}
+
+#[test]
+fn verify_operators_fall_back_to_special_methods() {
+    let vm = run("class Money { init(cents) { this.cents = cents; } \
+         __add__(other) { return Money(this.cents + other.cents); } __sub__(other) { return Money(this.cents - other.cents); } \
+         __neg__() { return Money(-this.cents); } __eq__(other) { return this.cents == other.cents; } \
+         __lt__(other) { return this.cents < other.cents; } __index__(i) { return this.cents * i; } } \
+         var a = Money(150); var b = Money(250); var sum = (a + b).cents; var difference = (b - a).cents; \
+         var negated = (-a).cents; var equal = a + a == Money(300); var different = a != b; \
+         var less = a < b; var greater = a > b; var at_least = b >= a; var indexed = a[2];");
+
+    assert_eq!(vm.get_global("sum"), Some(Value::from(400)));
+    assert_eq!(vm.get_global("difference"), Some(Value::from(100)));
+    assert_eq!(vm.get_global("negated"), Some(Value::from(-150)));
+    assert_eq!(vm.get_global("equal"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("different"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("less"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("greater"), Some(Value::from(false)));
+    assert_eq!(vm.get_global("at_least"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("indexed"), Some(Value::from(300)));
+
+    let error = run_error("class A {}\nA() + A();");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ExpectedNumberOrString(2)));
+
+    let vm = run("class V { init(x) { this.x = x; } __radd__(other) { return V(other + this.x); } \
+         __rsub__(other) { return V(other - this.x); } __gt__(other) { return this.x > other; } __str__() { return format(\"V({})\", this.x); } } \
+         var added = (1 + V(2)).x; var subtracted = (10 - V(3)).x; var less = 1 < V(2); \
+         var nested = format(\"{}\", [V(1), {\"k\": V(2)}, \"s\"]); var cyclic = [1]; cyclic.push(cyclic); var cycle = format(\"{}\", cyclic);");
+    assert_eq!(vm.get_global("added"), Some(Value::from(3)));
+    assert_eq!(vm.get_global("subtracted"), Some(Value::from(7)));
+    assert_eq!(vm.get_global("less"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("nested"), Some(Value::from(r#"[V(1), {"k": V(2)}, "s"]"#.to_string())));
+    assert_eq!(vm.get_global("cycle"), Some(Value::from("[1, [...]]".to_string())));
+}
+
+#[test]
+fn verify_match_destructures_enum_variants() {
+    let vm = run("enum Shape { Circle(r), Rect(w, h), Empty } \
+         fun area(shape) { return match (shape) { Shape.Circle(r) => 3 * r * r, Shape.Rect(w, h) if w == h => -1, \
+         Shape.Rect(w, h) => w * h, Shape.Empty => 0 }; } \
+         var circle = area(Shape.Circle(2)); var square = area(Shape.Rect(3, 3)); var rect = area(Shape.Rect(3, 4)); \
+         var empty = area(Shape.Empty); var field = Shape.Rect(5, 6).h; var equal = Shape.Circle(1) == Shape.Circle(1); \
+         var printed = Shape.Rect(1, \"a\");");
+
+    assert_eq!(vm.get_global("circle"), Some(Value::from(12)));
+    assert_eq!(vm.get_global("square"), Some(Value::from(-1)));
+    assert_eq!(vm.get_global("rect"), Some(Value::from(12)));
+    assert_eq!(vm.get_global("empty"), Some(Value::from(0)));
+    assert_eq!(vm.get_global("field"), Some(Value::from(6)));
+    assert_eq!(vm.get_global("equal"), Some(Value::from(true)));
+    assert_eq!(vm.get_global("printed").unwrap().to_string(), "Shape.Rect(1, \"a\")");
+
+    let error = run_error("enum O { Some(x) } var m = {}; m[O.Some(math.NAN)] = 1;");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::UnhashableKey("O.Some(NaN)".to_string(), 1)));
+}
+
+#[test]
+fn verify_match_literals_bindings_and_wildcards() {
+    let vm = run(
+        "var offset = 10; fun classify(x) { return offset + match (x) { 0 => 1, -1 => 2, \"s\" => 3, nil => 4, \
+         n if n > 100 => n, _ => 5 }; } \
+         var zero = classify(0); var negative = classify(-1); var string = classify(\"s\"); var none = classify(nil); \
+         var large = classify(500); var other = classify(7);",
+    );
+
+    assert_eq!(vm.get_global("zero"), Some(Value::from(11)));
+    assert_eq!(vm.get_global("negative"), Some(Value::from(12)));
+    assert_eq!(vm.get_global("string"), Some(Value::from(13)));
+    assert_eq!(vm.get_global("none"), Some(Value::from(14)));
+    assert_eq!(vm.get_global("large"), Some(Value::from(510)));
+    assert_eq!(vm.get_global("other"), Some(Value::from(15)));
+
+    let error = run_error("enum Opt { Some(v), None }\nmatch (Opt.None) { Opt.Some(v) => v };");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::NoMatchingArm("Opt.None".to_string(), 2)));
+
+    let error = run_error("enum Opt { Some(v), None }\nmatch (1) { Opt.Some(a, b) => a };");
+    assert_eq!(error.downcast_ref(), Some(&CompileError::Failed));
+}
+
+#[test]
+fn verify_match_arms_run_inline() {
+    let vm = run(
+        "fun pick(o) { var a = 1; var picked = [a, 2 + match (o) { n if n > 1 => n, _ => 100 }, 3]; \
+         var read = match (o) { n => () => n }; return [picked, read()]; } \
+         var some = pick(5); var other = pick(0); \
+         fun steps() { var total = 0; while (total < 2) { total = total + match (total) { 0 => yield \"a\", _ => yield \"b\" }; } \
+         yield total; } var g = Fiber(steps); var first = g.resume(nil); var second = g.resume(5); \
+         fun risky() { var caught; try { var y = [1, match (3) { 4 => 0 }]; } catch (e) { caught = \"caught\"; } var z = 7; return [caught, z]; } \
+         var recovered = risky();",
+    );
+
+    assert_eq!(vm.get_global("some").unwrap().to_string(), "[[1, 7, 3], 5]");
+    assert_eq!(vm.get_global("other").unwrap().to_string(), "[[1, 102, 3], 0]");
+    assert_eq!(vm.get_global("first"), Some(Value::from("a".to_string())));
+    assert_eq!(vm.get_global("second"), Some(Value::from(5)));
+    assert_eq!(vm.get_global("recovered").unwrap().to_string(), "[\"caught\", 7]");
+}
+
+#[test]
+fn verify_destructuring_and_multiple_return_values() {
+    let vm = run("fun divmod(a, b) { return int(a / b), a - int(a / b) * b; } var q, r = divmod(7, 2); \
+         var [x, y] = [1, 2]; class Point { init(x, y) { this.x = x; this.y = y; } } var {x: px, y: py} = Point(5, 6); \
+         fun swap() { var [a, b] = [\"a\", \"b\"]; var {x} = Point(3, 4); [a, b] = [b, a]; return [a, b, x]; } \
+         var swapped = swap(); var m = 1; var n = 2; [m, n] = [n, m];");
+
+    assert_eq!(vm.get_global("q"), Some(Value::from(3)));
+    assert_eq!(vm.get_global("r"), Some(Value::from(1)));
+    assert_eq!(vm.get_global("x"), Some(Value::from(1)));
+    assert_eq!(vm.get_global("y"), Some(Value::from(2)));
+    assert_eq!(vm.get_global("px"), Some(Value::from(5)));
+    assert_eq!(vm.get_global("py"), Some(Value::from(6)));
+    assert_eq!(vm.get_global("swapped").unwrap().to_string(), "[\"b\", \"a\", 3]");
+    assert_eq!(vm.get_global("m"), Some(Value::from(2)));
+    assert_eq!(vm.get_global("n"), Some(Value::from(1)));
+
+    let error = run_error("var [a, b] = [1];");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::IndexOutOfBounds(1, 1, 1)));
+}
+
+#[test]
+fn verify_object_patterns_read_map_keys() {
+    let vm = run(
+        "var {x} = {\"x\": 1}; fun read() { var {name, age: years} = {\"name\": \"ada\", \"age\": 36}; return [name, years]; } \
+         var read_map = read();",
+    );
+
+    assert_eq!(vm.get_global("x"), Some(Value::from(1)));
+    assert_eq!(vm.get_global("read_map").unwrap().to_string(), "[\"ada\", 36]");
+
+    let error = run_error("var {missing} = {\"x\": 1};");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::UndefinedKey("\"missing\"".to_string(), 1)));
+}
+
+#[test]
+fn verify_constants_cant_be_assigned() {
+    let vm = run("const LIMIT = 10; let NAME = \"config\"; fun total() { const extra = 5; return LIMIT + extra; } var sum = total();");
+    assert_eq!(vm.get_global("sum"), Some(Value::from(15)));
+    assert_eq!(vm.get_global("NAME"), Some(Value::from("config".to_string())));
+
+    for source in [
+        "const A = 1;\nA = 2;",
+        "const A = 1;\nvar A = 2;",
+        "fun f() { const a = 1; return fun () { a = 2; }; }",
+    ] {
+        let error = run_error(source);
+        assert_eq!(error.downcast_ref(), Some(&CompileError::Failed));
+    }
+
+    // NOTE: Like in the REPL, where each line is compiled on its own.
+    let mut vm = VirtualMachine::initialize();
+    lox::interpret("const LIMIT = 10;", false, &mut vm).unwrap();
+    let error = lox::interpret("LIMIT = 20;", false, &mut vm).unwrap_err();
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::AssignToConstant("LIMIT".to_string(), 1)));
+    assert_eq!(vm.get_global("LIMIT"), Some(Value::from(10)));
+}
+
+#[test]
+fn verify_block_comments_nest() {
+    let vm =
+        run("/* outer /* inner */ still a comment */\n/// Adds two numbers.\nfun add(a, b) { return a /* inline */ + b; }\nvar sum = add(1, 2);");
+    assert_eq!(vm.get_global("sum"), Some(Value::from(3)));
+
+    let error = run_error("var a = 1;\n/* open /* nested */\nvar b = 2;");
+    assert_eq!(error.downcast_ref(), Some(&CompileError::Failed));
+}
+
+#[test]
+fn verify_math_module() {
+    let vm = run("var root = math.sqrt(16); var power = math.pow(2, 10); var low = math.min(3, 1.5, 2); var floored = math.floor(-2.5); var absolute = math.abs(-3); var nan = math.isnan(math.NAN);");
+    assert_eq!(vm.get_global("root"), Some(Value::from(4.0)));
+    assert_eq!(vm.get_global("power"), Some(Value::Int(1024)));
+    assert_eq!(vm.get_global("low"), Some(Value::from(1.5)));
+    assert_eq!(vm.get_global("floored"), Some(Value::from(-3.0)));
+    assert_eq!(vm.get_global("absolute"), Some(Value::Int(3)));
+    assert_eq!(vm.get_global("nan"), Some(Value::from(true)));
+
+    let error = run_error("math.sqrt(\"16\");");
+    let expected = RuntimeError::InvalidArgument("math.sqrt".to_string(), "a number".to_string(), 1);
+    assert_eq!(error.downcast_ref(), Some(&expected));
+
+    let error = run_error("math.pow(2, 64);");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::IntegerOverflow(1)));
+}
+
+#[test]
+fn verify_string_methods_count_characters() {
+    let vm = run(r#"
+        var word = "héllo";
+        var length = word.len();
+        var second = word[1];
+        var middle = word.substring(1, 3);
+        var position = word.find("llo");
+        var parts = "a,b,,c".split(",");
+        var joined = "-".join(parts);
+        var shout = " hi ".trim().upper().repeat(2);
+    "#);
+    assert_eq!(vm.get_global("length"), Some(Value::Int(5)));
+    assert_eq!(vm.get_global("second"), Some(Value::from("é".to_string())));
+    assert_eq!(vm.get_global("middle"), Some(Value::from("él".to_string())));
+    assert_eq!(vm.get_global("position"), Some(Value::Int(2)));
+    assert_eq!(vm.get_global("parts").unwrap().to_string(), "[\"a\", \"b\", \"\", \"c\"]");
+    assert_eq!(vm.get_global("joined"), Some(Value::from("a-b--c".to_string())));
+    assert_eq!(vm.get_global("shout"), Some(Value::from("HIHI".to_string())));
+
+    let error = run_error("\"héllo\"[5];");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::IndexOutOfBounds(5, 5, 1)));
+
+    let error = run_error("\"héllo\".substring(3, 1);");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::IndexOutOfBounds(3, 1, 1)));
+
+    let error = run_error("\"ab\".repeat(9223372036854775807);");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::StringTooLong(1 << 30, 1)));
+}
+
+#[test]
+fn verify_format_specifiers() {
+    let vm = run(r#"
+        var total = format("{} has {:.2} points", "Ann", 3.14159);
+        var aligned = format("[{:>6}|{:<4}|{:*^5}]", 42, "ab", "x");
+        var padded = format("{:06.2} {{}} {1} {0}", -3.14159, 7);
+    "#);
+    assert_eq!(vm.get_global("total"), Some(Value::from("Ann has 3.14 points".to_string())));
+    assert_eq!(vm.get_global("aligned"), Some(Value::from("[    42|ab  |**x**]".to_string())));
+    assert_eq!(vm.get_global("padded"), Some(Value::from("-03.14 {} 7 -3.14159".to_string())));
+
+    let error = run_error("format(\"{} and {}\", 1);");
+    let expected = RuntimeError::InvalidFormat("no argument for placeholder 1".to_string(), 1);
+    assert_eq!(error.downcast_ref(), Some(&expected));
+
+    for spec in [":.18446744073709551615", ":9000000000000000000", ":65536"] {
+        let error = run_error(&format!("format(\"{{{spec}}}\", 1.5);"));
+        let expected = RuntimeError::InvalidFormat(format!("invalid placeholder '{{{spec}}}'"), 1);
+        assert_eq!(error.downcast_ref(), Some(&expected));
+    }
+}
+
+#[test]
+fn verify_io_follows_the_file_policy() {
+    let directory = write_modules("io", &[("data/notes.txt", "first\nsecond\n")]);
+    let data = directory.join("data").canonicalize().unwrap();
+    let source = format!(
+        r#"
+        var dir = "{}";
+        io.write_file(dir + "/out.txt", "a");
+        io.append(dir + "/out.txt", "b");
+        var written = io.read_file(dir + "/out.txt");
+        var lines = "";
+        for (line in io.lines(dir + "/notes.txt")) lines = lines + line + ";";
+        var entries = io.list_dir(dir);
+        var missing = io.exists(dir + "/missing.txt");
+        var escaped = nil;
+        try {{ io.read_file(dir + "/../notes.txt"); }} catch (error) {{ escaped = error; }}
+        "#,
+        data.display()
+    );
+
+    let mut vm = VirtualMachine::initialize();
+    vm.set_file_policy(FilePolicy::default().allow(&data));
+    lox::interpret(&source, false, &mut vm).unwrap();
+    assert_eq!(vm.get_global("written"), Some(Value::from("ab".to_string())));
+    assert_eq!(vm.get_global("lines"), Some(Value::from("first;second;".to_string())));
+    assert_eq!(vm.get_global("entries").unwrap().to_string(), r#"["notes.txt", "out.txt"]"#);
+    assert_eq!(vm.get_global("missing"), Some(Value::from(false)));
+    assert_ne!(vm.get_global("escaped"), Some(Value::Nil));
+
+    let mut vm = VirtualMachine::initialize();
+    vm.set_file_policy(FilePolicy::default().allow(&data).read_only());
+    let error = lox::interpret(&format!(r#"io.write_file("{}/out.txt", "c");"#, data.display()), false, &mut vm).unwrap_err();
+    assert!(matches!(error.downcast_ref(), Some(RuntimeError::FileAccessDenied(_, 1))));
+
+    let error = run_error(&format!(r#"io.read_file("{}/notes.txt");"#, data.display()));
+    assert!(matches!(error.downcast_ref(), Some(RuntimeError::FileAccessDenied(_, 1))));
+}
+
+#[cfg(unix)]
+#[test]
+fn verify_io_rejects_dangling_symlinks() {
+    let directory = write_modules("io-symlink", &[("data/notes.txt", "")]);
+    let data = directory.join("data").canonicalize().unwrap();
+    let outside = directory.join("outside.txt");
+    let _ = std::fs::remove_file(data.join("link.txt"));
+    std::os::unix::fs::symlink(&outside, data.join("link.txt")).unwrap();
+
+    let mut vm = VirtualMachine::initialize();
+    vm.set_file_policy(FilePolicy::default().allow(&data));
+    let error = lox::interpret(&format!(r#"io.write_file("{}/link.txt", "x");"#, data.display()), false, &mut vm).unwrap_err();
+    assert!(matches!(error.downcast_ref(), Some(RuntimeError::FileAccessDenied(_, 1))));
+    assert!(!outside.exists());
+}
+
+#[test]
+fn verify_json_round_trips_values() {
+    let vm = run(r#"
+        class Point { init(x, y) { this.x = x; this.y = y; } }
+        var data = {"ints": [1, -2], "floats": [2.0, 0.5, 1e21], "flags": [true, nil], "point": Point(1, 2.5)};
+        var text = json.stringify(data);
+        var same = json.stringify(json.parse(text)) == text;
+        var pretty = json.stringify([1, {"a": []}], 2);
+    "#);
+    let expected = r#"{"ints":[1,-2],"floats":[2.0,0.5,1e21],"flags":[true,null],"point":{"x":1,"y":2.5}}"#;
+    assert_eq!(vm.get_global("text"), Some(Value::from(expected.to_string())));
+    assert_eq!(vm.get_global("same"), Some(Value::from(true)));
+    assert_eq!(
+        vm.get_global("pretty"),
+        Some(Value::from("[\n  1,\n  {\n    \"a\": []\n  }\n]".to_string()))
+    );
+
+    let error = run_error("var l = [1]; l[0] = [l]; json.stringify(l);");
+    let expected = RuntimeError::JsonUnsupported("a cyclic structure".to_string(), 1);
+    assert_eq!(error.downcast_ref(), Some(&expected));
+
+    let vm = run("class Account { var #balance = 10; init(owner) { this.owner = owner; } } var account = json.stringify(Account(\"ann\"));");
+    assert_eq!(vm.get_global("account"), Some(Value::from(r#"{"owner":"ann"}"#.to_string())));
+
+    let error = run_error("var deep = []; var depth = 0; while (depth < 1000) { deep = [deep]; depth = depth + 1; } json.stringify(deep);");
+    let expected = RuntimeError::JsonUnsupported("a structure nested too deeply".to_string(), 1);
+    assert_eq!(error.downcast_ref(), Some(&expected));
+
+    for indent in ["11", "9223372036854775807", "-1"] {
+        let error = run_error(&format!("json.stringify([[1]], {indent});"));
+        let expected = RuntimeError::InvalidArgument("json.stringify".to_string(), "an integer indent from 0 to 10".to_string(), 1);
+        assert_eq!(error.downcast_ref(), Some(&expected));
+    }
+}
+
+#[test]
+fn verify_json_parse_errors_report_position() {
+    let mut vm = VirtualMachine::initialize();
+    vm.define_native("document", 0, |_, _| Ok(Value::from("{\n  \"a\": [1, 2,]\n}".to_string())));
+
+    let error = lox::interpret("json.parse(document());", false, &mut vm).unwrap_err();
+    let expected = RuntimeError::InvalidJson("unexpected ']'".to_string(), 2, 14, 1);
+    assert_eq!(error.downcast_ref(), Some(&expected));
+}
+
+#[test]
+fn verify_seeded_random_is_reproducible() {
+    let source = r#"
+        random.seed(42);
+        var first = random.random();
+        var roll = random.randint(1, 6);
+        var deck = [1, 2, 3, 4, 5];
+        random.shuffle(deck);
+        var picked = random.choice(deck);
+    "#;
+    let vm = run(source);
+    assert_eq!(vm.get_global("first"), Some(Value::from(0.08386297105988216)));
+    assert_eq!(vm.get_global("roll"), Some(Value::Int(1)));
+    assert_eq!(vm.get_global("deck").unwrap().to_string(), "[3, 1, 4, 2, 5]");
+    assert_eq!(vm.get_global("picked"), Some(Value::Int(5)));
+
+    let error = run_error("random.choice([]);");
+    let expected = RuntimeError::InvalidArgument("random.choice".to_string(), "a non-empty list".to_string(), 1);
+    assert_eq!(error.downcast_ref(), Some(&expected));
+}
+
+#[test]
+fn verify_clock_is_monotonic() {
+    let vm = run("var start = clock(); time.sleep(5); var elapsed = clock() - start; var epoch = time.now();");
+    assert!(vm.get_global("elapsed").and_then(|elapsed| elapsed.as_f64()).unwrap() >= 0.005);
+    assert!(vm.get_global("epoch").and_then(|epoch| epoch.as_f64()).unwrap() > 1.7e9);
+
+    for milliseconds in ["-1", "math.sqrt(-1)", "1e300"] {
+        let error = run_error(&format!("time.sleep({milliseconds});"));
+        let expected = RuntimeError::InvalidArgument("time.sleep".to_string(), "a non-negative number of milliseconds".to_string(), 1);
+        assert_eq!(error.downcast_ref(), Some(&expected));
+    }
+}
+
+#[test]
+fn verify_script_arguments_and_exit() {
+    let mut vm = VirtualMachine::initialize();
+    vm.set_env_policy(EnvPolicy::default().allow("PATH").allow("LOX_UNSET_VARIABLE"));
+    vm.set_arguments(vec!["input.txt".to_string(), "--verbose".to_string()]);
+    let source = "#!/usr/bin/env lox\nvar first = args[0]; var path = env.get(\"PATH\"); var missing = env.get(\"LOX_UNSET_VARIABLE\");";
+    lox::interpret(source, false, &mut vm).unwrap();
+    assert_eq!(vm.get_global("first"), Some(Value::from("input.txt".to_string())));
+    assert_eq!(vm.get_global("path"), Some(Value::from(std::env::var("PATH").unwrap())));
+    assert_eq!(vm.get_global("missing"), Some(Value::Nil));
+
+    let error = run_error("var caught = false; try { exit(3); } catch (error) { caught = true; }");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::Exit(3)));
+
+    let error = run_error("exit(256);");
+    let expected = RuntimeError::InvalidArgument("exit".to_string(), "an exit code from 0 to 255".to_string(), 1);
+    assert_eq!(error.downcast_ref(), Some(&expected));
+
+    let error = run_error("env.get(\"PATH\");");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::EnvAccessDenied("PATH".to_string(), 1)));
+}
+
+#[test]
+fn verify_prelude_functions() {
+    let vm = run(r#"
+        var doubled = map(range(1, 4), fun (x) { return x * 2; });
+        var large = filter([5, 12, 8, 20], fun (x) { return x > 10; });
+        var total = reduce(range(5), fun (sum, x) { return sum + x; }, 0);
+        var down = range(5, 0, -2);
+        var types = [type_of(1), type_of(1.5), type_of("s"), type_of(nil), type_of(map)];
+        var count = doubled.len();
+    "#);
+    assert_eq!(vm.get_global("doubled").unwrap().to_string(), "[2, 4, 6]");
+    assert_eq!(vm.get_global("large").unwrap().to_string(), "[12, 20]");
+    assert_eq!(vm.get_global("total"), Some(Value::Int(10)));
+    assert_eq!(vm.get_global("down").unwrap().to_string(), "[5, 3, 1]");
+    assert_eq!(
+        vm.get_global("types").unwrap().to_string(),
+        r#"["int", "float", "string", "nil", "function"]"#
+    );
+    assert_eq!(vm.get_global("count"), Some(Value::Int(3)));
+
+    let error = run_error("assert(1 > 2, \"math is broken\");");
+    assert!(error.to_string().contains("math is broken"));
+
+    let mut vm = VirtualMachine::without_prelude();
+    let error = lox::interpret("map([1], fun (x) { return x; });", false, &mut vm).unwrap_err();
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::UndefinedVariable("map".to_string(), 1)));
+}
+
+#[test]
+fn verify_reflection_functions() {
+    let vm = run(r#"
+        class Animal { init(name) { this.name = name; } speak() { return "..."; } }
+        class Dog < Animal { init(name, age) { super.init(name); this.age = age; } bark() { return "woof"; } }
+        var dog = Dog("rex", 3);
+        var names = fields(dog);
+        var found = [has_field(dog, "age"), has_field(dog, "bark")];
+        var owner = get_field(dog, "name");
+        set_field(dog, "bark", fun () { return "mocked"; });
+        var barked = dog.bark();
+        var same = class_of(dog) == Dog and superclass(Dog) == Animal and superclass(Animal) == nil;
+        var listed = methods(Dog);
+        var described = [name(Dog), name(dog.speak), arity(Dog), arity(fun (a) { return a; })];
+    "#);
+    assert_eq!(vm.get_global("names").unwrap().to_string(), r#"["age", "name"]"#);
+    assert_eq!(vm.get_global("found").unwrap().to_string(), "[true, false]");
+    assert_eq!(vm.get_global("owner"), Some(Value::from("rex".to_string())));
+    assert_eq!(vm.get_global("barked"), Some(Value::from("mocked".to_string())));
+    assert_eq!(vm.get_global("same"), Some(Value::Bool(true)));
+    assert_eq!(vm.get_global("listed").unwrap().to_string(), r#"["bark", "init", "speak"]"#);
+    assert_eq!(vm.get_global("described").unwrap().to_string(), r#"["Dog", "speak", 2, 1]"#);
+}
+
+#[test]
+fn verify_reflection_hides_private_members() {
+    let vm = run(r#"
+        class Account { init() { this.#balance = 10; this.owner = "ann"; } #audit() {} deposit() {} }
+        var account = Account();
+        var names = fields(account);
+        var listed = methods(Account);
+    "#);
+    assert_eq!(vm.get_global("names").unwrap().to_string(), r#"["owner"]"#);
+    assert_eq!(vm.get_global("listed").unwrap().to_string(), r#"["deposit", "init"]"#);
+
+    for (name, call) in [("get_field", ""), ("set_field", ", 0"), ("has_field", "")] {
+        let error = run_error(&format!(
+            "class Account {{ init() {{ this.#balance = 10; }} }} {name}(Account(), \"Account#balance\"{call});"
+        ));
+        let expected = RuntimeError::InvalidArgument(name.to_string(), "a public field name".to_string(), 1);
+        assert_eq!(error.downcast_ref(), Some(&expected));
+    }
+}
+
+#[test]
+fn verify_type_names_in_errors() {
+    let error = run_error("var x = nil; x();");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ExpectedCallable("nil", 1)));
+
+    let error = run_error("var x = 1.5; print x[0];");
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::ExpectedIndexable("float", 1)));
+}
+
+#[test]
+fn verify_regex_methods() {
+    let vm = run(r#"
+        var line = re.compile("(?<date>\d{4}-\d\d-\d\d) (\w+): (.*)");
+        var matched = line.match("2024-01-05 ERROR: disk full");
+        var anchored = line.match("> 2024-01-05 ERROR: disk full");
+        var named = line.captures("> 2024-01-05 WARN: low memory")["date"];
+        var numbers = re.compile("\d+").find_all("a1 b22 c333");
+        var pairs = re.compile("(\w)=(\d)").find_all("a=1, b=2");
+        var swapped = re.compile("(?<key>\w+)@(\w+)").replace("joe@host", "$2 at ${key} $$");
+        var doubled = re.compile("\d+").replace("a1 b22", fun (m) { return int(m[0]) * 2; });
+        var parts = re.compile(",\s*").split("a, b,c,   d");
+        var lazy = re.compile("<.+?>").search("x <a> <b>");
+    "#);
+    assert_eq!(
+        vm.get_global("matched").unwrap().to_string(),
+        r#"["2024-01-05 ERROR: disk full", "2024-01-05", "ERROR", "disk full"]"#
+    );
+    assert_eq!(vm.get_global("anchored"), Some(Value::Nil));
+    assert_eq!(vm.get_global("named"), Some(Value::from("2024-01-05".to_string())));
+    assert_eq!(vm.get_global("numbers").unwrap().to_string(), r#"["1", "22", "333"]"#);
+    assert_eq!(vm.get_global("pairs").unwrap().to_string(), r#"[["a=1", "a", "1"], ["b=2", "b", "2"]]"#);
+    assert_eq!(vm.get_global("swapped"), Some(Value::from("host at joe $".to_string())));
+    assert_eq!(vm.get_global("doubled"), Some(Value::from("a2 b44".to_string())));
+    assert_eq!(vm.get_global("parts").unwrap().to_string(), r#"["a", "b", "c", "d"]"#);
+    assert_eq!(vm.get_global("lazy").unwrap().to_string(), r#"["<a>"]"#);
+}
+
+#[test]
+fn verify_regex_without_backtracking() {
+    let vm = run(r#"
+        var text = "";
+        var count = 0;
+        while (count < 5000) { text = text + "a"; count = count + 1; }
+        var result = re.compile("(a*)*(a|aa)*b").search(text);
+    "#);
+    assert_eq!(vm.get_global("result"), Some(Value::Nil));
+
+    let error = run_error(r#"re.compile("(ab");"#);
+    assert_eq!(
+        error.downcast_ref(),
+        Some(&RuntimeError::InvalidRegex("missing ')' for the group at position 0".to_string(), 1))
+    );
+}
+
+#[test]
+fn verify_regex_assertions_after_the_start() {
+    let vm = run(r#"
+        var level = re.compile("\bERROR\b").search("id=7 ERROR x");
+        var word = re.compile("\bfoo").find_all("xfoo foo");
+        var end = re.compile("$").search("ab");
+        var first = re.compile("^a").find_all("aa");
+        var whole = re.compile("\bfoo\b").find_all("foo foobar foo");
+    "#);
+    assert_eq!(vm.get_global("level").unwrap().to_string(), r#"["ERROR"]"#);
+    assert_eq!(vm.get_global("word").unwrap().to_string(), r#"["foo"]"#);
+    assert_eq!(vm.get_global("end").unwrap().to_string(), r#"[""]"#);
+    assert_eq!(vm.get_global("first").unwrap().to_string(), r#"["a"]"#);
+    assert_eq!(vm.get_global("whole").unwrap().to_string(), r#"["foo", "foo"]"#);
+}
+
+#[test]
+fn verify_set_operations() {
+    let vm = run(r#"
+        var seen = Set([1, 2, 2, 3]);
+        seen.add(4);
+        seen.add(1);
+        var removed = seen.remove(2);
+        var members = [seen.has(1), seen.has(2), seen.len()];
+        var union = seen.union([5, 1]);
+        var common = seen.intersection(Set([3, 4, 9]));
+        var rest = seen.difference([1]);
+        var total = 0;
+        for (item in seen) total = total + item;
+    "#);
+    assert_eq!(vm.get_global("removed"), Some(Value::Bool(true)));
+    assert_eq!(vm.get_global("members").unwrap().to_string(), "[true, false, 3]");
+    assert_eq!(vm.get_global("union").unwrap().to_string(), "Set([1, 3, 4, 5])");
+    assert_eq!(vm.get_global("common").unwrap().to_string(), "Set([3, 4])");
+    assert_eq!(vm.get_global("rest").unwrap().to_string(), "Set([3, 4])");
+    assert_eq!(vm.get_global("total"), Some(Value::Int(8)));
+}
+
+#[test]
+fn verify_collection_functions() {
+    let vm = run(r#"
+        var people = [["bob", 30], ["al", 25], ["cy", 30], ["di", 25]];
+        var natural = sort([3, 1.5, 2, -1]);
+        var by_key = sort(people, fun (person) { return person[1]; });
+        var by_comparator = sort(people, fun (a, b) { return b[1] - a[1]; });
+        var by_defaulted_key = sort([3, 1, 2], fun (a, b = 0) { return -a; });
+        var by_rest_key = sort([3, 1, 2], fun (...values) { return values[0]; });
+        var reversed = reverse([1, 2, 3]);
+        var zipped = zip([1, 2, 3], ["a", "b"]);
+        var numbered = enumerate(["x", "y"]);
+        var sums = [sum([1, 2, 3]), sum([1, 2.5]), sum([])];
+        var tests = [any([1, 2], fun (x) { return x > 1; }), all([1, 2], fun (x) { return x > 1; }), any([nil, false])];
+        var distinct = unique([3, 1, 3, 2, 1]);
+    "#);
+    assert_eq!(vm.get_global("natural").unwrap().to_string(), "[-1, 1.5, 2, 3]");
+    assert_eq!(
+        vm.get_global("by_key").unwrap().to_string(),
+        r#"[["al", 25], ["di", 25], ["bob", 30], ["cy", 30]]"#
+    );
+    assert_eq!(
+        vm.get_global("by_comparator").unwrap().to_string(),
+        r#"[["bob", 30], ["cy", 30], ["al", 25], ["di", 25]]"#
+    );
+    assert_eq!(vm.get_global("by_defaulted_key").unwrap().to_string(), "[3, 2, 1]");
+    assert_eq!(vm.get_global("by_rest_key").unwrap().to_string(), "[1, 2, 3]");
+    assert_eq!(vm.get_global("reversed").unwrap().to_string(), "[3, 2, 1]");
+    assert_eq!(vm.get_global("zipped").unwrap().to_string(), r#"[[1, "a"], [2, "b"]]"#);
+    assert_eq!(vm.get_global("numbered").unwrap().to_string(), r#"[[0, "x"], [1, "y"]]"#);
+    assert_eq!(vm.get_global("sums").unwrap().to_string(), "[6, 3.5, 0]");
+    assert_eq!(vm.get_global("tests").unwrap().to_string(), "[true, false, false]");
+    assert_eq!(vm.get_global("distinct").unwrap().to_string(), "[3, 1, 2]");
+
+    let error = run_error(r#"sort([1, "a"]);"#);
+    assert_eq!(error.downcast_ref(), Some(&RuntimeError::Incomparable("string", "int", 1)));
+}
+
+#[test]
+fn verify_call_value_from_rust() {
+    let vm = &mut VirtualMachine::initialize();
+    lox::interpret("fun apply(f, x) { return f(x) + 1; }", false, vm).unwrap();
+
+    let apply = vm.get_global("apply").unwrap();
+    let sum = vm.get_global("sum").unwrap();
+    let result = vm.call_value(apply, vec![sum, vec![Value::Int(2), Value::Int(3)].into()]).unwrap();
+    assert_eq!(result, Value::Int(6));
+}